@@ -1016,6 +1016,52 @@ fn benchmark_tfhe_encrypted_modulus(c: &mut Criterion) {
     c.bench_function("tfhe_encrypted_modulus", |b| b.iter(tfhe_encrypted_modulus));
 }
 
+type GatewayCircuitOp = fn(
+    &mut compute::operations::circuits::builder::CircuitBuilder,
+    &compute::operations::circuits::types::GateIndexVec,
+    &compute::operations::circuits::types::GateIndexVec,
+) -> compute::operations::circuits::types::GateIndexVec;
+
+// Builds and compiles a 32-bit circuit for the given operation, returning its gate counts
+fn gateway_compile_stats(
+    op: GatewayCircuitOp,
+) -> compute::operations::circuits::optimizer::OptimizationStats {
+    use compute::operations::circuits::builder::CircuitBuilder;
+    use compute::uint::GarbledUint32;
+
+    let mut builder = CircuitBuilder::default();
+    let a = builder.input(&GarbledUint32::from(12297829u32));
+    let b = builder.input(&GarbledUint32::from(4242u32));
+
+    let output = op(&mut builder, &a, &b);
+    let (_, stats) = builder.compile_with_stats(&output);
+    stats
+}
+
+// Benchmark 39: Benchmarking benchmark_gateway_and_gate_counts
+// AND gates dominate garbling cost since XOR and NOT gates are free,
+// so the counts are reported alongside the compile time
+fn benchmark_gateway_and_gate_counts(c: &mut Criterion) {
+    let ops: [(&str, GatewayCircuitOp); 6] = [
+        ("mux", |builder, a, b| {
+            let s = builder.lt(a, b);
+            builder.mux(&s, a, b)
+        }),
+        ("lt", |builder, a, b| vec![builder.lt(a, b)].into()),
+        ("le", |builder, a, b| vec![builder.le(a, b)].into()),
+        ("addition", |builder, a, b| builder.add(a, b)),
+        ("multiplication", |builder, a, b| builder.mul(a, b)),
+        ("division", |builder, a, b| builder.div(a, b)),
+    ];
+
+    for (name, op) in ops {
+        println!("gateway_and_gates_{}: {}", name, gateway_compile_stats(op));
+        c.bench_function(&format!("gateway_compile_{}", name), |b| {
+            b.iter(|| gateway_compile_stats(op))
+        });
+    }
+}
+
 // Configure Criterion with a sample size of 10
 fn custom_criterion() -> Criterion {
     Criterion::default().sample_size(10)
@@ -1064,5 +1110,6 @@ criterion_group!(
         benchmark_tfhe_encrypted_modulus,
         benchmark_gateway_encrypted_mux,
         benchmark_tfhe_encrypted_mux,
+        benchmark_gateway_and_gate_counts,
 );
 criterion_main!(benches);
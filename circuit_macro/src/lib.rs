@@ -1,827 +1,1882 @@
 extern crate proc_macro;
-use core::panic;
 
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
-use std::collections::HashSet;
+use proc_macro2::{Literal, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use syn::parse::{Parse, ParseStream};
 use syn::{
-    parse_macro_input, BinOp, Expr, ExprAssign, ExprBinary, ExprBlock, ExprIf, ExprLet, ExprMatch,
-    ExprReference, ExprUnary, FnArg, ItemFn, Lit, Pat, PatType,
+    parse_macro_input, BinOp, Block, Expr, ExprBinary, ExprCast, ExprForLoop, ExprIf, ExprIndex,
+    ExprMatch, ExprMethodCall, ExprRepeat, ExprUnary, FnArg, Ident, ItemFn, Lit, LitInt, Local,
+    Pat, PatType, RangeLimits, ReturnType, Stmt, Token, UnOp,
 };
 
+/// Number of loop iterations an encrypted function may unroll unless its attribute sets `unroll`.
+const DEFAULT_UNROLL_LIMIT: usize = 1024;
+
 #[proc_macro_attribute]
 pub fn encrypted(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let mode = parse_macro_input!(attr as syn::Ident).to_string(); // Retrieve the mode (e.g., "compile" or "execute")
-    generate_macro(item, &mode)
+    let options = parse_macro_input!(attr as Options); // Retrieve the mode (e.g., "compile" or "execute")
+    generate_macro(item, &options)
+}
+
+/// The arguments of the attribute: the mode, then optional settings such as `unroll = 4096`.
+struct Options {
+    mode: String,
+    /// Total number of loop iterations the body may unroll into.
+    unroll_limit: usize,
+}
+
+impl Parse for Options {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mode: Ident = input.parse()?;
+        if mode != "compile" && mode != "execute" {
+            return Err(syn::Error::new_spanned(
+                mode,
+                "expected `execute` or `compile`",
+            ));
+        }
+
+        let mut unroll_limit = DEFAULT_UNROLL_LIMIT;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            if key != "unroll" {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    "unknown option, expected `unroll`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            unroll_limit = input.parse::<LitInt>()?.base10_parse()?;
+        }
+
+        Ok(Options {
+            mode: mode.to_string(),
+            unroll_limit,
+        })
+    }
 }
 
 /// Generates the macro code based on the mode (either "compile" or "execute")
-fn generate_macro(item: TokenStream, mode: &str) -> TokenStream {
-    let input_fn = parse_macro_input!(item as ItemFn);
+fn generate_macro(item: TokenStream, options: &Options) -> TokenStream {
+    let mut input_fn = parse_macro_input!(item as ItemFn);
+
+    // Parameters tagged with `#[evaluator]` are provided by the evaluator instead of the garbler.
+    // The attribute is only meaningful to this macro, so strip it from the emitted signature.
+    let evaluator_inputs: HashSet<String> = input_fn
+        .sig
+        .inputs
+        .iter_mut()
+        .filter_map(|input| {
+            if let FnArg::Typed(PatType { attrs, pat, .. }) = input {
+                let len = attrs.len();
+                attrs.retain(|attr| !attr.path().is_ident("evaluator"));
+                if attrs.len() != len {
+                    if let Pat::Ident(pat_ident) = &**pat {
+                        return Some(pat_ident.ident.to_string());
+                    }
+                }
+            }
+            None
+        })
+        .collect();
+
+    match expand(&input_fn, &evaluator_inputs, options) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(error) => TokenStream::from(error.to_compile_error()),
+    }
+}
+
+fn expand(
+    input_fn: &ItemFn,
+    evaluator_inputs: &HashSet<String>,
+    options: &Options,
+) -> syn::Result<TokenStream2> {
+    let mode = options.mode.as_str();
     let fn_name = &input_fn.sig.ident; // Function name
+    let fn_vis = &input_fn.vis; // Function visibility
     let inputs = &input_fn.sig.inputs; // Function input parameters
 
-    // get the type of the first input parameter
-    let type_name = if let FnArg::Typed(PatType { ty, .. }) = &inputs[0] {
-        quote! {#ty}
-    } else {
-        panic!("Expected typed argument");
+    let ReturnType::Type(_, return_type) = &input_fn.sig.output else {
+        return Err(syn::Error::new_spanned(
+            &input_fn.sig,
+            "encrypted functions must declare their return type",
+        ));
     };
 
-    // get the type of the first output parameter
-    let output_type = if let syn::ReturnType::Type(_, ty) = &input_fn.sig.output {
-        quote! {#ty}
-    } else {
-        panic!("Expected typed return type");
-    };
+    // A parameter or return type that is not a primitive is a type parameter, whose width is
+    // only known once the function is called
+    let generic = generic_parameter(input_fn)?;
 
-    // We need to extract each input's identifier
-    let mapped_inputs = inputs.iter().map(|input| {
-        if let FnArg::Typed(PatType { pat, .. }) = input {
-            if let Pat::Ident(pat_ident) = &**pat {
-                let var_name = &pat_ident.ident;
-                quote! {
-                    let #var_name = &context.input(&#var_name.clone().into());
-                }
-            } else {
-                quote! {}
-            }
-        } else {
-            quote! {}
-        }
-    });
+    let mut params = Vec::new();
+    for input in inputs {
+        let FnArg::Typed(PatType { pat, ty, .. }) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "encrypted functions cannot take `self`",
+            ));
+        };
+        let Pat::Ident(pat_ident) = &**pat else {
+            return Err(syn::Error::new_spanned(
+                pat,
+                "unsupported parameter pattern, expected an identifier",
+            ));
+        };
+        let shape = Shape::parse(ty, generic.as_ref()).ok_or_else(|| {
+            syn::Error::new_spanned(
+                ty,
+                "unsupported parameter type, expected `bool`, an integer type such as `u8` or \
+                 `i32`, a type parameter or an array of them",
+            )
+        })?;
+        params.push((pat_ident.ident.clone(), shape));
+    }
 
-    // Extract constants to be added at the top of the function
-    let mut constants = vec![];
-    let transformed_block = modify_body(*input_fn.block, &mut constants);
+    // Compiled circuits may declare any return type, as the function returns the circuit instead
+    let output_shape = Shape::parse(return_type, generic.as_ref());
+    if output_shape.is_none() && mode != "compile" {
+        return Err(syn::Error::new_spanned(
+            return_type,
+            "unsupported return type, expected `bool`, an integer type such as `u8` or `i32`, a \
+             type parameter or an array of them",
+        ));
+    }
 
-    // remove duplicates
-    let mut seen = HashSet::new();
-    let constants: Vec<proc_macro2::TokenStream> = constants
-        .into_iter()
-        .filter(|item| seen.insert(item.to_string()))
-        .collect();
+    // Integer literals that never meet a typed value take the type of the returned integer, or
+    // else the type of the widest parameter
+    let default_type = match output_shape.map(|shape| shape.element()) {
+        Some(ty) if ty.is_integer() => ty,
+        _ => params
+            .iter()
+            .map(|(_, shape)| match shape.element() {
+                Type::Bool => Type::Uint(Width::Fixed(1)),
+                ty => ty,
+            })
+            .reduce(|a, b| match (a.width(), b.width()) {
+                (Width::Fixed(x), Width::Fixed(y)) if y > x => b,
+                (Width::Fixed(_), Width::Fixed(_)) => a,
+                _ => Type::Uint(Width::Generic),
+            })
+            .unwrap_or(Type::Uint(Width::Fixed(1))),
+    };
 
-    // Collect parameter names dynamically
-    let param_names: Vec<_> = inputs
-        .iter()
-        .map(|input| {
-            if let FnArg::Typed(PatType { pat, .. }) = input {
-                if let Pat::Ident(pat_ident) = &**pat {
-                    pat_ident.ident.clone()
-                } else {
-                    panic!("Expected identifier pattern");
+    let mut translator = Translator::new(default_type, generic.clone(), options.unroll_limit);
+    // Inputs are flattened in the order of the parameters, the elements of an array from index 0
+    for (name, shape) in &params {
+        let evaluator = evaluator_inputs.contains(&name.to_string());
+        let value = match *shape {
+            Shape::Scalar(ty) => translator.input(quote! { #name }, ty, evaluator),
+            Shape::Array(ty, len) => {
+                let mut elements = Vec::with_capacity(len);
+                for i in 0..len {
+                    let i = Literal::usize_unsuffixed(i);
+                    elements.push(translator.input(quote! { #name[#i] }, ty, evaluator));
                 }
-            } else {
-                panic!("Expected typed argument");
+                Value::Array(elements)
             }
-        })
-        .collect();
+        };
+        translator.bind(name, value, Some(shape.element()));
+    }
 
-    // Dynamically generate the `generate` function calls using the parameter names
-    let match_arms = quote! {
-        match std::any::type_name::<#type_name>() {
-            "bool" => generate::<1, #type_name>(#(#param_names),*),
-            "u8" => generate::<8, #type_name>(#(#param_names),*),
-            "u16" => generate::<16, #type_name>(#(#param_names),*),
-            "u32" => generate::<32, #type_name>(#(#param_names),*),
-            "u64" => generate::<64, #type_name>(#(#param_names),*),
-            "u128" => generate::<128, #type_name>(#(#param_names),*),
-            _ => panic!("Unsupported type"),
-        }
+    let value = translator.block(&input_fn.block)?;
+    let site = match input_fn.block.stmts.last() {
+        Some(stmt) => Site::of(stmt),
+        None => Site::of(&input_fn.block),
+    };
+    let value = match output_shape {
+        Some(shape) => translator.conform(value, shape, site)?,
+        None => value,
     };
+    let output = translator.flatten(value, site)?;
+    let stmts = &translator.stmts;
+    let values = translator.vars;
 
     // Set the output type and operation logic based on mode
     let output_type = if mode == "compile" {
-        quote! {(Circuit, Vec<bool>)}
+        quote! {(Circuit, Vec<bool>, Vec<bool>)}
     } else {
-        quote! {#output_type}
+        quote! {#return_type}
     };
 
     let operation = if mode == "compile" {
         quote! {
-            (context.compile(&output), context.inputs().to_vec())
+            (
+                context.compile(&#output),
+                context.inputs().to_vec(),
+                context.eval_inputs().to_vec(),
+            )
         }
     } else {
-        quote! {
-            let compiled_circuit = context.compile(&output.into());
-            let result = context.execute::<N>(&compiled_circuit).expect("Execution failed");
-            result.into()
+        let shape = output_shape.expect("checked above");
+        let ty = shape.element();
+        let width = ty.width();
+        let result = match (ty, &generic) {
+            (Type::Int(_), _) => quote! { GarbledInt::<#width>::from(result).into() },
+            (Type::Uint(Width::Generic), Some(generic)) => quote! { #generic::decode(result) },
+            _ => quote! { result.into() },
+        };
+        match shape {
+            Shape::Scalar(_) => quote! {
+                let compiled_circuit = context.compile(&#output);
+                let result = context.execute::<#width>(&compiled_circuit).expect("Execution failed");
+                #result
+            },
+            // the output holds the elements one after another
+            Shape::Array(..) => quote! {
+                let compiled_circuit = context.compile(&#output);
+                let bits = get_executor()
+                    .execute(&compiled_circuit, context.inputs(), context.eval_inputs())
+                    .expect("Execution failed");
+                std::array::from_fn(|i| {
+                    let result = GarbledUint::<#width>::new(bits[i * #width..(i + 1) * #width].to_vec());
+                    #result
+                })
+            },
         }
     };
 
-    // Build the function body with circuit context, compile, and execute
-    let expanded = quote! {
-        #[allow(non_camel_case_types, non_snake_case, clippy::builtin_type_shadow, unused_assignments)]
-        fn #fn_name<#type_name>(#inputs) -> #output_type
-        where
-        #type_name: Into<GarbledUint<1>> + From<GarbledUint<1>>
-                + Into<GarbledUint<8>> + From<GarbledUint<8>>
-                + Into<GarbledUint<16>> + From<GarbledUint<16>>
-                + Into<GarbledUint<32>> + From<GarbledUint<32>>
-                + Into<GarbledUint<64>> + From<GarbledUint<64>>
-                + Into<GarbledUint<128>> + From<GarbledUint<128>>
-                + Clone,
-        {
-            fn generate<const N: usize, #type_name>(#inputs) -> #output_type
-            where
-                #type_name: Into<GarbledUint<N>> + From<GarbledUint<N>> + Clone,
-            {
-                let mut context = CircuitBuilder::default();
-                #(#mapped_inputs)*
-                #(#constants)*
-                let const_true = &context.input::<N>(&true.into());
-                let const_false = &context.input::<N>(&false.into());
+    let body = quote! {
+        let mut context = CircuitBuilder::default();
+        let mut __values: Vec<GateIndexVec> = Vec::with_capacity(#values);
+        #(#stmts)*
+        #operation
+    };
 
-                // Use the transformed function block (with context.add and if/else replacements)
-                let output = { #transformed_block };
+    let Some(generic) = generic else {
+        return Ok(quote! {
+            #[allow(unused_variables)]
+            #fn_vis fn #fn_name(#inputs) -> #output_type {
+                #body
+            }
+        });
+    };
+
+    // The body is instantiated at the width of the type parameter by its `Plaintext` impl, so
+    // the width is fixed when the caller is compiled
+    let param_names: Vec<&Ident> = params.iter().map(|(name, _)| name).collect();
+    let param_types = inputs.iter().filter_map(|input| match input {
+        FnArg::Typed(PatType { ty, .. }) => Some(ty),
+        FnArg::Receiver(_) => None,
+    });
+    Ok(quote! {
+        #[allow(unused_variables)]
+        #fn_vis fn #fn_name<#generic: Plaintext>(#inputs) -> #output_type {
+            struct Body<#generic> {
+                #(#param_names: #param_types,)*
+                __plaintext: std::marker::PhantomData<#generic>,
+            }
+
+            impl<#generic: Plaintext> Instantiate<#generic> for Body<#generic> {
+                type Output = #output_type;
 
-                #operation
+                fn call<const N: usize>(self) -> #output_type {
+                    let Body { #(#param_names,)* .. } = self;
+                    #body
+                }
             }
 
-            #match_arms
+            #generic::instantiate(Body {
+                #(#param_names,)*
+                __plaintext: std::marker::PhantomData,
+            })
         }
-    };
+    })
+}
+
+/// The type parameter standing for the parameter and return types that are not primitives, if any.
+fn generic_parameter(input_fn: &ItemFn) -> syn::Result<Option<Ident>> {
+    let mut types: Vec<&syn::Type> = input_fn
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { ty, .. }) => Some(&**ty),
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    if let ReturnType::Type(_, ty) = &input_fn.sig.output {
+        types.push(ty);
+    }
+
+    let mut generic: Option<Ident> = None;
+    for mut ty in types {
+        if let syn::Type::Array(array) = ty {
+            ty = &array.elem;
+        }
+        let syn::Type::Path(path) = ty else {
+            continue;
+        };
+        let Some(ident) = path.path.get_ident() else {
+            continue;
+        };
+        if Type::primitive(&ident.to_string()).is_some() {
+            continue;
+        }
+        match &generic {
+            Some(existing) if existing != ident => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "only one type parameter is supported, found `{}` and `{}`",
+                        existing, ident
+                    ),
+                ));
+            }
+            _ => generic = Some(ident.clone()),
+        }
+    }
+    Ok(generic)
+}
+
+/// Number of bits of a value: fixed by its type, or the width `N` a type parameter is
+/// instantiated with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Width {
+    Fixed(usize),
+    Generic,
+}
+
+impl ToTokens for Width {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            Width::Fixed(width) => Literal::usize_unsuffixed(*width).to_tokens(tokens),
+            Width::Generic => quote! { N }.to_tokens(tokens),
+        }
+    }
+}
+
+/// The type of a value computed by an encrypted function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Type {
+    Bool,
+    Uint(Width),
+    /// A two's complement integer.
+    Int(Width),
+}
+
+impl Type {
+    fn primitive(name: &str) -> Option<Type> {
+        match name {
+            "bool" => Some(Type::Bool),
+            "u8" => Some(Type::Uint(Width::Fixed(8))),
+            "u16" => Some(Type::Uint(Width::Fixed(16))),
+            "u32" => Some(Type::Uint(Width::Fixed(32))),
+            "u64" => Some(Type::Uint(Width::Fixed(64))),
+            "u128" => Some(Type::Uint(Width::Fixed(128))),
+            "i8" => Some(Type::Int(Width::Fixed(8))),
+            "i16" => Some(Type::Int(Width::Fixed(16))),
+            "i32" => Some(Type::Int(Width::Fixed(32))),
+            "i64" => Some(Type::Int(Width::Fixed(64))),
+            "i128" => Some(Type::Int(Width::Fixed(128))),
+            _ => None,
+        }
+    }
+
+    fn parse(ty: &syn::Type, generic: Option<&Ident>) -> Option<Type> {
+        let syn::Type::Path(path) = ty else {
+            return None;
+        };
+        let ident = path.path.get_ident()?;
+        if Some(ident) == generic {
+            return Some(Type::Uint(Width::Generic));
+        }
+        Type::primitive(&ident.to_string())
+    }
+
+    fn width(&self) -> Width {
+        match self {
+            Type::Bool => Width::Fixed(1),
+            Type::Uint(width) | Type::Int(width) => *width,
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self, Type::Uint(_) | Type::Int(_))
+    }
+
+    fn is_signed(&self) -> bool {
+        matches!(self, Type::Int(_))
+    }
+
+    /// Whether the integer `value` can be represented in this type without truncation.
+    fn fits(&self, value: i128) -> bool {
+        match (self, self.width()) {
+            (_, Width::Generic) => true,
+            (Type::Int(_), Width::Fixed(bits)) if bits < 128 => {
+                (-(1 << (bits - 1))..1 << (bits - 1)).contains(&value)
+            }
+            (Type::Int(_), _) => true,
+            (_, Width::Fixed(bits)) => value >= 0 && (bits >= 128 || (value as u128) >> bits == 0),
+        }
+    }
 
-    // Print the expanded code to stderr
-    // println!("Generated code:\n{}", expanded);
+    /// The type both `self` and `other` convert to without losing bits, if there is one.
+    fn wider(self, other: Type) -> Option<Type> {
+        match (self, other) {
+            _ if self == other => Some(self),
+            (Type::Uint(Width::Fixed(a)), Type::Uint(Width::Fixed(b))) => {
+                Some(Type::Uint(Width::Fixed(a.max(b))))
+            }
+            (Type::Int(Width::Fixed(a)), Type::Int(Width::Fixed(b))) => {
+                Some(Type::Int(Width::Fixed(a.max(b))))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::Uint(Width::Fixed(width)) => write!(f, "u{}", width),
+            Type::Int(Width::Fixed(width)) => write!(f, "i{}", width),
+            Type::Uint(Width::Generic) | Type::Int(Width::Generic) => {
+                write!(f, "the type parameter")
+            }
+        }
+    }
+}
+
+/// A declared type: a single value, or an array of a fixed number of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Shape {
+    Scalar(Type),
+    Array(Type, usize),
+}
+
+impl Shape {
+    fn parse(ty: &syn::Type, generic: Option<&Ident>) -> Option<Shape> {
+        let syn::Type::Array(array) = ty else {
+            return Type::parse(ty, generic).map(Shape::Scalar);
+        };
+        let Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(len), ..
+        }) = &array.len
+        else {
+            return None;
+        };
+        Some(Shape::Array(
+            Type::parse(&array.elem, generic)?,
+            len.base10_parse().ok()?,
+        ))
+    }
+
+    /// The type of the value, or of every element of the array.
+    fn element(&self) -> Type {
+        match self {
+            Shape::Scalar(ty) | Shape::Array(ty, _) => *ty,
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Scalar(ty) => write!(f, "{}", ty),
+            Shape::Array(Type::Uint(Width::Generic), len) => write!(f, "[T; {}]", len),
+            Shape::Array(ty, len) => write!(f, "[{}; {}]", ty, len),
+        }
+    }
+}
+
+/// A value of the generated code. Values are held in one vector rather than a `let` each, as
+/// every `let` opens a scope and an unrolled loop nests thousands of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Var(usize);
+
+impl ToTokens for Var {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let index = Literal::usize_unsuffixed(self.0);
+        quote! { __values[#index] }.to_tokens(tokens)
+    }
+}
+
+/// The result of translating an expression.
+#[derive(Clone)]
+enum Value {
+    /// Wires of the circuit, held in the generated variable `var`.
+    Wires { var: Var, ty: Type },
+    /// An integer literal, or an expression of them, which has no width until it meets a typed
+    /// value.
+    Const { value: i128, site: Site },
+    /// The elements of an array, each a single value.
+    Array(Vec<Value>),
+    /// The result of a statement.
+    Unit,
+}
+
+impl Value {
+    /// The type of the wires, or of the first element of an array that has one.
+    fn ty(&self) -> Option<Type> {
+        match self {
+            Value::Wires { ty, .. } => Some(*ty),
+            Value::Array(elements) => elements.iter().find_map(Value::ty),
+            _ => None,
+        }
+    }
+
+    fn same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Wires { var, .. }, Value::Wires { var: other, .. }) => var == other,
+            (Value::Const { value, .. }, Value::Const { value: other, .. }) => value == other,
+            (Value::Array(elements), Value::Array(others)) => {
+                elements.len() == others.len()
+                    && elements.iter().zip(others).all(|(a, b)| a.same(b))
+            }
+            (Value::Unit, Value::Unit) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Binding {
+    value: Value,
+    /// The type values assigned to the binding, or to the elements of an array binding, are
+    /// converted to, once it is known.
+    ty: Option<Type>,
+}
+
+/// The statements of a branch: the block of an `if` or the body of a `match` arm.
+enum Branch<'a> {
+    Block(&'a Block),
+    Expr(&'a Expr),
+}
+
+/// The source code an error points at. `Span::join` is nightly only, so a site keeps the spans of
+/// its first and last tokens and the compiler underlines everything between them.
+#[derive(Clone, Copy)]
+struct Site {
+    start: Span,
+    end: Span,
+}
+
+impl Site {
+    fn of(tokens: &impl ToTokens) -> Site {
+        let mut tokens = tokens.to_token_stream().into_iter();
+        let start = tokens
+            .next()
+            .map_or_else(Span::call_site, |token| token.span());
+        let end = tokens.last().map_or(start, |token| token.span());
+        Site { start, end }
+    }
+
+    fn error(self, message: impl fmt::Display) -> syn::Error {
+        let mut tokens = quote_spanned! { self.start=> start };
+        tokens.extend(quote_spanned! { self.end=> end });
+        syn::Error::new_spanned(tokens, message)
+    }
+}
 
-    TokenStream::from(expanded)
+/// Translates the body of an encrypted function into statements building its circuit.
+///
+/// Every value gets a type from the parameters, `let` annotations and casts it is computed from.
+/// Operands of different widths are extended to the wider one, zero-extending unsigned and
+/// sign-extending signed values, while narrowing and changing signedness only happen through
+/// `as`. Each intermediate value is held in a fresh variable, so the generated code is a flat list
+/// of statements whatever the nesting of the body. Both branches of an `if` are always evaluated,
+/// and variables assigned in them are multiplexed on the condition afterwards. Loops are unrolled,
+/// their index being a constant in each copy of the body. Calls of `CircuitBuilder` methods on
+/// `context`, such as `context.add(&a, b)`, are read as the operator they implement.
+struct Translator {
+    stmts: Vec<TokenStream2>,
+    scopes: Vec<HashMap<String, Binding>>,
+    default_type: Type,
+    generic: Option<Ident>,
+    vars: usize,
+    unroll_limit: usize,
+    /// Loop iterations unrolled so far.
+    unrolled: usize,
 }
 
-/// Traverse and transform the function body, replacing binary operators and if/else expressions.
-/// Also collects constants to add to the circuit context.
-fn modify_body(block: syn::Block, constants: &mut Vec<proc_macro2::TokenStream>) -> syn::Block {
-    let stmts = block
-        .stmts
-        .into_iter()
-        .map(|stmt| {
+impl Translator {
+    fn new(default_type: Type, generic: Option<Ident>, unroll_limit: usize) -> Self {
+        Translator {
+            stmts: Vec::new(),
+            scopes: vec![HashMap::new()],
+            default_type,
+            generic,
+            vars: 0,
+            unroll_limit,
+            unrolled: 0,
+        }
+    }
+
+    /// Emits a statement holding the wires built by `tokens` in a fresh value.
+    fn emit(&mut self, tokens: TokenStream2, ty: Type) -> Value {
+        let var = Var(self.vars);
+        self.vars += 1;
+        self.stmts.push(quote! { __values.push(#tokens); });
+        Value::Wires { var, ty }
+    }
+
+    fn emit_var(&mut self, tokens: TokenStream2, ty: Type) -> Var {
+        match self.emit(tokens, ty) {
+            Value::Wires { var, .. } => var,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The signed form of an operation on values of `ty` or its unsigned form. For the type
+    /// parameter both are emitted and `Plaintext::SIGNED` picks one when the function is
+    /// instantiated.
+    fn signed_or(&self, ty: Type, signed: TokenStream2, unsigned: TokenStream2) -> TokenStream2 {
+        match (ty, &self.generic) {
+            (Type::Uint(Width::Generic), Some(generic)) => quote! {
+                if <#generic as Plaintext>::SIGNED { #signed } else { #unsigned }
+            },
+            _ if ty.is_signed() => signed,
+            _ => unsigned,
+        }
+    }
+
+    fn bind(&mut self, name: &Ident, value: Value, ty: Option<Type>) {
+        let ty = ty.or(value.ty());
+        self.scopes
+            .last_mut()
+            .expect("the function scope is never popped")
+            .insert(name.to_string(), Binding { value, ty });
+    }
+
+    fn lookup(&self, name: &Ident) -> syn::Result<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.to_string()))
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    name,
+                    format!("cannot find value `{}` in this encrypted function", name),
+                )
+            })
+    }
+
+    fn parse_shape(&self, ty: &syn::Type) -> syn::Result<Shape> {
+        Shape::parse(ty, self.generic.as_ref()).ok_or_else(|| {
+            syn::Error::new_spanned(
+                ty,
+                "unsupported type, expected `bool`, an integer type such as `u8` or `i32`, or an \
+                 array of them",
+            )
+        })
+    }
+
+    fn parse_type(&self, ty: &syn::Type) -> syn::Result<Type> {
+        Type::parse(ty, self.generic.as_ref()).ok_or_else(|| {
+            syn::Error::new_spanned(
+                ty,
+                "unsupported type, expected `bool` or an integer type such as `u8` or `i32`",
+            )
+        })
+    }
+
+    fn block(&mut self, block: &Block) -> syn::Result<Value> {
+        self.scopes.push(HashMap::new());
+        let mut value = Value::Unit;
+        for stmt in &block.stmts {
+            value = Value::Unit;
             match stmt {
-                syn::Stmt::Expr(expr, semi_opt) => {
-                    syn::Stmt::Expr(replace_expressions(expr, constants), semi_opt)
-                }
-                syn::Stmt::Local(mut local) => {
-                    if let Some(local_init) = &mut local.init {
-                        // Replace the initializer expression
-                        //local_init.expr =
-                        //    Box::new(replace_expressions(*local_init.expr.clone(), constants));
-
-                        let local_expr = replace_expressions(*local_init.expr.clone(), constants);
-
-                        if let syn::Pat::Ident(ref pat_ident) = local.pat {
-                            if pat_ident.mutability.is_some() {
-                                local_init.expr = Box::new(syn::parse_quote! {
-                                    #local_expr.clone()
-                                });
-                            } else {
-                                local_init.expr = Box::new(syn::parse_quote! {
-                                    #local_expr
-                                });
-                            }
-                        }
+                Stmt::Local(local) => self.local(local)?,
+                Stmt::Expr(expr, semi) => {
+                    let result = self.expr(expr)?;
+                    if semi.is_none() {
+                        value = result;
                     }
-                    syn::Stmt::Local(local)
                 }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "unsupported statement in an encrypted function",
+                    ))
+                }
+            }
+        }
+        self.scopes.pop();
+        Ok(value)
+    }
 
-                other => other,
+    fn local(&mut self, local: &Local) -> syn::Result<()> {
+        let (name, declared) = match &local.pat {
+            Pat::Ident(pat_ident) => (&pat_ident.ident, None),
+            Pat::Type(PatType { pat, ty, .. }) => match &**pat {
+                Pat::Ident(pat_ident) => (&pat_ident.ident, Some(self.parse_shape(ty)?)),
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "unsupported pattern, expected an identifier",
+                    ))
+                }
+            },
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "unsupported pattern, expected an identifier",
+                ))
             }
-        })
-        .collect();
+        };
+        let Some(init) = &local.init else {
+            return Err(syn::Error::new_spanned(
+                local,
+                "variables of encrypted functions must be initialized where they are declared",
+            ));
+        };
 
-    syn::Block {
-        stmts,
-        brace_token: syn::token::Brace::default(),
+        let value = self.expr(&init.expr)?;
+        let value = match declared {
+            Some(shape) => self.conform(value, shape, Site::of(&init.expr))?,
+            None if matches!(value, Value::Unit) => {
+                return Err(syn::Error::new_spanned(&init.expr, "expected a value"))
+            }
+            None => value,
+        };
+        self.bind(name, value, declared.map(|shape| shape.element()));
+        Ok(())
     }
-}
 
-/// Replaces binary operators and if/else expressions with appropriate context calls.
-fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>) -> Expr {
-    match expr {
-        // if there is a block, recursively call modify_body
-        Expr::Block(ExprBlock { block, .. }) => {
-            let transformed_block = modify_body(block, constants);
-            syn::parse_quote! { #transformed_block }
-        }
-        // implement assignment
-        Expr::Assign(ExprAssign { left, right, .. }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-
-            match right_expr {
-                Expr::Reference(ExprReference { .. }) => {
-                    syn::parse_quote! {
-                        #left_expr = &#right_expr.clone()
-                    }
+    fn expr(&mut self, expr: &Expr) -> syn::Result<Value> {
+        match expr {
+            Expr::Lit(lit) => self.literal(&lit.lit),
+            Expr::Path(path) => match path.path.get_ident() {
+                Some(ident) => Ok(self.lookup(ident)?.value.clone()),
+                None => Err(syn::Error::new_spanned(
+                    path,
+                    "unsupported path, expected a variable",
+                )),
+            },
+            // parentheses only matter to the order of operations, which syn already resolved
+            Expr::Paren(paren) => self.expr(&paren.expr),
+            Expr::Group(group) => self.expr(&group.expr),
+            // references and copies are the same wires
+            Expr::Reference(reference) => self.expr(&reference.expr),
+            Expr::Block(block) => self.block(&block.block),
+            Expr::Unary(unary) => self.unary(unary),
+            Expr::Binary(binary) => self.binary(binary),
+            Expr::Assign(assign) => {
+                let value = self.expr(&assign.right)?;
+                self.assign(&assign.left, value, Site::of(&assign))
+            }
+            Expr::Cast(cast) => self.cast(cast),
+            Expr::If(expr_if) => self.if_expr(expr_if),
+            Expr::Match(expr_match) => self.match_expr(expr_match),
+            Expr::Array(array) => self.array_expr(array.elems.iter(), Site::of(array)),
+            Expr::Repeat(repeat) => self.repeat(repeat),
+            Expr::Index(index) => {
+                let elements = self.array(&index.expr)?;
+                let position = self.expr(&index.index)?;
+                self.element(elements, position, Site::of(index))
+            }
+            Expr::MethodCall(call) if self.is_context(&call.receiver) => self.context_call(call),
+            Expr::MethodCall(call) if call.method == "len" && call.args.is_empty() => {
+                let elements = self.array(&call.receiver)?;
+                Ok(Value::Const {
+                    value: elements.len() as i128,
+                    site: Site::of(call),
+                })
+            }
+            Expr::ForLoop(for_loop) => self.for_loop(for_loop),
+            Expr::While(_) | Expr::Loop(_) => Err(syn::Error::new_spanned(
+                expr,
+                "only `for` loops over a constant range are supported in encrypted functions",
+            )),
+            Expr::Break(_) | Expr::Continue(_) => Err(syn::Error::new_spanned(
+                expr,
+                "loops of encrypted functions always run every iteration, `break` and \
+                 `continue` are not supported",
+            )),
+            Expr::Return(expr_return) => Err(syn::Error::new_spanned(
+                expr_return,
+                "`return` is not supported in encrypted functions, the result is the value of the \
+                 body",
+            )),
+            other => Err(syn::Error::new_spanned(
+                other,
+                "unsupported expression in an encrypted function",
+            )),
+        }
+    }
+
+    /// Whether `expr` is the circuit builder of the generated code rather than a variable.
+    fn is_context(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Path(path) => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| ident == "context" && self.lookup(ident).is_err()),
+            _ => false,
+        }
+    }
+
+    /// A `CircuitBuilder` method called on `context`, translated as the operator it implements so
+    /// its operands are typed and extended like the operator's.
+    fn context_call(&mut self, call: &ExprMethodCall) -> syn::Result<Value> {
+        let site = Site::of(call);
+        let args: Vec<&Expr> = call.args.iter().collect();
+        let arity = |count: usize| {
+            if args.len() == count {
+                Ok(())
+            } else {
+                Err(site.error(format!(
+                    "`context.{}` takes {} arguments, found {}",
+                    call.method,
+                    count,
+                    args.len()
+                )))
+            }
+        };
+        let op: BinOp = match call.method.to_string().as_str() {
+            "not" => {
+                arity(1)?;
+                let value = self.expr(args[0])?;
+                return self.not(value, site);
+            }
+            "mux" => {
+                arity(3)?;
+                let condition = self.condition(args[0])?;
+                let if_true = self.expr(args[1])?;
+                let if_false = self.expr(args[2])?;
+                return self.select(&[condition], vec![if_true, if_false], site);
+            }
+            "add" => BinOp::Add(Default::default()),
+            "sub" => BinOp::Sub(Default::default()),
+            "mul" => BinOp::Mul(Default::default()),
+            "div" => BinOp::Div(Default::default()),
+            "rem" => BinOp::Rem(Default::default()),
+            "and" => BinOp::BitAnd(Default::default()),
+            "or" => BinOp::BitOr(Default::default()),
+            "xor" => BinOp::BitXor(Default::default()),
+            "eq" => BinOp::Eq(Default::default()),
+            "ne" => BinOp::Ne(Default::default()),
+            "lt" => BinOp::Lt(Default::default()),
+            "le" => BinOp::Le(Default::default()),
+            "gt" => BinOp::Gt(Default::default()),
+            "ge" => BinOp::Ge(Default::default()),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &call.method,
+                    format!(
+                        "unsupported method `context.{}` in an encrypted function",
+                        call.method
+                    ),
+                ))
+            }
+        };
+        arity(2)?;
+        let left = self.expr(args[0])?;
+        let right = self.expr(args[1])?;
+        self.operate(&op, left, right, site)
+    }
+
+    fn literal(&mut self, lit: &Lit) -> syn::Result<Value> {
+        match lit {
+            Lit::Bool(lit_bool) => Ok(self.bool_constant(lit_bool.value)),
+            Lit::Int(lit_int) => {
+                let value = lit_int.base10_parse::<i128>()?;
+                let site = Site::of(lit_int);
+                if lit_int.suffix().is_empty() {
+                    return Ok(Value::Const { value, site });
                 }
-                _ => {
-                    syn::parse_quote! {
-                        #left_expr = #right_expr.clone()
+                // a suffixed literal has the type of its suffix, like any typed value
+                match Type::primitive(lit_int.suffix()) {
+                    Some(ty) if ty.is_integer() => Ok(Value::Wires {
+                        var: self.constant(value, ty, site, false)?,
+                        ty,
+                    }),
+                    _ => {
+                        Err(site
+                            .error(format!("unsupported literal suffix `{}`", lit_int.suffix())))
                     }
                 }
             }
+            other => Err(syn::Error::new_spanned(
+                other,
+                "unsupported literal, expected an integer or a boolean",
+            )),
+        }
+    }
+
+    /// An array literal. Its elements take the widest of their types, unless they are all
+    /// constants, which stay untyped like any other constant.
+    fn array_expr<'a>(
+        &mut self,
+        exprs: impl Iterator<Item = &'a Expr>,
+        site: Site,
+    ) -> syn::Result<Value> {
+        let mut elements = Vec::new();
+        for expr in exprs {
+            match self.expr(expr)? {
+                Value::Array(_) => {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        "arrays of arrays are not supported in encrypted functions",
+                    ))
+                }
+                Value::Unit => return Err(syn::Error::new_spanned(expr, "expected a value")),
+                element => elements.push(element),
+            }
+        }
+        if elements.iter().all(|element| element.ty().is_none()) {
+            return Ok(Value::Array(elements));
+        }
+        let (vars, ty) = self.unify(elements, site)?;
+        Ok(Value::Array(
+            vars.into_iter()
+                .map(|var| Value::Wires { var, ty })
+                .collect(),
+        ))
+    }
+
+    /// An array repeating one value, as in `[0; 8]`.
+    fn repeat(&mut self, repeat: &ExprRepeat) -> syn::Result<Value> {
+        let len = match self.expr(&repeat.len)? {
+            Value::Const { value, .. } => usize::try_from(value).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                &repeat.len,
+                "array lengths must be constants known at compile time",
+            )
+        })?;
+        let element = self.array_expr(std::iter::once(&*repeat.expr), Site::of(repeat))?;
+        let Value::Array(element) = element else {
+            unreachable!("array literals are arrays")
+        };
+        Ok(Value::Array(vec![element[0].clone(); len]))
+    }
+
+    /// The elements of the array `expr` evaluates to.
+    fn array(&mut self, expr: &Expr) -> syn::Result<Vec<Value>> {
+        match self.expr(expr)? {
+            Value::Array(elements) => Ok(elements),
+            _ => Err(syn::Error::new_spanned(expr, "expected an array")),
+        }
+    }
+
+    /// Reads an element of an array. A constant index only picks its wires, while a secret one
+    /// multiplexes every element, reading zero when it is past the end.
+    fn element(&mut self, elements: Vec<Value>, index: Value, site: Site) -> syn::Result<Value> {
+        match index {
+            Value::Const {
+                value,
+                site: index_site,
+            } => usize::try_from(value)
+                .ok()
+                .and_then(|position| elements.get(position).cloned())
+                .ok_or_else(|| out_of_bounds(index_site, elements.len(), value)),
+            Value::Wires {
+                var: index,
+                ty: Type::Uint(_) | Type::Int(_),
+            } => {
+                if elements.is_empty() {
+                    return Err(site.error("cannot index an empty array"));
+                }
+                let (vars, ty) = self.unify(elements, site)?;
+                Ok(self.emit(
+                    quote! { context.mux_tree(&[#(#vars.clone()),*], &#index) },
+                    ty,
+                ))
+            }
+            Value::Wires { ty, .. } => {
+                Err(site.error(format!("array indices must be integers, found `{}`", ty)))
+            }
+            _ => Err(site.error("expected an index")),
         }
-        // return statement
-        Expr::Return(_) => {
-            panic!("Return statement not allowed in circuit macro");
+    }
+
+    /// Emits the input wires of the plaintext `value`, contributed by the garbler or, for
+    /// `#[evaluator]` parameters, by the evaluator.
+    fn input(&mut self, value: TokenStream2, ty: Type, evaluator: bool) -> Value {
+        let width = ty.width();
+        // signed values are handed over in two's complement
+        let bits = match (ty, &self.generic) {
+            (Type::Int(_), _) => quote! { &GarbledUint::from(GarbledInt::<#width>::from(#value)) },
+            (Type::Uint(Width::Generic), Some(generic)) => {
+                quote! { &#generic::encode::<#width>(#value) }
+            }
+            _ => quote! { &#value.into() },
+        };
+        if evaluator {
+            self.emit(quote! { context.input_eval::<#width>(#bits) }, ty)
+        } else {
+            self.emit(quote! { context.input::<#width>(#bits) }, ty)
+        }
+    }
+
+    fn bool_constant(&mut self, value: bool) -> Value {
+        if value {
+            self.emit(
+                quote! { GateIndexVec::from(context.const_one()) },
+                Type::Bool,
+            )
+        } else {
+            self.emit(
+                quote! { GateIndexVec::from(context.const_zero()) },
+                Type::Bool,
+            )
         }
-        // parentheses to ensure proper order of operations
-        Expr::Paren(expr_paren) => {
-            let inner_expr = replace_expressions(*expr_paren.expr, constants);
-            syn::parse_quote! { (#inner_expr) }
+    }
+
+    /// Wires carrying `value` as the integer type `ty`. An explicit constant is truncated to the
+    /// width, any other must fit in it.
+    fn constant(&mut self, value: i128, ty: Type, site: Site, explicit: bool) -> syn::Result<Var> {
+        if !explicit && !ty.fits(value) {
+            return Err(site.error(format!("literal `{}` does not fit in `{}`", value, ty)));
         }
-        // boolean literal
-        Expr::Lit(syn::ExprLit {
-            lit: Lit::Bool(lit_bool),
-            ..
-        }) => {
-            let value = lit_bool.value;
-            let const_var = format_ident!("const_{}", value as u128);
+        let width = ty.width();
+        // the low bits of the two's complement, which is the value itself when it is not negative
+        let value = Literal::u128_suffixed(value as u128);
+        Ok(self.emit_var(quote! { context.constant::<#width>(&#value.into()) }, ty))
+    }
 
-            if value {
-                constants.push(quote! {
-                    let #const_var = &context.input::<N>(&1_u128.into()).clone();
-                });
+    /// The wires of `value` as `target`: constants are given that type, narrower integers of the
+    /// same signedness are extended and anything else needs an explicit cast.
+    fn convert(
+        &mut self,
+        value: Value,
+        target: Type,
+        explicit: bool,
+        site: Site,
+    ) -> syn::Result<Var> {
+        let (var, ty) = match value {
+            Value::Unit => return Err(site.error("expected a value")),
+            Value::Array(_) => return Err(site.error("expected a single value, found an array")),
+            Value::Const { value, site } if target.is_integer() => {
+                return self.constant(value, target, site, explicit);
+            }
+            Value::Const { site, .. } => {
+                return Err(site.error(format!(
+                    "mismatched types: expected `{}`, found an integer",
+                    target
+                )));
+            }
+            Value::Wires { var, ty } => (var, ty),
+        };
+        if ty == target {
+            return Ok(var);
+        }
+
+        let (Width::Fixed(from), Width::Fixed(to)) = (ty.width(), target.width()) else {
+            return Err(mismatch(site, ty, target));
+        };
+        if !target.is_integer() || (ty == Type::Bool && !explicit) {
+            return Err(site.error(format!(
+                "mismatched types: expected `{}`, found `{}`",
+                target, ty
+            )));
+        }
+        if !explicit && ty.wider(target) != Some(target) {
+            return Err(if ty.is_signed() == target.is_signed() {
+                site.error(format!(
+                    "cannot narrow `{}` to `{}` implicitly, use `as {}`",
+                    ty, target, target
+                ))
             } else {
-                constants.push(quote! {
-                    let #const_var = &context.input::<N>(&0_u128.into()).clone();
-                });
-            }
-            syn::parse_quote! {#const_var}
-        }
-        // integer literal - handle as a constant in the circuit context
-        Expr::Lit(syn::ExprLit {
-            lit: Lit::Int(lit_int),
-            ..
-        }) => {
-            let value = lit_int
-                .base10_parse::<u128>()
-                .expect("Expected an integer literal");
-            let const_var = format_ident!("const_{}", value);
-            constants.push(quote! {
-                let #const_var = &context.input::<N>(&#value.into()).clone();
+                mismatch(site, ty, target)
             });
-            syn::parse_quote! {#const_var}
-        }
-        // equality
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Eq(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.eq(&left.into(), &right.into())
-            }}
-        }
-        // inequality
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Ne(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.ne(&left.into(), &right.into())
-            }}
-        }
-        // greater than
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Gt(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.gt(&left.into(), &right.into())
-            }}
-        }
-        // greater than or equal
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Ge(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.ge(&left.into(), &right.into())
-            }}
-        }
-        // less than
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Lt(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.lt(&left.into(), &right.into())
-            }}
-        }
-        // less than or equal
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Le(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.le(&left.into(), &right.into())
-            }}
-        }
-        // addition
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Add(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = &#left_expr;
-                let right = &#right_expr;
-                context.add(left.into(), right.into())
-            }}
-        }
-        // addition assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::AddAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.add(&#left, &#right)
-            }
-        }
-        // subtraction
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Sub(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.sub(&left.into(), &right.into())
-            }}
-        }
-        // subtraction assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::SubAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.sub(&#left, &#right)
-            }
-        }
-        // multiplication
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Mul(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = &#left_expr;
-                let right = &#right_expr;
-                context.mul(left.into(), right.into())
-            }}
-        }
-        // multiplication assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::MulAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.mul(&#left, &#right)
-            }
-        }
-        // division
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Div(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.div(&left.into(), &right.into())
-            }}
-        }
-        // division assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::DivAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.div(&#left, &#right)
-            }
-        }
-        // modulo
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Rem(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.rem(&left.into(), &right.into())
-            }}
-        }
-        // modulo assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::RemAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.rem(&#left, &#right)
-            }
-        }
-        // logical AND
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::And(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.land(&left, &right)
-            }}
-        }
-
-        // logical OR
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::Or(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.lor(&left, &right)
-            }}
-        }
-
-        // bitwise AND
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitAnd(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.and(&left.into(), &right.into())
-            }}
-        }
-        // bitwise AND assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitAndAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.and(&#left, &#right)
-            }
-        }
-
-        // bitwise OR
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitOr(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.or(&left.into(), &right.into())
-            }}
-        }
-        // bitwise OR assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitOrAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.or(&#left, &#right)
-            }
-        }
-
-        // bitwise XOR
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitXor(_),
-            ..
-        }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.xor(&left.into(), &right.into())
-            }}
-        }
-        // bitwise XOR assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitXorAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.xor(&#left, &#right)
-            }
-        }
-
-        // bitwise NOT
-        Expr::Unary(ExprUnary {
-            op: syn::UnOp::Not(_),
-            expr,
-            ..
-        }) => {
-            let single_expr = replace_expressions(*expr, constants);
-            syn::parse_quote! {{
-                let single = #single_expr;
-                context.not(&single.into())
-            }}
-        }
-
-        /*
-        Expr::If(ExprIf {
-            cond,
-            then_branch,
-            else_branch,
-            ..
-        }) => {
-            let cond_expr = replace_expressions(*cond, constants);
-            let then_block = modify_body(then_branch, constants);
-
-            // If there's an explicit else block, use it; otherwise, continue with remaining expressions
-            let else_expr = if let Some((_, else_expr)) = else_branch {
-                replace_expressions(*else_expr, constants)
-            } else {
-                // Placeholder for remaining function body as the fall-through `else` case
-                //syn::parse_quote! { context.input::<N>(&0u128.into()) }
-                panic!("else branch is required");
-            };
+        }
 
-            // Generate code for conditional execution and fall-through
-            syn::parse_quote! {{
-                let cond = #cond_expr;
-                let if_true = #then_block;
-                let if_false = #else_expr;
-                context.mux(&cond.into(), &if_true, &if_false)
-            }}
-        }
-        */
-        Expr::If(ExprIf {
-            cond,
-            then_branch,
-            else_branch,
-            ..
-        }) => {
-            // Check if `cond` is an `if let` with a range pattern
-            let cond_expr = match *cond {
-                Expr::Let(ExprLet { pat, expr, .. }) => {
-                    match &*pat {
-                        // Handle inclusive range pattern (e.g., 1..=5)
-                        syn::Pat::Range(syn::PatRange {
-                            start: Some(start),
-                            end: Some(end),
-                            limits: syn::RangeLimits::Closed(_),
-                            ..
-                        }) => {
-                            let start_expr = replace_expressions(*start.clone(), constants);
-                            let end_expr = replace_expressions(*end.clone(), constants);
-                            let input_expr = replace_expressions(*expr, constants);
-
-                            // Inclusive range with embedded `let` statements for `lhs` and `rhs`
-                            syn::parse_quote! {{
-                                let lhs = &context.ge(&#input_expr.into(), &#start_expr.into()).into();
-                                let rhs = &context.le(&#input_expr.into(), &#end_expr.into()).into();
-                                context.and(lhs, rhs)
-                            }}
-                        }
-                        // Handle exclusive range pattern (e.g., 1..10)
-                        syn::Pat::Range(syn::PatRange {
-                            start: Some(start),
-                            end: Some(end),
-                            limits: syn::RangeLimits::HalfOpen(_),
-                            ..
-                        }) => {
-                            let start_expr = replace_expressions(*start.clone(), constants);
-                            let end_expr = replace_expressions(*end.clone(), constants);
-                            let input_expr = replace_expressions(*expr, constants);
-
-                            // Exclusive range with embedded `let` statements for `lhs` and `rhs`
-                            syn::parse_quote! {{
-                                let lhs = &context.ge(&#input_expr.into(), &#start_expr.into()).into();
-                                let rhs = &context.lt(&#input_expr.into(), &#end_expr.into()).into();
-                                context.and(lhs, rhs)
-                            }}
-                        }
-                        // Handle single literal pattern, e.g., `if let 5 = n`
-                        syn::Pat::Lit(lit) => {
-                            let lit_expr = replace_expressions(Expr::Lit(lit.clone()), constants);
-                            let input_expr = replace_expressions(*expr, constants);
-
-                            syn::parse_quote! {
-                                context.eq(&#input_expr.into(), &#lit_expr.into())
-                            }
-                        }
-                        _ => panic!(
-                            "Unsupported pattern in if let: expected a range or literal pattern."
-                        ),
-                    }
+        // like Rust casts, widening follows the signedness of the source and narrowing truncates
+        Ok(if ty.is_signed() && from < to {
+            self.emit_var(quote! { context.sign_extend(&#var, #to) }, target)
+        } else {
+            self.emit_var(quote! { context.resize(&#var, #to) }, target)
+        })
+    }
+
+    /// The wires of a value whose type does not matter, giving constants the default type.
+    fn materialize(&mut self, value: Value, site: Site) -> syn::Result<Var> {
+        match value {
+            Value::Wires { var, .. } => Ok(var),
+            other => self.convert(other, self.default_type, false, site),
+        }
+    }
+
+    /// `value` converted to the declared `shape`, element by element for arrays.
+    fn conform(&mut self, value: Value, shape: Shape, site: Site) -> syn::Result<Value> {
+        match (shape, value) {
+            (Shape::Scalar(ty), value) => Ok(Value::Wires {
+                var: self.convert(value, ty, false, site)?,
+                ty,
+            }),
+            (Shape::Array(ty, len), Value::Array(elements)) if elements.len() == len => {
+                let mut conformed = Vec::with_capacity(len);
+                for element in elements {
+                    conformed.push(self.conform(element, Shape::Scalar(ty), site)?);
                 }
-                ref _other => {
-                    replace_expressions(*cond, constants) // Fallback for non-let conditions
+                Ok(Value::Array(conformed))
+            }
+            (shape, Value::Array(elements)) => Err(site.error(format!(
+                "mismatched types: expected `{}`, found an array of {} elements",
+                shape,
+                elements.len()
+            ))),
+            (shape, _) => Err(site.error(format!(
+                "mismatched types: expected `{}`, found a single value",
+                shape
+            ))),
+        }
+    }
+
+    /// The wires of `value` in one vector, the elements of an array one after another.
+    fn flatten(&mut self, value: Value, site: Site) -> syn::Result<Var> {
+        let Value::Array(elements) = value else {
+            return self.materialize(value, site);
+        };
+        let mut vars = Vec::with_capacity(elements.len());
+        for element in elements {
+            vars.push(self.materialize(element, site)?);
+        }
+        Ok(self.emit_var(
+            quote! {{
+                let mut wires = GateIndexVec::default();
+                #(wires.push_all(&#vars);)*
+                wires
+            }},
+            self.default_type,
+        ))
+    }
+
+    /// Converts `values` to the widest of their types.
+    fn unify(&mut self, values: Vec<Value>, site: Site) -> syn::Result<(Vec<Var>, Type)> {
+        let mut target: Option<Type> = None;
+        for value in &values {
+            match value {
+                Value::Wires { ty, .. } => {
+                    target = Some(match target {
+                        None => *ty,
+                        Some(target) => target
+                            .wider(*ty)
+                            .ok_or_else(|| mismatch(site, target, *ty))?,
+                    })
                 }
-            };
+                Value::Unit => return Err(site.error("expected a value")),
+                Value::Array(_) => {
+                    return Err(site.error("expected a single value, found an array"))
+                }
+                Value::Const { .. } => {}
+            }
+        }
+        let target = target.unwrap_or(self.default_type);
+        let vars = values
+            .into_iter()
+            .map(|value| self.convert(value, target, false, site))
+            .collect::<syn::Result<_>>()?;
+        Ok((vars, target))
+    }
+
+    fn unary(&mut self, unary: &ExprUnary) -> syn::Result<Value> {
+        let site = Site::of(unary);
+        let value = self.expr(&unary.expr)?;
+        match &unary.op {
+            UnOp::Not(_) => self.not(value, site),
+            UnOp::Neg(_) => match value {
+                Value::Const { value, .. } => Ok(Value::Const {
+                    value: value
+                        .checked_neg()
+                        .ok_or_else(|| site.error("constant overflows"))?,
+                    site,
+                }),
+                Value::Wires {
+                    var,
+                    ty: ty @ Type::Int(_),
+                } => Ok(self.emit(quote! { context.neg(&#var) }, ty)),
+                Value::Wires { .. } => Err(site.error("cannot negate a value of an unsigned type")),
+                Value::Array(_) => Err(site.error("expected a single value, found an array")),
+                Value::Unit => Err(site.error("expected a value")),
+            },
+            UnOp::Deref(_) => Ok(value),
+            other => Err(syn::Error::new_spanned(
+                other,
+                "unsupported operator in an encrypted function",
+            )),
+        }
+    }
+
+    fn not(&mut self, value: Value, site: Site) -> syn::Result<Value> {
+        let ty = match value {
+            Value::Wires { ty, .. } => ty,
+            _ => self.default_type,
+        };
+        let var = self.convert(value, ty, false, site)?;
+        Ok(self.emit(quote! { context.not(&#var) }, ty))
+    }
+
+    fn binary(&mut self, binary: &ExprBinary) -> syn::Result<Value> {
+        let site = Site::of(binary);
+        let Some(op) = compound_base(&binary.op) else {
+            let left = self.expr(&binary.left)?;
+            let right = self.expr(&binary.right)?;
+            return self.operate(&binary.op, left, right, site);
+        };
 
-            let then_block = modify_body(then_branch, constants);
+        // a compound assignment evaluates to the assigned value
+        if let Expr::Index(index) = &*binary.left {
+            // the element is read and written at the same index, translated once
+            let elements = self.array(&index.expr)?;
+            let position = self.expr(&index.index)?;
+            let left = self.element(elements, position.clone(), Site::of(index))?;
+            let right = self.expr(&binary.right)?;
+            let value = self.operate(&op, left, right, site)?;
+            return self.assign_element(index, position, value, site);
+        }
+        let left = self.expr(&binary.left)?;
+        let right = self.expr(&binary.right)?;
+        let value = self.operate(&op, left, right, site)?;
+        self.assign(&binary.left, value, site)
+    }
+
+    fn operate(&mut self, op: &BinOp, left: Value, right: Value, site: Site) -> syn::Result<Value> {
+        if let (Value::Const { value: a, .. }, Value::Const { value: b, .. }) = (&left, &right) {
+            if let Some(value) = self.fold(op, *a, *b, site)? {
+                return Ok(value);
+            }
+        }
 
-            // Check if an `else` branch exists, as it's required.
-            let else_expr = if let Some((_, else_expr)) = else_branch {
-                replace_expressions(*else_expr, constants)
+        match op {
+            BinOp::And(_) | BinOp::Or(_) => {
+                let (vars, ty) = self.unify(vec![left, right], site)?;
+                if ty != Type::Bool {
+                    return Err(site.error(format!("expected `bool` operands, found `{}`", ty)));
+                }
+                let (a, b) = (&vars[0], &vars[1]);
+                Ok(match op {
+                    BinOp::And(_) => self.emit(
+                        quote! { GateIndexVec::from(context.land(&#a[0], &#b[0])) },
+                        Type::Bool,
+                    ),
+                    _ => self.emit(
+                        quote! { GateIndexVec::from(context.lor(&#a, &#b)) },
+                        Type::Bool,
+                    ),
+                })
+            }
+            BinOp::Shl(_) | BinOp::Shr(_) => self.shift(op, left, right, site),
+            BinOp::Eq(_)
+            | BinOp::Ne(_)
+            | BinOp::Lt(_)
+            | BinOp::Le(_)
+            | BinOp::Gt(_)
+            | BinOp::Ge(_) => {
+                let (vars, ty) = self.unify(vec![left, right], site)?;
+                let (a, b) = (&vars[0], &vars[1]);
+                let (unsigned, signed) = match op {
+                    BinOp::Eq(_) => (quote! { eq }, quote! { eq }),
+                    BinOp::Ne(_) => (quote! { ne }, quote! { ne }),
+                    BinOp::Lt(_) => (quote! { lt }, quote! { lt_signed }),
+                    BinOp::Le(_) => (quote! { le }, quote! { le_signed }),
+                    BinOp::Gt(_) => (quote! { gt }, quote! { gt_signed }),
+                    _ => (quote! { ge }, quote! { ge_signed }),
+                };
+                let compare = self.signed_or(
+                    ty,
+                    quote! { context.#signed(&#a, &#b) },
+                    quote! { context.#unsigned(&#a, &#b) },
+                );
+                Ok(self.emit(quote! { GateIndexVec::from(#compare) }, Type::Bool))
+            }
+            _ => {
+                let (method, arithmetic) = match op {
+                    BinOp::Add(_) => (quote! { add }, true),
+                    BinOp::Sub(_) => (quote! { sub }, true),
+                    BinOp::Mul(_) => (quote! { mul }, true),
+                    BinOp::Div(_) => (quote! { div }, true),
+                    BinOp::Rem(_) => (quote! { rem }, true),
+                    BinOp::BitAnd(_) => (quote! { and }, false),
+                    BinOp::BitOr(_) => (quote! { or }, false),
+                    BinOp::BitXor(_) => (quote! { xor }, false),
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "unsupported operator in an encrypted function",
+                        ))
+                    }
+                };
+                let (vars, ty) = self.unify(vec![left, right], site)?;
+                if arithmetic && ty == Type::Bool {
+                    return Err(site.error("cannot apply an arithmetic operator to `bool`"));
+                }
+                // two's complement addition, subtraction and multiplication are the unsigned ones
+                let (a, b) = (&vars[0], &vars[1]);
+                let operation = match op {
+                    BinOp::Div(_) => self.signed_or(
+                        ty,
+                        quote! { context.div_signed(&#a, &#b) },
+                        quote! { context.div(&#a, &#b) },
+                    ),
+                    BinOp::Rem(_) => self.signed_or(
+                        ty,
+                        quote! { context.rem_signed(&#a, &#b) },
+                        quote! { context.rem(&#a, &#b) },
+                    ),
+                    _ => quote! { context.#method(&#a, &#b) },
+                };
+                Ok(self.emit(operation, ty))
+            }
+        }
+    }
+
+    /// Evaluates an operator on two constants, or returns `None` when its result is not a
+    /// constant.
+    fn fold(&mut self, op: &BinOp, a: i128, b: i128, site: Site) -> syn::Result<Option<Value>> {
+        let value = match op {
+            BinOp::Add(_) => a.checked_add(b),
+            BinOp::Sub(_) => a.checked_sub(b),
+            BinOp::Mul(_) => a.checked_mul(b),
+            BinOp::Div(_) | BinOp::Rem(_) if b == 0 => return Err(site.error("division by zero")),
+            BinOp::Div(_) => a.checked_div(b),
+            BinOp::Rem(_) => a.checked_rem(b),
+            BinOp::BitAnd(_) => Some(a & b),
+            BinOp::BitOr(_) => Some(a | b),
+            BinOp::BitXor(_) => Some(a ^ b),
+            BinOp::Shl(_) => u32::try_from(b).ok().and_then(|b| a.checked_shl(b)),
+            BinOp::Shr(_) => u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+            BinOp::Eq(_) => return Ok(Some(self.bool_constant(a == b))),
+            BinOp::Ne(_) => return Ok(Some(self.bool_constant(a != b))),
+            BinOp::Lt(_) => return Ok(Some(self.bool_constant(a < b))),
+            BinOp::Le(_) => return Ok(Some(self.bool_constant(a <= b))),
+            BinOp::Gt(_) => return Ok(Some(self.bool_constant(a > b))),
+            BinOp::Ge(_) => return Ok(Some(self.bool_constant(a >= b))),
+            _ => return Ok(None),
+        };
+        match value {
+            Some(value) => Ok(Some(Value::Const { value, site })),
+            None => Err(site.error("constant overflows")),
+        }
+    }
+
+    /// Shifts keep the type of the shifted value, and `>>` copies the sign bit of signed values. A
+    /// constant amount only rewires bits, while an integer amount of any width goes through a
+    /// barrel shifter.
+    fn shift(&mut self, op: &BinOp, left: Value, right: Value, site: Site) -> syn::Result<Value> {
+        let ty = match &left {
+            Value::Wires { ty, .. } => *ty,
+            _ => self.default_type,
+        };
+        if ty == Type::Bool {
+            return Err(site.error("cannot shift a `bool`"));
+        }
+        let value = self.convert(left, ty, false, site)?;
+        let (amount, shift_var) = match right {
+            Value::Const {
+                value: amount,
+                site,
+            } => {
+                let amount =
+                    usize::try_from(amount).map_err(|_| site.error("negative shift amount"))?;
+                (quote! { #amount }, false)
+            }
+            Value::Wires {
+                var: amount,
+                ty: Type::Uint(_) | Type::Int(_),
+            } => (quote! { &#amount }, true),
+            Value::Wires { ty, .. } => {
+                return Err(site.error(format!("shift amounts must be integers, found `{}`", ty)))
+            }
+            Value::Array(_) => return Err(site.error("expected a single value, found an array")),
+            Value::Unit => return Err(site.error("expected a value")),
+        };
+        let shift = |name: &str| {
+            let method = if shift_var {
+                format_ident!("{}_var", name)
             } else {
-                panic!("else branch is required for range if let");
+                format_ident!("{}", name)
             };
+            quote! { context.#method(&#value, #amount) }
+        };
+        let operation = match op {
+            BinOp::Shl(_) => shift("shl"),
+            _ => self.signed_or(ty, shift("sar"), shift("shr")),
+        };
+        Ok(self.emit(operation, ty))
+    }
 
-            // Generate code for conditional execution and chaining
-            syn::parse_quote! {{
-                let cond = #cond_expr;
-                let if_true = #then_block;
-                let if_false = #else_expr;
-                context.mux(&cond.into(), &if_true, &if_false)
-            }}
-        }
-
-        // Support match arms with mux and other operations
-        Expr::Match(ExprMatch { expr, arms, .. }) => {
-            let match_expr = replace_expressions(*expr, constants);
-
-            // Define an input variable to use in range proof processing
-            let input = syn::Ident::new("input", proc_macro2::Span::call_site());
-            let input_binding = quote! { let #input = #match_expr; };
-
-            // Process each arm, building up the conditional chain
-            let arm_exprs = arms
-                .into_iter()
-                .rev()
-                .fold(None as Option<Expr>, |acc, arm| {
-                    let pat = arm.pat;
-                    let body_expr = replace_expressions(*arm.body, constants);
-
-                    // Create conditional expression for each arm, handling ranges
-                    let cond_expr = match &pat {
-                        // Handle inclusive range pattern (start..=end)
-                        syn::Pat::Range(syn::PatRange {
-                            start: Some(start),
-                            end: Some(end),
-                            limits: syn::RangeLimits::Closed(_),
-                            ..
-                        }) => {
-                            let start = replace_expressions(*start.clone(), constants);
-                            let end = replace_expressions(*end.clone(), constants);
-                            quote! {
-                                let lhs = &context.ge(&#input.into(), &#start.into()).into();
-                                let rhs = &context.le(&#input.into(), &#end.into()).into();
-                                context.and(
-                                    lhs,
-                                    rhs
-                                )
-                            }
-                        }
-                        // Handle exclusive range pattern (start..end)
-                        syn::Pat::Range(syn::PatRange {
-                            start: Some(start),
-                            end: Some(end),
-                            limits: syn::RangeLimits::HalfOpen(_),
-                            ..
-                        }) => {
-                            let start = replace_expressions(*start.clone(), constants);
-                            let end = replace_expressions(*end.clone(), constants);
-                            quote! {
-                                let lhs = &context.ge(&#input.into(), &#start.into()).into();
-                                let rhs = &context.lt(&#input.into(), &#end.into()).into();
-                                context.and(
-                                    lhs,
-                                    rhs
-                                )
-                            }
-                        }
-                        // Handle single value pattern (e.g., `5`)
-                        syn::Pat::Lit(lit) => {
-                            let lit_expr =
-                                replace_expressions(syn::Expr::Lit(lit.clone()), constants);
-                            quote! {
-                                context.eq(&#input.into(), &#lit_expr.into())
-                            }
-                        }
-
-                        syn::Pat::Ident(pat) => {
-                            // Create conditional expression for each arm
-                            let cond_expr = replace_expressions(
-                                syn::parse_quote! { #match_expr == #pat },
-                                constants,
-                            );
-
-                            syn::parse_quote! {{
-                                { #cond_expr }
-                            }}
-                        }
-                        // Handle the wildcard pattern `_` as default/fallback case
-                        syn::Pat::Wild(_) => quote! { true },
-                        other => panic!("{:?}: Unsupported pattern in match arm", other),
+    fn assign(&mut self, left: &Expr, value: Value, site: Site) -> syn::Result<Value> {
+        if let Expr::Index(index) = left {
+            let position = self.expr(&index.index)?;
+            return self.assign_element(index, position, value, site);
+        }
+        let name = variable(left)?;
+        let binding = self.lookup(name)?.clone();
+        let value = match (&binding.value, binding.ty) {
+            (Value::Array(elements), ty) => {
+                let len = elements.len();
+                match (value, ty) {
+                    (Value::Array(values), None) if values.len() == len => Value::Array(values),
+                    (value, ty) => {
+                        let ty = ty.or(value.ty()).unwrap_or(self.default_type);
+                        self.conform(value, Shape::Array(ty, len), site)?
+                    }
+                }
+            }
+            (_, Some(ty)) => self.conform(value, Shape::Scalar(ty), site)?,
+            (_, None) if matches!(value, Value::Unit) => return Err(site.error("expected a value")),
+            (_, None) if matches!(value, Value::Array(_)) => {
+                return Err(site.error("mismatched types: expected a single value, found an array"))
+            }
+            (_, None) => value,
+        };
+
+        let ty = binding.ty.or(value.ty());
+        self.rebind(name, value.clone(), ty);
+        Ok(value)
+    }
+
+    /// Assigns to the element of an array at `position`, the value of the index expression of
+    /// `index`. A secret index writes every element, keeping its old value wherever the index does
+    /// not match.
+    fn assign_element(
+        &mut self,
+        index: &ExprIndex,
+        position: Value,
+        value: Value,
+        site: Site,
+    ) -> syn::Result<Value> {
+        let name = variable(&index.expr)?;
+        let binding = self.lookup(name)?.clone();
+        let Value::Array(mut elements) = binding.value else {
+            return Err(syn::Error::new_spanned(&index.expr, "expected an array"));
+        };
+        let value = match binding.ty {
+            Some(ty) => self.conform(value, Shape::Scalar(ty), site)?,
+            None => match value {
+                Value::Unit => return Err(site.error("expected a value")),
+                Value::Array(_) => {
+                    return Err(site.error("expected a single value, found an array"))
+                }
+                value => value,
+            },
+        };
+
+        match position {
+            Value::Const {
+                value: position,
+                site: index_site,
+            } => {
+                let position = usize::try_from(position)
+                    .ok()
+                    .filter(|position| *position < elements.len())
+                    .ok_or_else(|| out_of_bounds(index_site, elements.len(), position))?;
+                elements[position] = value.clone();
+            }
+            Value::Wires {
+                var,
+                ty: index_ty @ (Type::Uint(_) | Type::Int(_)),
+            } => {
+                for (position, element) in elements.iter_mut().enumerate() {
+                    // elements the index type cannot address are never written
+                    if !index_ty.fits(position as i128) {
+                        continue;
+                    }
+                    let position = Value::Const {
+                        value: position as i128,
+                        site,
+                    };
+                    let index = Value::Wires { var, ty: index_ty };
+                    let Value::Wires { var: hit, .. } =
+                        self.operate(&BinOp::Eq(Default::default()), index, position, site)?
+                    else {
+                        unreachable!("comparisons are always wires")
                     };
+                    *element =
+                        self.select(&[Some(hit)], vec![value.clone(), element.clone()], site)?;
+                }
+            }
+            Value::Wires { ty, .. } => {
+                return Err(syn::Error::new_spanned(
+                    &index.index,
+                    format!("array indices must be integers, found `{}`", ty),
+                ))
+            }
+            _ => return Err(syn::Error::new_spanned(&index.index, "expected an index")),
+        }
 
-                    // Chain the condition with the body, selecting based on condition
-                    Some(if let Some(else_expr) = acc {
-                        syn::parse_quote! {{
-                            let if_true = { #body_expr };
-                            let if_false = { #else_expr };
-                            let cond = { #cond_expr };
-                            context.mux(&cond.into(), &if_true, &if_false)
-                        }}
-                    } else {
-                        syn::parse_quote! {{
-                            { #body_expr }
-                        }}
-                    })
-                });
+        let ty = binding.ty.or(value.ty());
+        self.rebind(name, Value::Array(elements), ty);
+        Ok(value)
+    }
+
+    /// Replaces the value of the innermost binding of `name`.
+    fn rebind(&mut self, name: &Ident, value: Value, ty: Option<Type>) {
+        let scope = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.contains_key(&name.to_string()))
+            .expect("the binding was looked up before");
+        scope.insert(name.to_string(), Binding { value, ty });
+    }
+
+    fn cast(&mut self, cast: &ExprCast) -> syn::Result<Value> {
+        let ty = self.parse_type(&cast.ty)?;
+        let value = self.expr(&cast.expr)?;
+        Ok(Value::Wires {
+            var: self.convert(value, ty, true, Site::of(&cast))?,
+            ty,
+        })
+    }
+
+    /// The wire that is set when `expr` holds, or `None` when it always holds.
+    fn condition(&mut self, expr: &Expr) -> syn::Result<Option<Var>> {
+        if let Expr::Let(expr_let) = expr {
+            let value = self.expr(&expr_let.expr)?;
+            return self.pattern(&expr_let.pat, value, Site::of(&expr));
+        }
+        match self.expr(expr)? {
+            Value::Wires {
+                var,
+                ty: Type::Bool,
+            } => Ok(Some(var)),
+            Value::Wires { ty, .. } => Err(syn::Error::new_spanned(
+                expr,
+                format!("expected a `bool` condition, found `{}`", ty),
+            )),
+            _ => Err(syn::Error::new_spanned(expr, "expected a `bool` condition")),
+        }
+    }
 
-            match arm_exprs {
-                Some(result) => syn::parse_quote! {{
-                    #input_binding // Bind `input` at the beginning
-                    #result        // Process the chained expressions
-                }},
-                None => panic!("Match expression requires at least one arm"),
+    /// The wire that is set when `value` matches `pat`, or `None` when it always matches.
+    fn pattern(&mut self, pat: &Pat, value: Value, site: Site) -> syn::Result<Option<Var>> {
+        let matched = match pat {
+            Pat::Range(syn::PatRange {
+                start: Some(start),
+                end: Some(end),
+                limits,
+                ..
+            }) => {
+                let start = self.expr(start)?;
+                let end = self.expr(end)?;
+                let lower =
+                    self.operate(&BinOp::Ge(Default::default()), value.clone(), start, site)?;
+                let upper = match limits {
+                    syn::RangeLimits::Closed(_) => {
+                        self.operate(&BinOp::Le(Default::default()), value, end, site)?
+                    }
+                    syn::RangeLimits::HalfOpen(_) => {
+                        self.operate(&BinOp::Lt(Default::default()), value, end, site)?
+                    }
+                };
+                self.operate(&BinOp::And(Default::default()), lower, upper, site)?
+            }
+            Pat::Lit(lit) => {
+                let lit = self.literal(&lit.lit)?;
+                self.operate(&BinOp::Eq(Default::default()), value, lit, site)?
             }
+            // a name is a constant to compare with, it does not bind the matched value
+            Pat::Ident(pat_ident)
+                if pat_ident.subpat.is_none()
+                    && pat_ident.by_ref.is_none()
+                    && pat_ident.mutability.is_none() =>
+            {
+                let constant = self.lookup(&pat_ident.ident)?.value.clone();
+                self.operate(&BinOp::Eq(Default::default()), value, constant, site)?
+            }
+            Pat::Wild(_) => return Ok(None),
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "unsupported pattern, expected a range, a literal, a constant or `_`",
+                ))
+            }
+        };
+        match matched {
+            Value::Wires { var, .. } => Ok(Some(var)),
+            _ => unreachable!("comparisons are always wires"),
         }
+    }
 
-        other => other,
+    fn if_expr(&mut self, expr_if: &ExprIf) -> syn::Result<Value> {
+        let condition = self.condition(&expr_if.cond)?;
+        let Some((_, else_branch)) = &expr_if.else_branch else {
+            return Err(syn::Error::new_spanned(
+                expr_if,
+                "`if` without `else` is not supported in encrypted functions, add an `else` branch",
+            ));
+        };
+        self.branches(
+            &[condition],
+            vec![
+                Branch::Block(&expr_if.then_branch),
+                Branch::Expr(else_branch),
+            ],
+            Site::of(&expr_if),
+        )
     }
+
+    fn match_expr(&mut self, expr_match: &ExprMatch) -> syn::Result<Value> {
+        if expr_match.arms.is_empty() {
+            return Err(syn::Error::new_spanned(
+                expr_match,
+                "`match` without arms is not supported in encrypted functions",
+            ));
+        }
+        let value = self.expr(&expr_match.expr)?;
+
+        // the last arm is taken whenever none of the others matched
+        let mut conditions = Vec::new();
+        for arm in &expr_match.arms[..expr_match.arms.len() - 1] {
+            if let Some((_, guard)) = &arm.guard {
+                return Err(syn::Error::new_spanned(
+                    guard,
+                    "match guards are not supported in encrypted functions",
+                ));
+            }
+            conditions.push(self.pattern(&arm.pat, value.clone(), Site::of(&arm.pat))?);
+        }
+        let branches = expr_match
+            .arms
+            .iter()
+            .map(|arm| Branch::Expr(&arm.body))
+            .collect();
+        self.branches(&conditions, branches, Site::of(&expr_match))
+    }
+
+    /// Unrolls a loop over a constant range, binding its index to a constant in every iteration,
+    /// or over an array, binding each element in turn.
+    fn for_loop(&mut self, for_loop: &ExprForLoop) -> syn::Result<Value> {
+        if let Some(label) = &for_loop.label {
+            return Err(syn::Error::new_spanned(
+                label,
+                "loop labels are not supported in encrypted functions",
+            ));
+        }
+        let index = match &*for_loop.pat {
+            Pat::Ident(pat_ident) => Some(&pat_ident.ident),
+            Pat::Wild(_) => None,
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "unsupported loop pattern, expected an identifier or `_`",
+                ))
+            }
+        };
+        let items = match &*for_loop.expr {
+            Expr::Range(syn::ExprRange {
+                start: Some(start),
+                end: Some(end),
+                limits,
+                ..
+            }) => {
+                let start = self.bound(start)?;
+                let mut end = self.bound(end)?;
+                if let RangeLimits::Closed(_) = limits {
+                    end = end.checked_add(1).ok_or_else(|| {
+                        syn::Error::new_spanned(&for_loop.expr, "constant overflows")
+                    })?;
+                }
+                let iterations =
+                    usize::try_from(end.saturating_sub(start).max(0)).unwrap_or(usize::MAX);
+                self.unroll(iterations, &for_loop.expr)?;
+                let site = Site::of(&for_loop.pat);
+                (start..end)
+                    .map(|value| Value::Const { value, site })
+                    .collect()
+            }
+            Expr::Range(_) => return Err(unbounded(&for_loop.expr)),
+            expr => match self.expr(expr)? {
+                // the loop variable takes each element in turn
+                Value::Array(elements) => {
+                    self.unroll(elements.len(), &for_loop.expr)?;
+                    elements
+                }
+                _ => return Err(unbounded(&for_loop.expr)),
+            },
+        };
+
+        for item in items {
+            self.scopes.push(HashMap::new());
+            if let Some(index) = index {
+                self.bind(index, item, None);
+            }
+            self.block(&for_loop.body)?;
+            self.scopes.pop();
+        }
+        Ok(Value::Unit)
+    }
+
+    /// Counts `iterations` more unrolled iterations against the limit.
+    fn unroll(&mut self, iterations: usize, expr: &Expr) -> syn::Result<()> {
+        self.unrolled = self.unrolled.saturating_add(iterations);
+        if self.unrolled > self.unroll_limit {
+            return Err(syn::Error::new_spanned(
+                expr,
+                format!(
+                    "unrolling this loop exceeds the limit of {} iterations, raise it with \
+                     `#[encrypted(..., unroll = ...)]`",
+                    self.unroll_limit
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The value of a loop bound, which must be known when the function is compiled.
+    fn bound(&mut self, expr: &Expr) -> syn::Result<i128> {
+        match self.expr(expr)? {
+            Value::Const { value, .. } => Ok(value),
+            _ => Err(syn::Error::new_spanned(
+                expr,
+                "loop bounds must be constants known at compile time",
+            )),
+        }
+    }
+
+    /// Evaluates every branch and selects the value of the first one whose condition holds, the
+    /// last branch having none. Variables assigned in any branch are selected the same way.
+    fn branches(
+        &mut self,
+        conditions: &[Option<Var>],
+        branches: Vec<Branch>,
+        site: Site,
+    ) -> syn::Result<Value> {
+        let before = self.scopes.clone();
+        let mut values = Vec::new();
+        let mut scopes = Vec::new();
+        for branch in branches {
+            self.scopes = before.clone();
+            values.push(match branch {
+                Branch::Block(block) => self.block(block)?,
+                Branch::Expr(expr) => self.expr(expr)?,
+            });
+            scopes.push(std::mem::take(&mut self.scopes));
+        }
+
+        self.scopes = before;
+        for depth in 0..self.scopes.len() {
+            let names: Vec<String> = self.scopes[depth].keys().cloned().collect();
+            for name in names {
+                let assigned: Vec<Binding> = scopes
+                    .iter()
+                    .map(|scope| scope[depth][&name].clone())
+                    .collect();
+                if assigned
+                    .iter()
+                    .all(|binding| binding.value.same(&assigned[0].value))
+                {
+                    continue;
+                }
+                let ty = assigned.iter().find_map(|binding| binding.ty);
+                let values = assigned.into_iter().map(|binding| binding.value).collect();
+                let value = self.select(conditions, values, site)?;
+                self.scopes[depth].insert(name, Binding { value, ty });
+            }
+        }
+
+        if values.iter().any(|value| matches!(value, Value::Unit)) {
+            return Ok(Value::Unit);
+        }
+        self.select(conditions, values, site)
+    }
+
+    /// Multiplexes `values` into the first one whose condition holds, or else the last one.
+    fn select(
+        &mut self,
+        conditions: &[Option<Var>],
+        values: Vec<Value>,
+        site: Site,
+    ) -> syn::Result<Value> {
+        if values.iter().any(|value| matches!(value, Value::Array(_))) {
+            return self.select_elements(conditions, values, site);
+        }
+        let (vars, ty) = self.unify(values, site)?;
+        let mut vars = vars.into_iter().rev();
+        let mut selected = vars.next().expect("at least one branch");
+        for (condition, var) in conditions.iter().rev().zip(vars) {
+            selected = match condition {
+                Some(condition) => self.emit_var(
+                    quote! { context.mux(&#condition[0], &#var, &#selected) },
+                    ty,
+                ),
+                None => var,
+            };
+        }
+        Ok(Value::Wires { var: selected, ty })
+    }
+
+    /// Multiplexes arrays of the same length element by element.
+    fn select_elements(
+        &mut self,
+        conditions: &[Option<Var>],
+        values: Vec<Value>,
+        site: Site,
+    ) -> syn::Result<Value> {
+        let mut arrays = Vec::with_capacity(values.len());
+        for value in values {
+            match value {
+                Value::Array(elements) => arrays.push(elements),
+                _ => {
+                    return Err(site.error(
+                        "mismatched types: an array in one branch and a single value in another",
+                    ))
+                }
+            }
+        }
+        let len = arrays[0].len();
+        if arrays.iter().any(|elements| elements.len() != len) {
+            return Err(site.error("mismatched types: arrays of different lengths"));
+        }
+
+        let mut selected = Vec::with_capacity(len);
+        for position in 0..len {
+            let values = arrays
+                .iter()
+                .map(|elements| elements[position].clone())
+                .collect();
+            selected.push(self.select(conditions, values, site)?);
+        }
+        Ok(Value::Array(selected))
+    }
+}
+
+/// The name of the variable `expr` refers to.
+fn variable(expr: &Expr) -> syn::Result<&Ident> {
+    match expr {
+        Expr::Path(path) => path.path.get_ident(),
+        _ => None,
+    }
+    .ok_or_else(|| syn::Error::new_spanned(expr, "can only assign to a variable"))
+}
+
+fn unbounded(expr: &Expr) -> syn::Error {
+    syn::Error::new_spanned(
+        expr,
+        "encrypted functions can only loop over an array or a range with constant bounds, such \
+         as `0..8`",
+    )
+}
+
+fn out_of_bounds(site: Site, len: usize, index: i128) -> syn::Error {
+    site.error(format!(
+        "index out of bounds: the length is {} but the index is {}",
+        len, index
+    ))
+}
+
+fn mismatch(site: Site, a: Type, b: Type) -> syn::Error {
+    site.error(format!(
+        "mismatched types `{}` and `{}`, use `as` to convert",
+        a, b
+    ))
+}
+
+/// The operator a compound assignment applies, such as `+` for `+=`.
+fn compound_base(op: &BinOp) -> Option<BinOp> {
+    Some(match op {
+        BinOp::AddAssign(_) => BinOp::Add(Default::default()),
+        BinOp::SubAssign(_) => BinOp::Sub(Default::default()),
+        BinOp::MulAssign(_) => BinOp::Mul(Default::default()),
+        BinOp::DivAssign(_) => BinOp::Div(Default::default()),
+        BinOp::RemAssign(_) => BinOp::Rem(Default::default()),
+        BinOp::BitAndAssign(_) => BinOp::BitAnd(Default::default()),
+        BinOp::BitOrAssign(_) => BinOp::BitOr(Default::default()),
+        BinOp::BitXorAssign(_) => BinOp::BitXor(Default::default()),
+        BinOp::ShlAssign(_) => BinOp::Shl(Default::default()),
+        BinOp::ShrAssign(_) => BinOp::Shr(Default::default()),
+        _ => return None,
+    })
 }
@@ -0,0 +1,92 @@
+//! Asynchronous execution of the 2 Party MPC protocol.
+//!
+//! `run_garbler` and `run_evaluator` drive one party each over a `Transport`, so the two halves can
+//! run in different tasks, processes or machines. `AsyncLocalSimulator` runs both halves
+//! concurrently in the current process.
+
+use anyhow::Result;
+use std::future::Future;
+use tandem::Circuit;
+
+use crate::evaluator::{Evaluator, GatewayEvaluator};
+use crate::garbler::{Garbler, GatewayGarbler};
+use crate::transport::{ChannelTransport, Transport};
+
+pub trait AsyncExecutor {
+    /// Executes the 2 Party MPC protocol without blocking the async runtime between messages.
+    ///
+    /// # Arguments
+    /// * `circuit` - The circuit to be evaluated.
+    /// * `input_contributor` - Input provided by the contributor.
+    /// * `input_evaluator` - Input provided by the evaluator.
+    ///
+    /// # Returns
+    /// The result of the execution as a vector of booleans.
+    fn execute(
+        &self,
+        circuit: &Circuit,
+        input_contributor: &[bool],
+        input_evaluator: &[bool],
+    ) -> impl Future<Output = Result<Vec<bool>>> + Send;
+}
+
+/// Runs the garbler and the evaluator as concurrent futures connected by a `ChannelTransport`.
+pub struct AsyncLocalSimulator;
+
+impl AsyncExecutor for AsyncLocalSimulator {
+    async fn execute(
+        &self,
+        circuit: &Circuit,
+        input_contributor: &[bool],
+        input_evaluator: &[bool],
+    ) -> Result<Vec<bool>> {
+        let (garbler_transport, evaluator_transport) = ChannelTransport::pair();
+
+        // try_join drops both parties as soon as one fails, so the other is never left waiting
+        let ((), output) = tokio::try_join!(
+            run_garbler(garbler_transport, circuit, input_contributor),
+            run_evaluator(evaluator_transport, circuit, input_evaluator),
+        )?;
+        Ok(output)
+    }
+}
+
+/// Runs the garbler's side of the protocol with its `input` until the evaluator has everything it
+/// needs to compute the output.
+pub async fn run_garbler<T: Transport>(
+    mut transport: T,
+    circuit: &Circuit,
+    input: &[bool],
+) -> Result<()> {
+    let (mut garbler, message) = GatewayGarbler::start(circuit, input)?;
+    transport.send(&message).await?;
+
+    while !garbler.is_complete() {
+        let message = transport.recv().await?;
+        let (next_garbler, reply) = garbler.next(&message)?;
+        garbler = next_garbler;
+        transport.send(&reply).await?;
+    }
+
+    Ok(())
+}
+
+/// Runs the evaluator's side of the protocol with its `input` and returns the circuit output.
+pub async fn run_evaluator<T: Transport>(
+    mut transport: T,
+    circuit: &Circuit,
+    input: &[bool],
+) -> Result<Vec<bool>> {
+    let mut evaluator = GatewayEvaluator::new(circuit, input)?;
+
+    loop {
+        let message = transport.recv().await?;
+        if evaluator.is_complete() {
+            return evaluator.output(&message);
+        }
+
+        let (next_evaluator, reply) = evaluator.next(&message)?;
+        evaluator = next_evaluator;
+        transport.send(&reply).await?;
+    }
+}
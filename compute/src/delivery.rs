@@ -0,0 +1,253 @@
+//! Who learns which part of a circuit's output.
+//!
+//! An `OutputPolicy` names a `Recipient` for each element of the output schema, where a tuple
+//! schema has one element per field and any other schema is a single element. The evaluator learns
+//! every output bit of the circuit it evaluates, so elements for the garbler alone are masked: the
+//! circuit is extended with one random garbler input per masked bit, XORed onto that output bit,
+//! and only the garbler can remove the masks from what the evaluator forwards to it.
+
+use anyhow::Result;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter;
+use tandem::{Circuit, Gate, GateIndex};
+
+use crate::async_executor::{run_evaluator, run_garbler};
+use crate::output::{recv_output, send_output, Output, OutputError, OutputSchema};
+use crate::transport::Transport;
+
+/// The parties that learn an element of the output.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Recipient {
+    /// Only the garbler, through a masked output.
+    Garbler,
+    /// Only the evaluator, which does not forward the element.
+    Evaluator,
+    Both,
+}
+
+impl Recipient {
+    pub fn to_garbler(self) -> bool {
+        matches!(self, Recipient::Garbler | Recipient::Both)
+    }
+
+    pub fn to_evaluator(self) -> bool {
+        matches!(self, Recipient::Evaluator | Recipient::Both)
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recipient::Garbler => write!(f, "garbler"),
+            Recipient::Evaluator => write!(f, "evaluator"),
+            Recipient::Both => write!(f, "both"),
+        }
+    }
+}
+
+/// The recipients of the elements of a circuit's output.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputPolicy {
+    /// Both parties learn the whole output.
+    #[default]
+    Shared,
+    /// One recipient per element of the output schema, in order.
+    PerElement(Vec<Recipient>),
+}
+
+impl OutputPolicy {
+    /// The recipient of each element of `schema`.
+    pub fn recipients(&self, schema: &OutputSchema) -> Result<Vec<Recipient>, OutputError> {
+        let elements = schema.elements().len();
+        match self {
+            OutputPolicy::Shared => Ok(vec![Recipient::Both; elements]),
+            OutputPolicy::PerElement(recipients) if recipients.len() == elements => {
+                Ok(recipients.clone())
+            }
+            OutputPolicy::PerElement(recipients) => Err(OutputError::PolicyMismatch {
+                schema: schema.clone(),
+                recipients: recipients.len(),
+            }),
+        }
+    }
+
+    /// Whether both policies deliver every element of `schema` to the same parties.
+    pub fn agrees_with(&self, other: &OutputPolicy, schema: &OutputSchema) -> bool {
+        match (self.recipients(schema), other.recipients(schema)) {
+            (Ok(recipients), Ok(other)) => recipients == other,
+            _ => false,
+        }
+    }
+
+    /// Whether each output bit of `schema` is masked for the garbler.
+    pub fn masked_bits(&self, schema: &OutputSchema) -> Result<Vec<bool>, OutputError> {
+        let recipients = self.recipients(schema)?;
+        Ok(masked_bits(schema, &recipients))
+    }
+}
+
+impl fmt::Display for OutputPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputPolicy::Shared => write!(f, "shared"),
+            OutputPolicy::PerElement(recipients) => {
+                let recipients: Vec<String> = recipients.iter().map(Recipient::to_string).collect();
+                write!(f, "({})", recipients.join(", "))
+            }
+        }
+    }
+}
+
+fn masked_bits(schema: &OutputSchema, recipients: &[Recipient]) -> Vec<bool> {
+    schema
+        .elements()
+        .iter()
+        .zip(recipients)
+        .flat_map(|(element, recipient)| {
+            iter::repeat_n(*recipient == Recipient::Garbler, element.width())
+        })
+        .collect()
+}
+
+/// Extends `circuit` with a garbler input XORed onto every output bit selected by `masked`.
+///
+/// The mask inputs follow the circuit's own garbler inputs, in the order of the output bits they
+/// mask. Both parties derive the same circuit from the original one and the policy.
+pub fn mask_outputs(circuit: &Circuit, masked: &[bool]) -> Circuit {
+    let mut gates = circuit.gates().clone();
+    let mut outputs = circuit.output_gates().clone();
+    for (output, _) in outputs
+        .iter_mut()
+        .zip(masked)
+        .filter(|(_, masked)| **masked)
+    {
+        let mask = gates.len() as GateIndex;
+        gates.push(Gate::InContrib);
+        gates.push(Gate::Xor(*output, mask));
+        *output = mask + 1;
+    }
+    Circuit::new(gates, outputs)
+}
+
+/// The masked circuit and the recipient of each output element.
+fn prepare(
+    circuit: &Circuit,
+    schema: &OutputSchema,
+    policy: &OutputPolicy,
+) -> Result<(Circuit, Vec<Recipient>, Vec<bool>), OutputError> {
+    let outputs = circuit.output_gates().len();
+    if schema.width() != outputs {
+        return Err(OutputError::WidthMismatch {
+            schema: schema.clone(),
+            bits: outputs,
+        });
+    }
+    let recipients = policy.recipients(schema)?;
+    let masked = masked_bits(schema, &recipients);
+    Ok((mask_outputs(circuit, &masked), recipients, masked))
+}
+
+/// The elements of `schema` delivered to the parties selected by `delivered`, with their bits.
+///
+/// A single delivered element is returned on its own rather than as a one-field tuple, unless the
+/// schema is that tuple.
+fn select(
+    schema: &OutputSchema,
+    recipients: &[Recipient],
+    bits: &[bool],
+    delivered: fn(Recipient) -> bool,
+) -> Option<(OutputSchema, Vec<bool>)> {
+    if recipients.iter().all(|recipient| delivered(*recipient)) {
+        return Some((schema.clone(), bits.to_vec()));
+    }
+
+    let mut elements = Vec::new();
+    let mut selected = Vec::new();
+    let mut offset = 0;
+    for (element, recipient) in schema.elements().iter().zip(recipients) {
+        let width = element.width();
+        if delivered(*recipient) {
+            elements.push(element.clone());
+            selected.extend_from_slice(&bits[offset..offset + width]);
+        }
+        offset += width;
+    }
+    match elements.len() {
+        0 => None,
+        1 => Some((elements.remove(0), selected)),
+        _ => Some((OutputSchema::Tuple(elements), selected)),
+    }
+}
+
+/// Runs the garbler's side of `circuit` with its outputs delivered according to `policy`.
+///
+/// `inputs` are the garbler's inputs to `circuit`, and the masks for its elements are drawn here.
+/// Returns the elements delivered to the garbler, if any.
+pub async fn run_garbler_with_policy<T: Transport>(
+    mut transport: T,
+    circuit: &Circuit,
+    inputs: &[bool],
+    schema: &OutputSchema,
+    policy: &OutputPolicy,
+) -> Result<Option<Output>> {
+    let (circuit, recipients, masked) = prepare(circuit, schema, policy)?;
+    let mut rng = ChaCha20Rng::from_entropy();
+    let masks: Vec<bool> = masked.iter().map(|masked| *masked && rng.gen()).collect();
+    let inputs: Vec<bool> = inputs
+        .iter()
+        .copied()
+        .chain(
+            masked
+                .iter()
+                .zip(&masks)
+                .filter(|(masked, _)| **masked)
+                .map(|(_, mask)| *mask),
+        )
+        .collect();
+    run_garbler(&mut transport, &circuit, &inputs).await?;
+
+    let Some((schema, masks)) = select(schema, &recipients, &masks, Recipient::to_garbler) else {
+        return Ok(None);
+    };
+    let output = recv_output(&mut transport).await?;
+    if output.schema() != &schema {
+        return Err(OutputError::SchemaMismatch {
+            expected: schema,
+            found: output.schema().clone(),
+        }
+        .into());
+    }
+    let bits: Vec<bool> = output
+        .bits()
+        .into_iter()
+        .zip(masks)
+        .map(|(bit, mask)| bit ^ mask)
+        .collect();
+    Ok(Some(Output::new(schema, &bits)?))
+}
+
+/// Runs the evaluator's side of `circuit` with its outputs delivered according to `policy`.
+///
+/// Sends the garbler the elements delivered to it, still masked where they are the garbler's
+/// alone, and returns the elements delivered to the evaluator, if any.
+pub async fn run_evaluator_with_policy<T: Transport>(
+    mut transport: T,
+    circuit: &Circuit,
+    inputs: &[bool],
+    schema: &OutputSchema,
+    policy: &OutputPolicy,
+) -> Result<Option<Output>> {
+    let (circuit, recipients, _) = prepare(circuit, schema, policy)?;
+    let bits = run_evaluator(&mut transport, &circuit, inputs).await?;
+
+    if let Some((schema, bits)) = select(schema, &recipients, &bits, Recipient::to_garbler) {
+        send_output(&mut transport, &Output::new(schema, &bits)?).await?;
+    }
+    match select(schema, &recipients, &bits, Recipient::to_evaluator) {
+        Some((schema, bits)) => Ok(Some(Output::new(schema, &bits)?)),
+        None => Ok(None),
+    }
+}
@@ -1,7 +1,9 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use once_cell::sync::Lazy;
+use std::cell::RefCell;
+use std::marker::PhantomData;
 use std::sync::Arc;
-use tandem::Circuit;
+use tandem::{Circuit, Gate};
 
 use crate::evaluator::{Evaluator, GatewayEvaluator};
 use crate::garbler::{Garbler, GatewayGarbler};
@@ -10,9 +12,52 @@ use crate::garbler::{Garbler, GatewayGarbler};
 static SINGLETON_EXECUTOR: Lazy<Arc<dyn Executor + Send + Sync>> =
     Lazy::new(|| Arc::new(LocalSimulator) as Arc<dyn Executor + Send + Sync>);
 
-/// Provides access to the singleton Executor instance.
+thread_local! {
+    /// Executor installed for the current thread with `set_executor`, if any.
+    static THREAD_EXECUTOR: RefCell<Option<Arc<dyn Executor + Send + Sync>>> =
+        const { RefCell::new(None) };
+}
+
+/// Provides access to the Executor installed for the current thread, falling back to the
+/// singleton LocalSimulator.
 pub fn get_executor() -> Arc<dyn Executor + Send + Sync> {
-    SINGLETON_EXECUTOR.clone()
+    THREAD_EXECUTOR
+        .with(|executor| executor.borrow().clone())
+        .unwrap_or_else(|| SINGLETON_EXECUTOR.clone())
+}
+
+/// Installs `executor` for the current thread until the returned guard is dropped.
+///
+/// Everything that executes through `get_executor`, including `CircuitBuilder::execute` and
+/// `#[encrypted(execute)]` functions, uses the installed executor. Guards can be nested; dropping
+/// one restores the executor that was installed before it.
+pub fn set_executor(executor: Arc<dyn Executor + Send + Sync>) -> ExecutorGuard {
+    let previous = THREAD_EXECUTOR.with(|current| current.borrow_mut().replace(executor));
+    ExecutorGuard {
+        previous,
+        _thread_bound: PhantomData,
+    }
+}
+
+/// Runs `f` with `executor` installed for the current thread.
+pub fn with_executor<R>(executor: Arc<dyn Executor + Send + Sync>, f: impl FnOnce() -> R) -> R {
+    let _guard = set_executor(executor);
+    f()
+}
+
+/// Restores the previously installed executor when dropped.
+#[must_use = "the executor is uninstalled as soon as the guard is dropped"]
+pub struct ExecutorGuard {
+    previous: Option<Arc<dyn Executor + Send + Sync>>,
+    // The guard restores thread-local state, so it must stay on the thread that created it
+    _thread_bound: PhantomData<*const ()>,
+}
+
+impl Drop for ExecutorGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD_EXECUTOR.with(|current| *current.borrow_mut() = previous);
+    }
 }
 
 pub trait Executor {
@@ -74,3 +119,58 @@ impl Executor for LocalSimulator {
         Ok(output)
     }
 }
+
+/// Evaluates circuits directly over plaintext bools, without running the MPC protocol.
+///
+/// This is a reference for debugging and testing circuits: it computes the same outputs as the
+/// `LocalSimulator` but offers no privacy at all, so it must never be used with real inputs.
+pub struct PlaintextExecutor;
+
+impl Executor for PlaintextExecutor {
+    fn execute(
+        &self,
+        circuit: &Circuit,
+        input_contributor: &[bool],
+        input_evaluator: &[bool],
+    ) -> Result<Vec<bool>> {
+        let mut contributor = input_contributor.iter();
+        let mut evaluator = input_evaluator.iter();
+        let mut wires: Vec<bool> = Vec::with_capacity(circuit.gates().len());
+
+        for gate in circuit.gates() {
+            let value = match gate {
+                Gate::InContrib => *contributor
+                    .next()
+                    .ok_or_else(|| anyhow!("Not enough contributor inputs for the circuit"))?,
+                Gate::InEval => *evaluator
+                    .next()
+                    .ok_or_else(|| anyhow!("Not enough evaluator inputs for the circuit"))?,
+                Gate::Xor(a, b) => wire(&wires, *a)? ^ wire(&wires, *b)?,
+                Gate::And(a, b) => wire(&wires, *a)? & wire(&wires, *b)?,
+                Gate::Not(a) => !wire(&wires, *a)?,
+            };
+            wires.push(value);
+        }
+
+        if contributor.next().is_some() {
+            bail!("Too many contributor inputs for the circuit");
+        }
+        if evaluator.next().is_some() {
+            bail!("Too many evaluator inputs for the circuit");
+        }
+
+        circuit
+            .output_gates()
+            .iter()
+            .map(|output| wire(&wires, *output))
+            .collect()
+    }
+}
+
+// Gates may only reference earlier gates, so a missing wire means the circuit is malformed
+fn wire(wires: &[bool], index: u32) -> Result<bool> {
+    wires
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| anyhow!("Gate {} is referenced before it is defined", index))
+}
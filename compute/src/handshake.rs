@@ -0,0 +1,267 @@
+//! Agreeing on the circuit before garbling starts.
+//!
+//! The garbler opens every session with a `CircuitProposal`. The evaluator resolves it against its
+//! `CircuitRegistry` and replies with a `HandshakeReply`; on a rejection both sides stop before
+//! any garbled material is exchanged, instead of failing somewhere inside the protocol. The proposal
+//! also states the `OutputPolicy` the garbler expects, so neither party learns more of the output
+//! than the other agreed to. Before proposing, the garbler may ask for the metadata of every
+//! registered circuit.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use tandem::Circuit;
+
+use crate::delivery::OutputPolicy;
+use crate::operations::util::{deserialize_circuit, serialize_circuit};
+use crate::registry::{CircuitHash, CircuitInfo, CircuitRegistry};
+use crate::transport::{MessageKind, Transport};
+
+/// The circuit the garbler wants to run in a session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum CircuitProposal {
+    /// A circuit registered on the evaluator under `id`, which must hash to `hash`.
+    Named { id: String, hash: CircuitHash },
+    /// Any circuit the evaluator has registered with this hash.
+    Hash(CircuitHash),
+    /// A circuit serialized with `serialize_circuit`, which the evaluator must allow by hash.
+    Upload(Vec<u8>),
+}
+
+impl CircuitProposal {
+    pub fn named(id: impl Into<String>, circuit: &Circuit) -> Self {
+        CircuitProposal::Named {
+            id: id.into(),
+            hash: circuit.blake3_hash(),
+        }
+    }
+
+    pub fn hash(circuit: &Circuit) -> Self {
+        CircuitProposal::Hash(circuit.blake3_hash())
+    }
+
+    pub fn upload(circuit: &Circuit) -> Result<Self> {
+        Ok(CircuitProposal::Upload(serialize_circuit(circuit)?))
+    }
+}
+
+/// A handshake message from the garbler.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeRequest {
+    /// Asks for the metadata of every circuit registered on the evaluator.
+    ListCircuits,
+    /// Selects the circuit for this session and the delivery of its output, which ends the
+    /// handshake once accepted.
+    Propose(CircuitProposal, OutputPolicy),
+}
+
+/// The evaluator's answer to a `HandshakeRequest`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeReply {
+    Circuits(Vec<CircuitInfo>),
+    Accepted,
+    Rejected(HandshakeError),
+}
+
+/// Why the evaluator refused a proposal.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    /// No circuit is registered under the proposed id.
+    UnknownCircuit(String),
+    /// The circuit registered under the id differs from the garbler's.
+    HashMismatch {
+        id: String,
+        expected: CircuitHash,
+        proposed: CircuitHash,
+    },
+    /// No circuit with the proposed hash is registered or allowed for upload.
+    NotAllowed(CircuitHash),
+    /// The uploaded circuit could not be deserialized.
+    InvalidCircuit(String),
+    /// The uploaded circuit has more gates than the evaluator accepts.
+    CircuitTooLarge { gates: usize, max: usize },
+    /// The upload is longer than any circuit within the evaluator's gate limit.
+    UploadTooLarge { bytes: usize, max: usize },
+    /// The evaluator delivers the circuit's output according to a different policy.
+    PolicyMismatch {
+        expected: OutputPolicy,
+        proposed: OutputPolicy,
+    },
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::UnknownCircuit(id) => write!(f, "Unknown circuit '{}'", id),
+            HandshakeError::HashMismatch {
+                id,
+                expected,
+                proposed,
+            } => write!(
+                f,
+                "Circuit '{}' has hash {} on the evaluator but {} was proposed",
+                id,
+                hex::encode(expected),
+                hex::encode(proposed)
+            ),
+            HandshakeError::NotAllowed(hash) => {
+                write!(f, "Circuit {} is not allowed", hex::encode(hash))
+            }
+            HandshakeError::InvalidCircuit(reason) => {
+                write!(f, "Invalid circuit upload: {}", reason)
+            }
+            HandshakeError::CircuitTooLarge { gates, max } => {
+                write!(
+                    f,
+                    "Circuit has {} gates, at most {} are allowed",
+                    gates, max
+                )
+            }
+            HandshakeError::UploadTooLarge { bytes, max } => {
+                write!(
+                    f,
+                    "Circuit upload has {} bytes, at most {} are allowed",
+                    bytes, max
+                )
+            }
+            HandshakeError::PolicyMismatch { expected, proposed } => write!(
+                f,
+                "The evaluator delivers the output as {} but {} was proposed",
+                expected, proposed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+/// Asks the evaluator for the metadata of every circuit it has registered.
+///
+/// The session stays in the handshake, so a proposal can follow on the same transport.
+pub async fn list_circuits<T: Transport>(mut transport: T) -> Result<Vec<CircuitInfo>> {
+    match request(&mut transport, &HandshakeRequest::ListCircuits).await? {
+        HandshakeReply::Circuits(infos) => Ok(infos),
+        HandshakeReply::Rejected(error) => Err(error.into()),
+        HandshakeReply::Accepted => bail!("Unexpected handshake reply to a circuit listing"),
+    }
+}
+
+/// Proposes a circuit whose output both parties learn and waits for it to be accepted.
+///
+/// Fails with the evaluator's `HandshakeError` if the proposal is rejected.
+pub async fn propose_circuit<T: Transport>(transport: T, proposal: &CircuitProposal) -> Result<()> {
+    propose_circuit_with_policy(transport, proposal, &OutputPolicy::Shared).await
+}
+
+/// Proposes a circuit whose output is delivered according to `policy` and waits for it to be
+/// accepted.
+///
+/// The evaluator rejects the proposal unless it delivers the output the same way.
+pub async fn propose_circuit_with_policy<T: Transport>(
+    mut transport: T,
+    proposal: &CircuitProposal,
+    policy: &OutputPolicy,
+) -> Result<()> {
+    let proposal = HandshakeRequest::Propose(proposal.clone(), policy.clone());
+    match request(&mut transport, &proposal).await? {
+        HandshakeReply::Accepted => Ok(()),
+        HandshakeReply::Rejected(error) => Err(error.into()),
+        HandshakeReply::Circuits(_) => bail!("Unexpected handshake reply to a proposal"),
+    }
+}
+
+async fn request<T: Transport>(
+    transport: &mut T,
+    request: &HandshakeRequest,
+) -> Result<HandshakeReply> {
+    transport
+        .send_message(MessageKind::Handshake, &bincode::serialize(request)?)
+        .await?;
+    let reply = transport.recv_kind(MessageKind::Handshake).await?;
+    Ok(bincode::deserialize(&reply)?)
+}
+
+/// Answers the garbler's handshake requests until it proposes a circuit, and returns the agreed
+/// circuit.
+///
+/// The garbler has agreed to the output schema and policy the registry holds for the circuit.
+/// A rejected proposal is reported to the garbler before this returns the same error.
+pub async fn accept_circuit<T: Transport>(
+    mut transport: T,
+    registry: &CircuitRegistry,
+) -> Result<Arc<Circuit>> {
+    loop {
+        let request = transport.recv_kind(MessageKind::Handshake).await?;
+        let (reply, resolved) = match bincode::deserialize(&request)? {
+            HandshakeRequest::ListCircuits => (HandshakeReply::Circuits(registry.infos()), None),
+            HandshakeRequest::Propose(proposal, policy) => {
+                let resolved = resolve(registry, proposal).and_then(|circuit| {
+                    check_policy(registry, &circuit, policy)?;
+                    Ok(circuit)
+                });
+                let reply = match &resolved {
+                    Ok(_) => HandshakeReply::Accepted,
+                    Err(error) => HandshakeReply::Rejected(error.clone()),
+                };
+                (reply, Some(resolved))
+            }
+        };
+        transport
+            .send_message(MessageKind::Handshake, &bincode::serialize(&reply)?)
+            .await?;
+
+        if let Some(resolved) = resolved {
+            return Ok(resolved?);
+        }
+    }
+}
+
+fn check_policy(
+    registry: &CircuitRegistry,
+    circuit: &Circuit,
+    proposed: OutputPolicy,
+) -> Result<(), HandshakeError> {
+    let expected = registry.output_policy(circuit);
+    if !expected.agrees_with(&proposed, &registry.output_schema(circuit)) {
+        return Err(HandshakeError::PolicyMismatch { expected, proposed });
+    }
+    Ok(())
+}
+
+fn resolve(
+    registry: &CircuitRegistry,
+    proposal: CircuitProposal,
+) -> Result<Arc<Circuit>, HandshakeError> {
+    match proposal {
+        CircuitProposal::Named { id, hash } => {
+            let circuit = registry
+                .get(&id)
+                .ok_or_else(|| HandshakeError::UnknownCircuit(id.clone()))?;
+            let expected = circuit.blake3_hash();
+            if expected != hash {
+                return Err(HandshakeError::HashMismatch {
+                    id,
+                    expected,
+                    proposed: hash,
+                });
+            }
+            Ok(circuit.clone())
+        }
+        CircuitProposal::Hash(hash) => registry
+            .get_by_hash(&hash)
+            .cloned()
+            .ok_or(HandshakeError::NotAllowed(hash)),
+        CircuitProposal::Upload(data) => {
+            registry.check_upload_size(data.len())?;
+            let circuit = deserialize_circuit(&data)
+                .map_err(|e| HandshakeError::InvalidCircuit(e.to_string()))?;
+            let hash = circuit.blake3_hash();
+            if !registry.is_allowed(&hash) {
+                return Err(HandshakeError::NotAllowed(hash));
+            }
+            registry.check_size(&circuit)?;
+            Ok(Arc::new(circuit))
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Lazy garbled values.
+//!
+//! Operators on `GarbledUint` build and run a separate circuit for every operation. A `LazyUint`
+//! instead records its operations into the thread's shared `CircuitBuilder`, so a composed
+//! expression is compiled and executed as a single circuit when `reveal` is called.
+//!
+//! The shared circuit keeps growing until it is reset, so each computation should run inside a
+//! `LazySession`, which starts from an empty circuit and clears it again when dropped.
+
+use crate::operations::circuits::builder::CircuitBuilder;
+use crate::operations::circuits::types::GateIndexVec;
+use crate::uint::GarbledUint;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Div, DivAssign,
+    Mul, MulAssign, Not, Rem, RemAssign, Shl, Shr, Sub, SubAssign,
+};
+use tandem::Circuit;
+
+pub type LazyBoolean = LazyUint<1>;
+pub type LazyUint8 = LazyUint<8>;
+pub type LazyUint16 = LazyUint<16>;
+pub type LazyUint32 = LazyUint<32>;
+pub type LazyUint64 = LazyUint<64>;
+pub type LazyUint128 = LazyUint<128>;
+
+thread_local! {
+    // Bumped whenever the shared builder is reset, so values from an earlier session are caught
+    static SESSION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Clears the thread's shared circuit, invalidating every `LazyUint` created before the call.
+pub fn reset() {
+    CircuitBuilder::reset_global();
+    SESSION.with(|session| session.set(session.get() + 1));
+}
+
+/// Starts a lazy session on the current thread.
+///
+/// The shared circuit is cleared when the session starts and again when the returned guard is
+/// dropped, so every `reveal` inside it only contains the values created since. Values from before
+/// the session cannot be used inside it, and sessions are not meant to be nested.
+pub fn lazy_session() -> LazySession {
+    reset();
+    LazySession {
+        _thread_bound: PhantomData,
+    }
+}
+
+/// Runs `f` inside a lazy session.
+pub fn with_lazy_session<R>(f: impl FnOnce() -> R) -> R {
+    let _session = lazy_session();
+    f()
+}
+
+/// Clears the thread's shared circuit when dropped.
+#[must_use = "the shared circuit is cleared as soon as the session is dropped"]
+pub struct LazySession {
+    // The session clears thread-local state, so it must stay on the thread that created it
+    _thread_bound: PhantomData<*const ()>,
+}
+
+impl Drop for LazySession {
+    fn drop(&mut self) {
+        reset();
+    }
+}
+
+fn current_session() -> u64 {
+    SESSION.with(|session| session.get())
+}
+
+/// An unsigned integer whose value is a set of wires in the thread's shared circuit.
+#[derive(Debug, Clone)]
+pub struct LazyUint<const N: usize> {
+    wires: GateIndexVec,
+    session: u64,
+    _phantom: PhantomData<[bool; N]>,
+}
+
+impl<const N: usize> LazyUint<N> {
+    fn from_wires(wires: GateIndexVec) -> Self {
+        LazyUint {
+            wires,
+            session: current_session(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn wires(&self) -> &GateIndexVec {
+        assert_eq!(
+            self.session,
+            current_session(),
+            "LazyUint used after the shared circuit was reset"
+        );
+        &self.wires
+    }
+
+    /// Adds a garbler input to the shared circuit.
+    pub fn input(value: &GarbledUint<N>) -> Self {
+        Self::from_wires(CircuitBuilder::global(|builder| builder.input(value)))
+    }
+
+    /// Adds an evaluator input to the shared circuit.
+    pub fn input_eval(value: &GarbledUint<N>) -> Self {
+        Self::from_wires(CircuitBuilder::global(|builder| builder.input_eval(value)))
+    }
+
+    /// Adds a public constant to the shared circuit.
+    pub fn constant(value: &GarbledUint<N>) -> Self {
+        Self::from_wires(CircuitBuilder::global(|builder| builder.constant(value)))
+    }
+
+    /// Compiles the shared circuit with this value as its output, without executing it.
+    pub fn compile(&self) -> Circuit {
+        let wires = self.wires();
+        CircuitBuilder::global(|builder| builder.compile(wires))
+    }
+
+    /// Compiles the shared circuit with this value as its output and executes it.
+    pub fn reveal(&self) -> GarbledUint<N> {
+        let wires = self.wires();
+        CircuitBuilder::global(|builder| builder.compile_and_execute::<N>(wires))
+            .expect("Failed to execute lazy circuit")
+    }
+
+    fn unary(&self, op: impl FnOnce(&mut CircuitBuilder, &GateIndexVec) -> GateIndexVec) -> Self {
+        let a = self.wires();
+        Self::from_wires(CircuitBuilder::global(|builder| op(builder, a)))
+    }
+
+    fn binary<const R: usize>(
+        &self,
+        rhs: &Self,
+        op: impl FnOnce(&mut CircuitBuilder, &GateIndexVec, &GateIndexVec) -> GateIndexVec,
+    ) -> LazyUint<R> {
+        let a = self.wires();
+        let b = rhs.wires();
+        LazyUint::from_wires(CircuitBuilder::global(|builder| op(builder, a, b)))
+    }
+
+    pub fn eq(&self, rhs: &Self) -> LazyBoolean {
+        self.binary(rhs, |builder, a, b| builder.eq(a, b).into())
+    }
+
+    pub fn ne(&self, rhs: &Self) -> LazyBoolean {
+        self.binary(rhs, |builder, a, b| builder.ne(a, b).into())
+    }
+
+    pub fn lt(&self, rhs: &Self) -> LazyBoolean {
+        self.binary(rhs, |builder, a, b| builder.lt(a, b).into())
+    }
+
+    pub fn le(&self, rhs: &Self) -> LazyBoolean {
+        self.binary(rhs, |builder, a, b| builder.le(a, b).into())
+    }
+
+    pub fn gt(&self, rhs: &Self) -> LazyBoolean {
+        self.binary(rhs, |builder, a, b| builder.gt(a, b).into())
+    }
+
+    pub fn ge(&self, rhs: &Self) -> LazyBoolean {
+        self.binary(rhs, |builder, a, b| builder.ge(a, b).into())
+    }
+
+    /// Selects `if_true` when `condition` is set and `if_false` otherwise.
+    pub fn mux(condition: &LazyBoolean, if_true: &Self, if_false: &Self) -> Self {
+        let s = condition.wires()[0];
+        if_true.binary(if_false, |builder, a, b| builder.mux(&s, a, b))
+    }
+}
+
+impl<const N: usize> GarbledUint<N> {
+    /// Adds this value to the thread's shared circuit as a garbler input.
+    pub fn lazy(&self) -> LazyUint<N> {
+        LazyUint::input(self)
+    }
+}
+
+impl<const N: usize> From<GarbledUint<N>> for LazyUint<N> {
+    fn from(value: GarbledUint<N>) -> Self {
+        LazyUint::input(&value)
+    }
+}
+
+impl<const N: usize> From<&GarbledUint<N>> for LazyUint<N> {
+    fn from(value: &GarbledUint<N>) -> Self {
+        LazyUint::input(value)
+    }
+}
+
+macro_rules! lazy_binary_op {
+    ($trait:ident, $fn_name:ident, $assign_trait:ident, $assign_fn_name:ident, $op:ident) => {
+        impl<const N: usize> $trait for LazyUint<N> {
+            type Output = Self;
+
+            fn $fn_name(self, rhs: Self) -> Self::Output {
+                self.binary(&rhs, CircuitBuilder::$op)
+            }
+        }
+
+        impl<const N: usize> $trait<&LazyUint<N>> for LazyUint<N> {
+            type Output = Self;
+
+            fn $fn_name(self, rhs: &Self) -> Self::Output {
+                self.binary(rhs, CircuitBuilder::$op)
+            }
+        }
+
+        impl<const N: usize> $trait for &LazyUint<N> {
+            type Output = LazyUint<N>;
+
+            fn $fn_name(self, rhs: Self) -> Self::Output {
+                self.binary(rhs, CircuitBuilder::$op)
+            }
+        }
+
+        impl<const N: usize> $assign_trait for LazyUint<N> {
+            fn $assign_fn_name(&mut self, rhs: Self) {
+                *self = self.binary(&rhs, CircuitBuilder::$op);
+            }
+        }
+
+        impl<const N: usize> $assign_trait<&LazyUint<N>> for LazyUint<N> {
+            fn $assign_fn_name(&mut self, rhs: &Self) {
+                *self = self.binary(rhs, CircuitBuilder::$op);
+            }
+        }
+    };
+}
+
+lazy_binary_op!(Add, add, AddAssign, add_assign, add);
+lazy_binary_op!(Sub, sub, SubAssign, sub_assign, sub);
+lazy_binary_op!(Mul, mul, MulAssign, mul_assign, mul);
+lazy_binary_op!(Div, div, DivAssign, div_assign, div);
+lazy_binary_op!(Rem, rem, RemAssign, rem_assign, rem);
+lazy_binary_op!(BitAnd, bitand, BitAndAssign, bitand_assign, and);
+lazy_binary_op!(BitOr, bitor, BitOrAssign, bitor_assign, or);
+lazy_binary_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, xor);
+
+impl<const N: usize> Not for LazyUint<N> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        self.unary(CircuitBuilder::not)
+    }
+}
+
+impl<const N: usize> Not for &LazyUint<N> {
+    type Output = LazyUint<N>;
+
+    fn not(self) -> Self::Output {
+        self.unary(CircuitBuilder::not)
+    }
+}
+
+impl<const N: usize> Shl<usize> for LazyUint<N> {
+    type Output = Self;
+
+    fn shl(self, shift: usize) -> Self::Output {
+        self.unary(|builder, a| builder.shl(a, shift))
+    }
+}
+
+impl<const N: usize> Shl<usize> for &LazyUint<N> {
+    type Output = LazyUint<N>;
+
+    fn shl(self, shift: usize) -> Self::Output {
+        self.unary(|builder, a| builder.shl(a, shift))
+    }
+}
+
+impl<const N: usize> Shr<usize> for LazyUint<N> {
+    type Output = Self;
+
+    fn shr(self, shift: usize) -> Self::Output {
+        self.unary(|builder, a| builder.shr(a, shift))
+    }
+}
+
+impl<const N: usize> Shr<usize> for &LazyUint<N> {
+    type Output = LazyUint<N>;
+
+    fn shr(self, shift: usize) -> Self::Output {
+        self.unary(|builder, a| builder.shr(a, shift))
+    }
+}
@@ -1,22 +1,56 @@
+pub mod async_executor;
+pub mod delivery;
 pub mod evaluator;
 pub mod executor;
 pub mod garbler;
+pub mod handshake;
 pub mod int;
+pub mod lazy;
 pub mod operations;
+pub mod output;
+pub mod registry;
+pub mod session;
+pub mod transport;
 pub mod uint;
 
 pub mod prelude {
     pub use crate::operations::circuits::builder::CircuitBuilder;
 
-    pub use crate::executor::get_executor;
+    pub use crate::async_executor::{
+        run_evaluator, run_garbler, AsyncExecutor, AsyncLocalSimulator,
+    };
+
+    pub use crate::delivery::{
+        run_evaluator_with_policy, run_garbler_with_policy, OutputPolicy, Recipient,
+    };
+    pub use crate::executor::{
+        get_executor, set_executor, with_executor, Executor, ExecutorGuard, LocalSimulator,
+        PlaintextExecutor,
+    };
+    pub use crate::handshake::{
+        accept_circuit, list_circuits, propose_circuit, propose_circuit_with_policy,
+        CircuitProposal,
+    };
     pub use crate::int::{
         GarbledInt, GarbledInt128, GarbledInt16, GarbledInt256, GarbledInt32, GarbledInt512,
         GarbledInt64, GarbledInt8,
     };
+    pub use crate::lazy::{
+        lazy_session, with_lazy_session, LazyBoolean, LazySession, LazyUint, LazyUint128,
+        LazyUint16, LazyUint32, LazyUint64, LazyUint8,
+    };
     pub use crate::operations::circuits::types::GateIndexVec;
+    pub use crate::output::{recv_output, send_output, FromOutput, Output, OutputSchema};
+    pub use crate::registry::{CircuitInfo, CircuitRegistry, InputWidths};
+    pub use crate::session::{report_error, PeerError, SessionError};
+    pub use crate::transport::{
+        ChannelTransport, MemoryTransport, MessageKind, QuicTransport, StreamTransport,
+        TcpTransport, Transport,
+    };
     pub use crate::uint::{
         GarbledBoolean, GarbledUint, GarbledUint128, GarbledUint16, GarbledUint2, GarbledUint256,
-        GarbledUint32, GarbledUint4, GarbledUint512, GarbledUint64, GarbledUint8,
+        GarbledUint32, GarbledUint4, GarbledUint512, GarbledUint64, GarbledUint8, Instantiate,
+        Plaintext,
     };
     pub use circuit_macro::encrypted;
     pub use tandem::{Circuit, Gate};
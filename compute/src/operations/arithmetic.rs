@@ -4,9 +4,15 @@ use crate::operations::circuits::builder::{
     build_and_execute_subtraction,
 };
 use crate::uint::GarbledUint;
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Rem, RemAssign, Sub, SubAssign};
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
 
-use super::circuits::builder::build_and_execute_remainder;
+use super::circuits::builder::{
+    build_and_execute_abs, build_and_execute_neg, build_and_execute_remainder,
+    build_and_execute_signed_division, build_and_execute_signed_remainder,
+    build_and_execute_signum, build_and_execute_widening_multiplication,
+};
 
 // Implement the Add operation for Uint<N> and &GarbledUint<N>
 impl<const N: usize> Add for GarbledUint<N> {
@@ -68,6 +74,13 @@ impl<const N: usize> SubAssign<&GarbledUint<N>> for GarbledUint<N> {
     }
 }
 
+impl<const N: usize> GarbledUint<N> {
+    // Multiply without overflow, returning the low and high halves of the full product
+    pub fn widening_mul(&self, rhs: &Self) -> (GarbledUint<N>, GarbledUint<N>) {
+        build_and_execute_widening_multiplication(self, rhs)
+    }
+}
+
 impl<const N: usize> Mul for GarbledUint<N> {
     type Output = GarbledUint<N>;
 
@@ -251,7 +264,7 @@ impl<const N: usize> Div for GarbledInt<N> {
     type Output = Self;
 
     fn div(self, rhs: Self) -> Self::Output {
-        build_and_execute_division(&self.into(), &rhs.into()).into()
+        build_and_execute_signed_division(&self.into(), &rhs.into()).into()
     }
 }
 
@@ -259,20 +272,20 @@ impl<const N: usize> Div for &GarbledInt<N> {
     type Output = GarbledInt<N>;
 
     fn div(self, rhs: Self) -> Self::Output {
-        build_and_execute_division(&self.into(), &rhs.into()).into()
+        build_and_execute_signed_division(&self.into(), &rhs.into()).into()
     }
 }
 
 // Implement the DivAssign operation for GarbledInt<N> and &GarbledInt<N>
 impl<const N: usize> DivAssign for GarbledInt<N> {
     fn div_assign(&mut self, rhs: Self) {
-        *self = build_and_execute_division(&self.clone().into(), &rhs.into()).into();
+        *self = build_and_execute_signed_division(&self.clone().into(), &rhs.into()).into();
     }
 }
 
 impl<const N: usize> DivAssign<&GarbledInt<N>> for GarbledInt<N> {
     fn div_assign(&mut self, rhs: &Self) {
-        *self = build_and_execute_division(&self.clone().into(), &rhs.into()).into();
+        *self = build_and_execute_signed_division(&self.clone().into(), &rhs.into()).into();
     }
 }
 
@@ -281,7 +294,7 @@ impl<const N: usize> Rem for GarbledInt<N> {
     type Output = Self;
 
     fn rem(self, rhs: Self) -> Self::Output {
-        build_and_execute_remainder(&self.into(), &rhs.into()).into()
+        build_and_execute_signed_remainder(&self.into(), &rhs.into()).into()
     }
 }
 
@@ -289,19 +302,48 @@ impl<const N: usize> Rem for &GarbledInt<N> {
     type Output = GarbledInt<N>;
 
     fn rem(self, rhs: Self) -> Self::Output {
-        build_and_execute_remainder(&self.into(), &rhs.into()).into()
+        build_and_execute_signed_remainder(&self.into(), &rhs.into()).into()
     }
 }
 
 // Implement the RemAssign operation for GarbledInt<N> and &GarbledInt<N>
 impl<const N: usize> RemAssign for GarbledInt<N> {
     fn rem_assign(&mut self, rhs: Self) {
-        *self = build_and_execute_remainder(&self.clone().into(), &rhs.into()).into();
+        *self = build_and_execute_signed_remainder(&self.clone().into(), &rhs.into()).into();
     }
 }
 
 impl<const N: usize> RemAssign<&GarbledInt<N>> for GarbledInt<N> {
     fn rem_assign(&mut self, rhs: &Self) {
-        *self = build_and_execute_remainder(&self.clone().into(), &rhs.into()).into();
+        *self = build_and_execute_signed_remainder(&self.clone().into(), &rhs.into()).into();
+    }
+}
+
+// Implement the Neg operation for GarbledInt<N> and &GarbledInt<N>
+impl<const N: usize> Neg for GarbledInt<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        build_and_execute_neg(&self.into()).into()
+    }
+}
+
+impl<const N: usize> Neg for &GarbledInt<N> {
+    type Output = GarbledInt<N>;
+
+    fn neg(self) -> Self::Output {
+        build_and_execute_neg(&self.into()).into()
+    }
+}
+
+impl<const N: usize> GarbledInt<N> {
+    // Absolute value, wrapping for the minimum value like `i8::wrapping_abs`
+    pub fn abs(&self) -> GarbledInt<N> {
+        build_and_execute_abs(&self.into()).into()
+    }
+
+    // -1, 0 or 1 depending on the sign of the value
+    pub fn signum(&self) -> GarbledInt<N> {
+        build_and_execute_signum(&self.into()).into()
     }
 }
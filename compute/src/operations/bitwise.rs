@@ -1,7 +1,8 @@
 use crate::int::GarbledInt;
 use crate::operations::circuits::builder::{
     build_and_execute_and, build_and_execute_nand, build_and_execute_nor, build_and_execute_not,
-    build_and_execute_or, build_and_execute_xnor, build_and_execute_xor,
+    build_and_execute_or, build_and_execute_sar, build_and_execute_shl, build_and_execute_shr,
+    build_and_execute_xnor, build_and_execute_xor,
 };
 use crate::uint::GarbledUint;
 use std::ops::{
@@ -252,6 +253,14 @@ fn shift_bits_right<const N: usize>(bits: &mut Vec<bool>, shift: usize) {
     }
 }
 
+fn shift_bits_right_arithmetic<const N: usize>(bits: &mut Vec<bool>, shift: usize) {
+    for _ in 0..shift {
+        let sign = bits[N - 1];
+        bits.remove(0); // Remove the least significant bit
+        bits.push(sign); // Copy the sign into the most significant bit
+    }
+}
+
 // Implement Shift Left operation for Uint<N>
 impl<const N: usize> Shl<usize> for GarbledUint<N> {
     type Output = Self;
@@ -361,13 +370,13 @@ impl<const N: usize> ShrAssign<usize> for &GarbledUint<N> {
     }
 }
 
-// Implement Shift Right operation for GarbledInt<N>
+// Implement arithmetic Shift Right operation for GarbledInt<N>
 impl<const N: usize> Shr<usize> for GarbledInt<N> {
     type Output = Self;
 
     fn shr(self, shift: usize) -> Self::Output {
         let mut bits = self.bits;
-        shift_bits_right::<N>(&mut bits, shift);
+        shift_bits_right_arithmetic::<N>(&mut bits, shift);
         GarbledInt::new(bits)
     }
 }
@@ -378,7 +387,7 @@ impl<const N: usize> Shr<usize> for &GarbledInt<N> {
 
     fn shr(self, shift: usize) -> Self::Output {
         let mut bits = self.bits.clone();
-        shift_bits_right::<N>(&mut bits, shift);
+        shift_bits_right_arithmetic::<N>(&mut bits, shift);
         GarbledInt::new(bits)
     }
 }
@@ -386,7 +395,7 @@ impl<const N: usize> Shr<usize> for &GarbledInt<N> {
 // Implement ShrAssign for GarbledInt<N>
 impl<const N: usize> ShrAssign<usize> for GarbledInt<N> {
     fn shr_assign(&mut self, shift: usize) {
-        shift_bits_right::<N>(&mut self.bits, shift);
+        shift_bits_right_arithmetic::<N>(&mut self.bits, shift);
     }
 }
 
@@ -394,7 +403,154 @@ impl<const N: usize> ShrAssign<usize> for GarbledInt<N> {
 impl<const N: usize> ShrAssign<usize> for &GarbledInt<N> {
     fn shr_assign(&mut self, shift: usize) {
         let mut bits: Vec<bool> = self.bits.clone();
-        shift_bits_right::<N>(&mut bits, shift);
+        shift_bits_right_arithmetic::<N>(&mut bits, shift);
+    }
+}
+
+// Implement Shift Left by a secret amount for GarbledUint<N>
+impl<const N: usize> Shl<GarbledUint<N>> for GarbledUint<N> {
+    type Output = Self;
+
+    fn shl(self, shift: GarbledUint<N>) -> Self::Output {
+        build_and_execute_shl(&self, &shift)
+    }
+}
+
+impl<const N: usize> Shl<&GarbledUint<N>> for &GarbledUint<N> {
+    type Output = GarbledUint<N>;
+
+    fn shl(self, shift: &GarbledUint<N>) -> Self::Output {
+        build_and_execute_shl(self, shift)
+    }
+}
+
+impl<const N: usize> ShlAssign<GarbledUint<N>> for GarbledUint<N> {
+    fn shl_assign(&mut self, shift: GarbledUint<N>) {
+        *self = build_and_execute_shl(self, &shift);
+    }
+}
+
+impl<const N: usize> ShlAssign<&GarbledUint<N>> for GarbledUint<N> {
+    fn shl_assign(&mut self, shift: &GarbledUint<N>) {
+        *self = build_and_execute_shl(self, shift);
+    }
+}
+
+// Implement Shift Right by a secret amount for GarbledUint<N>
+impl<const N: usize> Shr<GarbledUint<N>> for GarbledUint<N> {
+    type Output = Self;
+
+    fn shr(self, shift: GarbledUint<N>) -> Self::Output {
+        build_and_execute_shr(&self, &shift)
+    }
+}
+
+impl<const N: usize> Shr<&GarbledUint<N>> for &GarbledUint<N> {
+    type Output = GarbledUint<N>;
+
+    fn shr(self, shift: &GarbledUint<N>) -> Self::Output {
+        build_and_execute_shr(self, shift)
+    }
+}
+
+impl<const N: usize> ShrAssign<GarbledUint<N>> for GarbledUint<N> {
+    fn shr_assign(&mut self, shift: GarbledUint<N>) {
+        *self = build_and_execute_shr(self, &shift);
+    }
+}
+
+impl<const N: usize> ShrAssign<&GarbledUint<N>> for GarbledUint<N> {
+    fn shr_assign(&mut self, shift: &GarbledUint<N>) {
+        *self = build_and_execute_shr(self, shift);
+    }
+}
+
+// Implement Shift Left by a secret amount for GarbledInt<N>
+impl<const N: usize> Shl<GarbledUint<N>> for GarbledInt<N> {
+    type Output = Self;
+
+    fn shl(self, shift: GarbledUint<N>) -> Self::Output {
+        build_and_execute_shl(&self.into(), &shift).into()
+    }
+}
+
+impl<const N: usize> Shl<&GarbledUint<N>> for &GarbledInt<N> {
+    type Output = GarbledInt<N>;
+
+    fn shl(self, shift: &GarbledUint<N>) -> Self::Output {
+        build_and_execute_shl(&self.into(), shift).into()
+    }
+}
+
+impl<const N: usize> ShlAssign<GarbledUint<N>> for GarbledInt<N> {
+    fn shl_assign(&mut self, shift: GarbledUint<N>) {
+        *self = build_and_execute_shl(&self.clone().into(), &shift).into();
+    }
+}
+
+impl<const N: usize> ShlAssign<&GarbledUint<N>> for GarbledInt<N> {
+    fn shl_assign(&mut self, shift: &GarbledUint<N>) {
+        *self = build_and_execute_shl(&self.clone().into(), shift).into();
+    }
+}
+
+// Implement arithmetic Shift Right by a secret amount for GarbledInt<N>
+impl<const N: usize> Shr<GarbledUint<N>> for GarbledInt<N> {
+    type Output = Self;
+
+    fn shr(self, shift: GarbledUint<N>) -> Self::Output {
+        build_and_execute_sar(&self.into(), &shift).into()
+    }
+}
+
+impl<const N: usize> Shr<&GarbledUint<N>> for &GarbledInt<N> {
+    type Output = GarbledInt<N>;
+
+    fn shr(self, shift: &GarbledUint<N>) -> Self::Output {
+        build_and_execute_sar(&self.into(), shift).into()
+    }
+}
+
+impl<const N: usize> ShrAssign<GarbledUint<N>> for GarbledInt<N> {
+    fn shr_assign(&mut self, shift: GarbledUint<N>) {
+        *self = build_and_execute_sar(&self.clone().into(), &shift).into();
+    }
+}
+
+impl<const N: usize> ShrAssign<&GarbledUint<N>> for GarbledInt<N> {
+    fn shr_assign(&mut self, shift: &GarbledUint<N>) {
+        *self = build_and_execute_sar(&self.clone().into(), shift).into();
+    }
+}
+
+// Implement rotations by a public amount for GarbledUint<N>; bits are stored least significant
+// first, so rotating the value left rotates the bit vector right
+impl<const N: usize> GarbledUint<N> {
+    pub fn rotate_left(&self, n: usize) -> Self {
+        let mut bits = self.bits.clone();
+        bits.rotate_right(n % N);
+        GarbledUint::new(bits)
+    }
+
+    pub fn rotate_right(&self, n: usize) -> Self {
+        let mut bits = self.bits.clone();
+        bits.rotate_left(n % N);
+        GarbledUint::new(bits)
+    }
+}
+
+// Implement rotations by a public amount for GarbledInt<N>
+impl<const N: usize> GarbledInt<N> {
+    pub fn rotate_left(&self, n: usize) -> Self {
+        let mut bits = self.bits.clone();
+        bits.rotate_right(n % N);
+        GarbledInt::new(bits)
+    }
+
+    pub fn rotate_right(&self, n: usize) -> Self {
+        let mut bits = self.bits.clone();
+        bits.rotate_left(n % N);
+        GarbledInt::new(bits)
     }
 }
 
@@ -1,12 +1,18 @@
+use crate::executor::{get_executor, Executor};
+use crate::operations::circuits::optimizer::{optimize, OptimizationStats};
 use crate::operations::circuits::types::GateIndexVec;
+use crate::uint::GarbledBoolean;
 use crate::uint::GarbledUint;
-use crate::{executor::get_executor, uint::GarbledBoolean};
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use tandem::{Circuit, Gate};
 
 pub type GateIndex = u32;
 
+/// Operand width from which `widening_mul` splits operands with Karatsuba instead of summing
+/// partial products; `mul` only needs the low half, so it switches over at twice this width
+pub const KARATSUBA_THRESHOLD: usize = 16;
+
 // Global instance of CircuitBuilder
 thread_local! {
     static CIRCUIT_BUILDER: RefCell<CircuitBuilder> = RefCell::new(CircuitBuilder::default());
@@ -15,13 +21,16 @@ thread_local! {
 #[derive(Default)]
 pub struct CircuitBuilder {
     inputs: Vec<bool>,
+    eval_inputs: Vec<bool>,
     gates: Vec<Gate>,
+    // Constant wires, created lazily on first use
+    const_zero: Option<GateIndex>,
+    const_one: Option<GateIndex>,
 }
 
 impl CircuitBuilder {
     // Static `global` function to access `CIRCUIT_BUILDER`
-    #[allow(dead_code)]
-    pub(super) fn global<F, R>(f: F) -> R
+    pub(crate) fn global<F, R>(f: F) -> R
     where
         F: FnOnce(&mut CircuitBuilder) -> R,
     {
@@ -31,21 +40,95 @@ impl CircuitBuilder {
         })
     }
 
-    pub fn input<const R: usize>(&mut self, input: &GarbledUint<R>) -> GateIndexVec {
-        // get the cumulative size of all inputs in input_labels
-        //let input_offset = self.input_labels.iter().map(|x| x.len()).sum::<usize>();
+    // Replace `CIRCUIT_BUILDER` with an empty builder
+    pub(crate) fn reset_global() {
+        CIRCUIT_BUILDER.with(|builder| {
+            *builder.borrow_mut() = CircuitBuilder::default();
+        })
+    }
 
-        let input_offset = self.inputs.len();
+    /// Adds an input contributed by the garbler, emitting one `Gate::InContrib` per bit.
+    pub fn input<const R: usize>(&mut self, input: &GarbledUint<R>) -> GateIndexVec {
         let mut input_label = GateIndexVec::default();
-        for (i, bool_value) in input.bits.iter().enumerate() {
-            self.gates.insert(0, Gate::InContrib);
-
+        for bool_value in input.bits.iter() {
+            input_label.push(self.len());
+            self.gates.push(Gate::InContrib);
             self.inputs.push(*bool_value);
-            input_label.push((input_offset + i) as GateIndex);
         }
         input_label
     }
 
+    /// Adds an input owned by the evaluator, emitting one `Gate::InEval` per bit.
+    pub fn input_eval<const R: usize>(&mut self, input: &GarbledUint<R>) -> GateIndexVec {
+        let mut input_label = GateIndexVec::default();
+        for bool_value in input.bits.iter() {
+            input_label.push(self.len());
+            self.gates.push(Gate::InEval);
+            self.eval_inputs.push(*bool_value);
+        }
+        input_label
+    }
+
+    /// Returns wires carrying the bits of a public constant.
+    ///
+    /// Constants are part of the circuit definition rather than contributor input, so they are
+    /// bound to the circuit hash and cost no input bandwidth.
+    pub fn constant<const R: usize>(&mut self, value: &GarbledUint<R>) -> GateIndexVec {
+        let mut output = GateIndexVec::default();
+        for bit in value.bits.iter() {
+            let wire = if *bit {
+                self.const_one()
+            } else {
+                self.const_zero()
+            };
+            output.push(wire);
+        }
+        output
+    }
+
+    /// Returns the wire that is always `false`, computed as the XOR of a wire with itself.
+    pub fn const_zero(&mut self) -> GateIndex {
+        if let Some(zero) = self.const_zero {
+            return zero;
+        }
+
+        // A circuit without any gates has nothing to derive the constant from, so anchor it on a
+        // single contributor input bit.
+        if self.gates.is_empty() {
+            self.gates.push(Gate::InContrib);
+            self.inputs.push(false);
+        }
+
+        let zero = self.len();
+        self.gates.push(Gate::Xor(0, 0));
+        self.const_zero = Some(zero);
+        zero
+    }
+
+    /// Returns the wire that is always `true`, computed as the negation of the zero wire.
+    pub fn const_one(&mut self) -> GateIndex {
+        if let Some(one) = self.const_one {
+            return one;
+        }
+
+        let zero = self.const_zero();
+        let one = self.len();
+        self.gates.push(Gate::Not(zero));
+        self.const_one = Some(one);
+        one
+    }
+
+    /// Returns the value carried by `wire` if it is one of the constant wires.
+    pub fn constant_value(&self, wire: &GateIndex) -> Option<bool> {
+        if Some(*wire) == self.const_zero {
+            Some(false)
+        } else if Some(*wire) == self.const_one {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
     pub fn len(&self) -> GateIndex {
         self.gates.len() as u32
     }
@@ -58,8 +141,22 @@ impl CircuitBuilder {
         &self.inputs
     }
 
+    pub fn eval_inputs(&self) -> &Vec<bool> {
+        &self.eval_inputs
+    }
+
     // Add a XOR gate between two inputs and return the index
     pub fn push_xor(&mut self, a: &GateIndex, b: &GateIndex) -> GateIndex {
+        // constant propagation: x ^ 0 = x, x ^ 1 = !x, x ^ x = 0
+        match (self.constant_value(a), self.constant_value(b)) {
+            (Some(false), _) => return *b,
+            (_, Some(false)) => return *a,
+            (Some(true), _) => return self.push_not(b),
+            (_, Some(true)) => return self.push_not(a),
+            _ if a == b => return self.const_zero(),
+            _ => {}
+        }
+
         let xor_index = self.gates.len() as u32;
         self.gates.push(Gate::Xor(*a, *b));
         xor_index
@@ -77,6 +174,15 @@ impl CircuitBuilder {
 
     // Add an Aa.len()D gate between two inputs and return the index
     pub fn push_and(&mut self, a: &GateIndex, b: &GateIndex) -> GateIndex {
+        // constant propagation: x & 0 = 0, x & 1 = x, x & x = x
+        match (self.constant_value(a), self.constant_value(b)) {
+            (Some(false), _) | (_, Some(false)) => return self.const_zero(),
+            (Some(true), _) => return *b,
+            (_, Some(true)) => return *a,
+            _ if a == b => return *a,
+            _ => {}
+        }
+
         let and_index = self.gates.len() as u32;
         self.gates.push(Gate::And(*a, *b));
         and_index
@@ -102,6 +208,12 @@ impl CircuitBuilder {
 
     // Add a NOT gate for a single input and return the index
     pub fn push_not(&mut self, a: &GateIndex) -> GateIndex {
+        match self.constant_value(a) {
+            Some(true) => return self.const_zero(),
+            Some(false) => return self.const_one(),
+            None => {}
+        }
+
         let not_index = self.gates.len() as u32;
         self.gates.push(Gate::Not(*a));
         not_index
@@ -117,6 +229,7 @@ impl CircuitBuilder {
     }
 
     // Add a gate for OR operation: OR(a, b) = (a ⊕ b) ⊕ (a & b)
+    // XOR gates are free to garble, so this costs a single AND gate
     pub fn push_or(&mut self, a: &GateIndex, b: &GateIndex) -> GateIndex {
         let xor_gate = self.push_xor(a, b);
         let and_gate = self.push_and(a, b);
@@ -185,35 +298,168 @@ impl CircuitBuilder {
         output
     }
 
+    // Shift left by a public amount; this only rewires bits, filling with constant zeros
+    pub fn shl(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+        let n = a.len();
+        let amount = amount.min(n);
+        let mut output = self.zero_extend(&GateIndexVec::default(), amount);
+        output.push_all(&a.slice(0..n - amount));
+        output
+    }
+
+    // Logical shift right by a public amount, filling with constant zeros
+    pub fn shr(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+        let n = a.len();
+        let amount = amount.min(n);
+        let shifted = a.slice(amount..n);
+        self.zero_extend(&shifted, n)
+    }
+
+    // Arithmetic shift right by a public amount, filling with copies of the sign bit
+    pub fn sar(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+        let n = a.len();
+        let amount = amount.min(n);
+        let mut output = a.slice(amount..n);
+        for _ in 0..amount {
+            output.push(a[n - 1]);
+        }
+        output
+    }
+
+    pub fn rotate_left(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+        let n = a.len();
+        if n == 0 {
+            return a.clone();
+        }
+        let amount = amount % n;
+        let mut output = a.slice(n - amount..n);
+        output.push_all(&a.slice(0..n - amount));
+        output
+    }
+
+    pub fn rotate_right(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+        let n = a.len();
+        if n == 0 {
+            return a.clone();
+        }
+        self.rotate_left(a, n - amount % n)
+    }
+
+    // Shift left by a secret amount with a barrel shifter, one MUX layer per amount bit.
+    // Only the low ceil(log2(a.len())) bits of the amount are used, which matches
+    // `wrapping_shl` for power of two widths
+    pub fn shl_var(&mut self, a: &GateIndexVec, amount: &GateIndexVec) -> GateIndexVec {
+        self.barrel_shift(a, amount, Self::shl)
+    }
+
+    pub fn shr_var(&mut self, a: &GateIndexVec, amount: &GateIndexVec) -> GateIndexVec {
+        self.barrel_shift(a, amount, Self::shr)
+    }
+
+    pub fn sar_var(&mut self, a: &GateIndexVec, amount: &GateIndexVec) -> GateIndexVec {
+        self.barrel_shift(a, amount, Self::sar)
+    }
+
+    pub fn rotate_left_var(&mut self, a: &GateIndexVec, amount: &GateIndexVec) -> GateIndexVec {
+        self.barrel_shift(a, amount, Self::rotate_left)
+    }
+
+    pub fn rotate_right_var(&mut self, a: &GateIndexVec, amount: &GateIndexVec) -> GateIndexVec {
+        self.barrel_shift(a, amount, Self::rotate_right)
+    }
+
+    fn barrel_shift(
+        &mut self,
+        a: &GateIndexVec,
+        amount: &GateIndexVec,
+        shift: fn(&mut Self, &GateIndexVec, usize) -> GateIndexVec,
+    ) -> GateIndexVec {
+        if a.is_empty() {
+            return a.clone();
+        }
+        let stages = (usize::BITS - (a.len() - 1).leading_zeros()) as usize;
+        let stages = stages.min(amount.len());
+
+        // A public amount needs no MUX layers at all
+        if self.is_constant(&amount.slice(0..stages)) {
+            let amount = (0..stages)
+                .filter(|&k| self.constant_value(&amount[k]) == Some(true))
+                .map(|k| 1 << k)
+                .sum();
+            return shift(self, a, amount);
+        }
+
+        let mut output = a.clone();
+        for k in 0..stages {
+            let shifted = shift(self, &output, 1 << k);
+            output = self.mux(&amount[k], &shifted, &output);
+        }
+        output
+    }
+
     pub fn mux(&mut self, s: &GateIndex, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
         // repeat with output_indices
         let mut output = GateIndexVec::default();
         for i in 0..a.len() {
-            let mux = self.push_mux(s, &b[i], &a[i]);
+            let mux = self.push_mux(s, &a[i], &b[i]);
             output.push(mux);
         }
         output
     }
 
-    pub fn mux_lookahead(&mut self, a: &GateIndexVec) -> GateIndexVec {
-        // repeat with output_indices
-        let mut output = GateIndexVec::default();
-        let mut counter = self.len() + 5 + 1;
+    /// Selects `elements[index]` with a tree of multiplexers, one level per bit of `index`, so the
+    /// circuit reads every element whichever is selected. An index past the last element selects
+    /// zero.
+    pub fn mux_tree(&mut self, elements: &[GateIndexVec], index: &GateIndexVec) -> GateIndexVec {
+        assert!(!elements.is_empty(), "cannot select from no elements");
+        let zero = self.const_zero();
+        let zero = GateIndexVec::new(vec![zero; elements[0].len()]);
+
+        let mut level = elements.to_vec();
+        let mut bit = 0;
+        while level.len() > 1 && bit < index.len() {
+            let s = index[bit];
+            let mut next = Vec::with_capacity(level.len().div_ceil(2));
+            for pair in level.chunks(2) {
+                next.push(match pair {
+                    [a, b] => self.mux(&s, b, a),
+                    // an odd element out is paired with the zero past the end
+                    _ => self.mux(&s, &zero, &pair[0]),
+                });
+            }
+            level = next;
+            bit += 1;
+        }
 
-        for _ in 0..a.len() {
-            output.push(counter);
-            counter += 6 + 1;
+        // Index bits above the tree can only address elements past the end
+        let mut out_of_range: Option<GateIndex> = None;
+        for high in index.iter().skip(bit) {
+            out_of_range = Some(match out_of_range {
+                Some(any) => self.push_or(&any, high),
+                None => *high,
+            });
+        }
+        match out_of_range {
+            Some(out_of_range) => self.mux(&out_of_range, &zero, &level[0]),
+            None => level.swap_remove(0),
         }
-        output
     }
 
-    #[allow(dead_code)]
-    // Add a MUX gate: MUX(a, b, s) = (a & !s) | (b & s)
+    // Add a MUX gate: MUX(s, a, b) = b ⊕ (s & (a ⊕ b)), selecting a when s is set
+    // XOR gates are free to garble, so this costs a single AND gate
     pub fn push_mux(&mut self, s: &GateIndex, a: &GateIndex, b: &GateIndex) -> GateIndex {
-        let not_s = self.push_not(s);
-        let and_a_not_s = self.push_and(a, &not_s);
-        let and_b_s = self.push_and(b, s);
-        self.push_or(&and_a_not_s, &and_b_s)
+        let xor_ab = self.push_xor(a, b);
+        let and_s = self.push_and(s, &xor_ab);
+        self.push_xor(b, &and_s)
+    }
+
+    // Add a majority gate: MAJ(a, b, c) = ((a ⊕ c) & (b ⊕ c)) ⊕ c
+    // Used for the carry and borrow chains, costing a single AND gate
+    pub fn push_maj(&mut self, a: &GateIndex, b: &GateIndex, c: &GateIndex) -> GateIndex {
+        let xor_ac = self.push_xor(a, c);
+        let xor_bc = self.push_xor(b, c);
+        let and_gate = self.push_and(&xor_ac, &xor_bc);
+        self.push_xor(&and_gate, c)
     }
 
     pub fn add(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
@@ -228,6 +474,15 @@ impl CircuitBuilder {
     }
 
     pub fn sub(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        self.sub_with_borrow(a, b).0
+    }
+
+    // Subtract b from a, also returning the final borrow which is set exactly when a < b
+    pub fn sub_with_borrow(
+        &mut self,
+        a: &GateIndexVec,
+        b: &GateIndexVec,
+    ) -> (GateIndexVec, GateIndex) {
         let mut borrow = None;
         let mut output_indices = GateIndexVec::default();
         for i in 0..a.len() {
@@ -235,10 +490,93 @@ impl CircuitBuilder {
             output_indices.push(diff);
             borrow = new_borrow;
         }
+        let borrow = borrow.unwrap_or_else(|| self.const_zero());
+        (output_indices, borrow)
+    }
+
+    // Add with the final carry appended, so the result is one bit wider than a
+    pub fn widening_add(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let mut carry = None;
+        let mut output_indices = GateIndexVec::default();
+        for i in 0..a.len() {
+            let (sum, new_carry) = full_adder(self, a[i], b[i], carry);
+            output_indices.push(sum);
+            carry = new_carry;
+        }
+        let carry = carry.unwrap_or_else(|| self.const_zero());
+        output_indices.push(carry);
         output_indices
     }
 
+    // Pad a with constant zero wires up to len bits
+    pub fn zero_extend(&mut self, a: &GateIndexVec, len: usize) -> GateIndexVec {
+        let mut output = a.clone();
+        while output.len() < len {
+            let zero = self.const_zero();
+            output.push(zero);
+        }
+        output
+    }
+
+    // Pad a with copies of its top bit up to len bits, preserving its two's complement value
+    pub fn sign_extend(&mut self, a: &GateIndexVec, len: usize) -> GateIndexVec {
+        let mut output = a.clone();
+        while output.len() < len {
+            output.push(a[a.len() - 1]);
+        }
+        output
+    }
+
+    // Zero-extend or truncate a to exactly len bits
+    pub fn resize(&mut self, a: &GateIndexVec, len: usize) -> GateIndexVec {
+        if a.len() >= len {
+            a.slice(0..len)
+        } else {
+            self.zero_extend(a, len)
+        }
+    }
+
+    // Multiply keeping the low a.len() bits, picking the cheapest strategy for the operands:
+    // shift-and-add when either side is a public constant, Karatsuba for wide operands and
+    // summed partial products otherwise
     pub fn mul(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        if self.is_constant(b) {
+            self.mul_constant(a, b)
+        } else if self.is_constant(a) {
+            self.mul_constant(b, a)
+        } else if a.len() >= 2 * KARATSUBA_THRESHOLD {
+            self.mul_karatsuba(a, b)
+        } else {
+            self.mul_schoolbook(a, b)
+        }
+    }
+
+    // Multiply returning the full a.len() + b.len() bit product
+    pub fn widening_mul(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let n = a.len().max(b.len());
+        if self.is_constant(b) {
+            let a = self.zero_extend(a, a.len() + b.len());
+            self.mul_constant(&a, b)
+        } else if self.is_constant(a) {
+            let b = self.zero_extend(b, a.len() + b.len());
+            self.mul_constant(&b, a)
+        } else if n >= KARATSUBA_THRESHOLD {
+            let len = a.len() + b.len();
+            let a = self.zero_extend(a, n);
+            let b = self.zero_extend(b, n);
+            let mut product = self.widening_mul_karatsuba(&a, &b);
+            product.truncate(len);
+            product
+        } else {
+            self.widening_mul_schoolbook(a, b)
+        }
+    }
+
+    fn is_constant(&self, a: &GateIndexVec) -> bool {
+        a.iter().all(|bit| self.constant_value(bit).is_some())
+    }
+
+    fn mul_schoolbook(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
         let mut partial_products: Vec<GateIndexVec> = Vec::with_capacity(a.len());
 
         // Generate partial products
@@ -256,48 +594,175 @@ impl CircuitBuilder {
         result
     }
 
+    // Shift-and-add over the set bits of the constant b; the low i bits of a << i are zero,
+    // so each addition only covers the bits above the shift
+    fn mul_constant(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let n = a.len();
+        let mut result = GateIndexVec::default();
+        let mut empty = true;
+
+        for i in 0..n.min(b.len()) {
+            if self.constant_value(&b[i]) != Some(true) {
+                continue;
+            }
+
+            if empty {
+                result = self.zero_extend(&GateIndexVec::default(), i);
+                result.push_all(&a.slice(0..n - i));
+                empty = false;
+            } else {
+                let sum = self.add(&result.slice(i..n), &a.slice(0..n - i));
+                let mut shifted = result.slice(0..i);
+                shifted.push_all(&sum);
+                result = shifted;
+            }
+        }
+
+        if empty {
+            result = self.zero_extend(&GateIndexVec::default(), n);
+        }
+        result
+    }
+
+    // Low half of the product: a0 * b0 in full plus the cross terms a1 * b0 + a0 * b1 shifted
+    // into place, with the cross terms only needed modulo 2^(n - h). Rounding h up keeps
+    // a1 * b1 out of the low n bits for odd widths, so it can be dropped
+    fn mul_karatsuba(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let n = a.len();
+        let h = n.div_ceil(2);
+
+        let low = self.widening_mul(&a.slice(0..h), &b.slice(0..h));
+        let cross_a = self.mul(&a.slice(h..n), &b.slice(0..n - h));
+        let cross_b = self.mul(&a.slice(0..n - h), &b.slice(h..n));
+        let cross = self.add(&cross_a, &cross_b);
+
+        let mut result = self.zero_extend(&low, n);
+        let upper = self.add(&result.slice(h..n), &cross);
+        result.truncate(h);
+        result.push_all(&upper);
+        result
+    }
+
+    // Full 2n-bit product from three half-width products:
+    // a * b = z2 << 2h + (z1 - z2 - z0) << h + z0 with z1 = (a0 + a1) * (b0 + b1)
+    fn widening_mul_karatsuba(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let n = a.len();
+        let h = n / 2;
+
+        let a0 = self.zero_extend(&a.slice(0..h), n - h);
+        let b0 = self.zero_extend(&b.slice(0..h), n - h);
+        let a1 = a.slice(h..n);
+        let b1 = b.slice(h..n);
+
+        let z0 = self.widening_mul(&a.slice(0..h), &b.slice(0..h));
+        let z2 = self.widening_mul(&a1, &b1);
+        let sum_a = self.widening_add(&a0, &a1);
+        let sum_b = self.widening_add(&b0, &b1);
+        let z1 = self.widening_mul(&sum_a, &sum_b);
+
+        // z1 - z0 - z2 = a0 * b1 + a1 * b0, which fits in n + 1 bits
+        let z0_extended = self.zero_extend(&z0, z1.len());
+        let z2_extended = self.zero_extend(&z2, z1.len());
+        let mut middle = self.sub(&z1, &z0_extended);
+        middle = self.sub(&middle, &z2_extended);
+        middle.truncate(n + 1);
+        let middle = self.zero_extend(&middle, 2 * n - h);
+
+        // z0 and z2 do not overlap, so they are concatenated for free
+        let mut result = z0;
+        result.push_all(&z2);
+        let upper = self.add(&result.slice(h..2 * n), &middle);
+        result.truncate(h);
+        result.push_all(&upper);
+        result
+    }
+
+    fn widening_mul_schoolbook(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let mut result = GateIndexVec::default();
+
+        // Running upper part of the accumulated product, a.len() bits wide
+        let first = self.and_bit(a, &b[0]);
+        result.push(first[0]);
+        let mut running = first.slice(1..a.len());
+        let zero = self.const_zero();
+        running.push(zero);
+
+        for i in 1..b.len() {
+            let partial_product = self.and_bit(a, &b[i]);
+            let sum = self.widening_add(&running, &partial_product);
+            result.push(sum[0]);
+            running = sum.slice(1..sum.len());
+        }
+
+        result.push_all(&running);
+        result
+    }
+
+    // AND every bit of a with the single wire s
+    fn and_bit(&mut self, a: &GateIndexVec, s: &GateIndex) -> GateIndexVec {
+        let mut output = GateIndexVec::default();
+        for i in 0..a.len() {
+            let and_gate = self.push_and(&a[i], s);
+            output.push(and_gate);
+        }
+        output
+    }
+
     fn div_inner(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndexVec, GateIndexVec) {
         let n = a.len();
         let mut quotient = GateIndexVec::default();
-        let mut remainder = GateIndexVec::default();
 
         // Initialize remainder with 0
-        for _ in 0..n {
-            remainder.push(GateIndex::default()); // Zero initialize
-        }
+        let mut remainder = self.zero_extend(&GateIndexVec::default(), n);
+
+        // The shifted remainder can reach 2 * b - 1, so it is compared one bit wider than b
+        let divisor = self.zero_extend(b, n + 1);
 
         // Iterate through each bit, starting from the most significant
         for i in (0..n).rev() {
             // Shift remainder left by 1 (equivalent to adding a bit)
             remainder.insert(0, a[i]);
-            if remainder.len() > n {
-                remainder.truncate(n); // Ensure remainder does not exceed bit width
-            }
 
-            // Check if remainder is greater than or equal to divisor
-            let greater_or_equal = self.ge(&remainder, b);
+            // Subtract divisor from remainder; no final borrow means remainder >= divisor
+            let (new_remainder, borrow) = self.sub_with_borrow(&remainder, &divisor);
+            let greater_or_equal = self.push_not(&borrow);
 
-            // If remainder is greater than or equal to divisor, set quotient bit to 1 and subtract divisor from remainder
-            if greater_or_equal != GateIndex::default() {
-                // Subtract divisor from remainder if it’s greater than or equal
-                let new_remainder = self.sub(&remainder, b);
-                remainder = self.mux(&greater_or_equal, &new_remainder, &remainder);
+            // If remainder is greater than or equal to divisor, keep the difference,
+            // which is below the divisor again and fits in n bits
+            remainder = self.mux(
+                &greater_or_equal,
+                &new_remainder.slice(0..n),
+                &remainder.slice(0..n),
+            );
 
-                // Set quotient bit to 1
-                quotient.insert(0, greater_or_equal);
-            } else {
-                // Set quotient bit to 0
-                quotient.insert(0, GateIndex::default());
-            }
-
-            if quotient.len() > n {
-                quotient.truncate(n); // Ensure quotient does not exceed bit width
-            }
+            // The quotient bit is set exactly when the divisor was subtracted
+            quotient.insert(0, greater_or_equal);
         }
 
         (quotient, remainder)
     }
 
+    // Signed division truncating toward zero: divide the magnitudes, then negate the quotient
+    // when the signs differ and give the remainder the sign of the dividend
+    fn div_signed_inner(
+        &mut self,
+        a: &GateIndexVec,
+        b: &GateIndexVec,
+    ) -> (GateIndexVec, GateIndexVec) {
+        let n = a.len();
+        let sign_a = a[n - 1];
+        let sign_b = b[n - 1];
+
+        let abs_a = self.conditional_neg(a, &sign_a);
+        let abs_b = self.conditional_neg(b, &sign_b);
+        let (quotient, remainder) = self.div_inner(&abs_a, &abs_b);
+
+        let sign_quotient = self.push_xor(&sign_a, &sign_b);
+        let quotient = self.conditional_neg(&quotient, &sign_quotient);
+        let remainder = self.conditional_neg(&remainder, &sign_a);
+        (quotient, remainder)
+    }
+
     pub fn div(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
         self.div_inner(a, b).0
     }
@@ -306,6 +771,55 @@ impl CircuitBuilder {
         self.div_inner(a, b).1
     }
 
+    pub fn div_signed(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        self.div_signed_inner(a, b).0
+    }
+
+    pub fn rem_signed(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        self.div_signed_inner(a, b).1
+    }
+
+    // Two's complement negation: 0 - a
+    pub fn neg(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let zero = self.zero_extend(&GateIndexVec::default(), a.len());
+        self.sub(&zero, a)
+    }
+
+    // Negate a when s is set: (a ⊕ s) + s, at one AND gate per bit
+    pub fn conditional_neg(&mut self, a: &GateIndexVec, s: &GateIndex) -> GateIndexVec {
+        let mut output = GateIndexVec::default();
+        let mut carry = *s;
+        for i in 0..a.len() {
+            let flipped = self.push_xor(&a[i], s);
+            let sum = self.push_xor(&flipped, &carry);
+            carry = self.push_and(&flipped, &carry);
+            output.push(sum);
+        }
+        output
+    }
+
+    pub fn abs(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let sign = a[a.len() - 1];
+        self.conditional_neg(a, &sign)
+    }
+
+    // -1, 0 or 1 depending on the sign of a: the low bit is set for any nonzero value and
+    // every other bit copies the sign
+    pub fn signum(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let sign = a[a.len() - 1];
+        let mut nonzero = a[0];
+        for i in 1..a.len() {
+            nonzero = self.push_or(&nonzero, &a[i]);
+        }
+
+        let mut output = GateIndexVec::default();
+        output.push(nonzero);
+        for _ in 1..a.len() {
+            output.push(sign);
+        }
+        output
+    }
+
     pub fn eq(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
         let mut eq_list = vec![0; a.len()];
 
@@ -328,9 +842,7 @@ impl CircuitBuilder {
     }
 
     pub fn gt(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
-        let (lt, eq) = self.compare(a, b);
-        let or_gate = self.push_or(&lt, &eq);
-        self.push_not(&or_gate)
+        self.lt(b, a)
     }
 
     pub fn ge(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
@@ -338,9 +850,18 @@ impl CircuitBuilder {
         self.push_not(&lt)
     }
 
+    // a < b exactly when computing a - b borrows out of the most significant bit,
+    // so only the borrow chain is built, at one AND gate per bit
     pub fn lt(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
-        let (lt, _eq) = self.compare(a, b);
-        lt
+        let mut borrow: Option<GateIndex> = None;
+        for i in 0..a.len() {
+            let not_a = self.push_not(&a[i]);
+            borrow = Some(match borrow {
+                Some(borrow) => self.push_maj(&not_a, &b[i], &borrow),
+                None => self.push_and(&not_a, &b[i]),
+            });
+        }
+        borrow.unwrap_or_else(|| self.const_zero())
     }
 
     pub fn le(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
@@ -349,37 +870,77 @@ impl CircuitBuilder {
     }
 
     pub fn compare(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndex, GateIndex) {
-        let mut eq_list = vec![0; a.len()];
-        let mut lt_list = vec![0; a.len()];
+        let lt = self.lt(a, b);
+        let eq = self.eq(a, b);
+        (lt, eq)
+    }
 
-        let i = a.len() - 1;
-        let eq_i = self.push_xnor(&a[i], &b[i]);
-        eq_list[i] = eq_i;
+    // Flipping the sign bits maps two's complement order onto unsigned order for free
+    fn flip_sign(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let n = a.len();
+        let mut output = a.slice(0..n - 1);
+        let sign = self.push_not(&a[n - 1]);
+        output.push(sign);
+        output
+    }
 
-        let nt = self.push_not(&a[i]);
-        let lt_i = self.push_and(&nt, &b[i]);
-        lt_list[i] = lt_i;
+    pub fn lt_signed(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        let a = self.flip_sign(a);
+        let b = self.flip_sign(b);
+        self.lt(&a, &b)
+    }
 
-        for idx in (0..i).rev() {
-            let xn = self.push_xnor(&a[idx], &b[idx]);
-            let eq_i = self.push_and(&eq_list[idx + 1], &xn);
-            eq_list[idx] = eq_i;
+    pub fn gt_signed(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        self.lt_signed(b, a)
+    }
 
-            let nt = self.push_not(&a[idx]);
-            let aa = self.push_and(&nt, &b[idx]);
-            let temp_lt = self.push_and(&eq_list[idx + 1], &aa);
-            lt_list[idx] = self.push_or(&lt_list[idx + 1], &temp_lt);
-        }
+    pub fn le_signed(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        let gt = self.gt_signed(a, b);
+        self.push_not(&gt)
+    }
 
-        (lt_list[0], eq_list[0])
+    pub fn ge_signed(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        let lt = self.lt_signed(a, b);
+        self.push_not(&lt)
+    }
+
+    pub fn compare_signed(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndex, GateIndex) {
+        let lt = self.lt_signed(a, b);
+        let eq = self.eq(a, b);
+        (lt, eq)
     }
 
+    /// Compiles the gates into a circuit, running the optimizer first
     pub fn compile(&self, output_indices: &GateIndexVec) -> Circuit {
+        self.compile_with_stats(output_indices).0
+    }
+
+    /// Compiles the gates into a circuit and reports how much the optimizer saved
+    pub fn compile_with_stats(
+        &self,
+        output_indices: &GateIndexVec,
+    ) -> (Circuit, OptimizationStats) {
+        let output_gates: Vec<GateIndex> = output_indices.clone().into();
+        let (gates, output_gates, stats) = optimize(&self.gates, &output_gates);
+        (Circuit::new(gates, output_gates), stats)
+    }
+
+    /// Compiles the gates into a circuit exactly as they were built
+    pub fn compile_unoptimized(&self, output_indices: &GateIndexVec) -> Circuit {
         Circuit::new(self.gates.clone(), output_indices.clone().into())
     }
 
     pub fn execute<const N: usize>(&self, circuit: &Circuit) -> anyhow::Result<GarbledUint<N>> {
-        let result = get_executor().execute(circuit, &self.inputs, &[])?;
+        self.execute_with(circuit, get_executor().as_ref())
+    }
+
+    /// Executes the circuit on the given executor instead of the current thread's executor
+    pub fn execute_with<const N: usize>(
+        &self,
+        circuit: &Circuit,
+        executor: &dyn Executor,
+    ) -> anyhow::Result<GarbledUint<N>> {
+        let result = executor.execute(circuit, &self.inputs, &self.eval_inputs)?;
         Ok(GarbledUint::new(result))
     }
 
@@ -389,8 +950,7 @@ impl CircuitBuilder {
         output_indices: &GateIndexVec,
     ) -> anyhow::Result<GarbledUint<N>> {
         let circuit = self.compile(output_indices);
-        let result = get_executor().execute(&circuit, &self.inputs, &[])?;
-        Ok(GarbledUint::new(result))
+        self.execute(&circuit)
     }
 }
 
@@ -429,6 +989,32 @@ build_and_execute!(build_and_execute_subtraction, sub);
 build_and_execute!(build_and_execute_multiplication, mul);
 build_and_execute!(build_and_execute_division, div);
 build_and_execute!(build_and_execute_remainder, rem);
+build_and_execute!(build_and_execute_signed_division, div_signed);
+build_and_execute!(build_and_execute_signed_remainder, rem_signed);
+build_and_execute!(build_and_execute_shl, shl_var);
+build_and_execute!(build_and_execute_shr, shr_var);
+build_and_execute!(build_and_execute_sar, sar_var);
+
+macro_rules! build_and_execute_unary {
+    ($fn_name:ident, $op:ident) => {
+        pub(crate) fn $fn_name<const N: usize>(input: &GarbledUint<N>) -> GarbledUint<N> {
+            let mut builder = CircuitBuilder::default();
+            let a = builder.input(input);
+
+            let output = builder.$op(&a);
+            let circuit = builder.compile(&output);
+
+            // Execute the circuit
+            builder
+                .execute(&circuit)
+                .expect("Failed to execute circuit")
+        }
+    };
+}
+
+build_and_execute_unary!(build_and_execute_neg, neg);
+build_and_execute_unary!(build_and_execute_abs, abs);
+build_and_execute_unary!(build_and_execute_signum, signum);
 
 fn full_adder(
     builder: &mut CircuitBuilder,
@@ -436,32 +1022,22 @@ fn full_adder(
     b: GateIndex,
     carry: Option<GateIndex>,
 ) -> (GateIndex, Option<GateIndex>) {
-    let xor_ab = builder.len();
-    builder.gates.push(Gate::Xor(a, b));
+    let xor_ab = builder.push_xor(&a, &b);
 
     let sum = if let Some(c) = carry {
-        let sum_with_carry = builder.len();
-        builder.gates.push(Gate::Xor(xor_ab, c));
-        sum_with_carry
+        builder.push_xor(&xor_ab, &c)
     } else {
         xor_ab
     };
 
-    let and_ab = builder.len();
-    builder.gates.push(Gate::And(a, b));
-
+    // The carry is the majority of a, b and the incoming carry
     let new_carry = if let Some(c) = carry {
-        let and_axorb_c = builder.len();
-        builder.gates.push(Gate::And(xor_ab, c));
-
-        let or_gate = builder.len();
-        builder.gates.push(Gate::Xor(and_ab, and_axorb_c));
-        Some(or_gate)
+        builder.push_maj(&a, &b, &c)
     } else {
-        Some(and_ab)
+        builder.push_and(&a, &b)
     };
 
-    (sum, new_carry)
+    (sum, Some(new_carry))
 }
 
 fn full_subtractor(
@@ -480,20 +1056,12 @@ fn full_subtractor(
         xor_ab
     };
 
-    // Compute the new borrow: (!a & b) | (a & borrow) | (!b & borrow)
+    // The new borrow is the majority of !a, b and the incoming borrow
     let not_a = builder.push_not(a);
-    let and_not_a_b = builder.push_and(&not_a, b);
-
     let new_borrow = if let Some(borrow) = borrow {
-        let and_a_borrow = builder.push_and(a, borrow);
-        let not_b = builder.push_not(b);
-        let and_not_b_borrow = builder.push_and(&not_b, borrow);
-
-        // Combine borrow parts using XOR and Aa.len()D to simulate OR
-        let xor_borrow_parts = builder.push_xor(&and_not_a_b, &and_a_borrow);
-        builder.push_xor(&xor_borrow_parts, &and_not_b_borrow)
+        builder.push_maj(&not_a, b, borrow)
     } else {
-        and_not_a_b
+        builder.push_and(&not_a, b)
     };
 
     (diff, Some(new_borrow))
@@ -510,9 +1078,7 @@ fn partial_product_shift(
     for i in 0..lhs.len() {
         if i < shift {
             // For the lower bits, we push a constant 0.
-            let zero_bit = builder.push_not(&rhs[0]);
-            let and_gate = builder.push_and(&rhs[0], &zero_bit); // Constant 0
-            shifted.push(and_gate);
+            shifted.push(builder.const_zero());
         } else {
             let lhs_bit = lhs[i - shift];
             let and_gate = builder.push_and(&lhs_bit, &(rhs[shift]));
@@ -524,6 +1090,27 @@ fn partial_product_shift(
     shifted
 }
 
+// Returns the low and high halves of the full 2N-bit product
+pub(crate) fn build_and_execute_widening_multiplication<const N: usize>(
+    lhs: &GarbledUint<N>,
+    rhs: &GarbledUint<N>,
+) -> (GarbledUint<N>, GarbledUint<N>) {
+    let mut builder = CircuitBuilder::default();
+    let a = builder.input(lhs);
+    let b = builder.input(rhs);
+
+    let output = builder.widening_mul(&a, &b);
+    let result = builder
+        .compile_and_execute::<N>(&output)
+        .expect("Failed to execute widening multiplication circuit");
+
+    let (low, high) = result.bits.split_at(a.len());
+    (
+        GarbledUint::new(low.to_vec()),
+        GarbledUint::new(high.to_vec()),
+    )
+}
+
 pub(crate) fn build_and_execute_equality<const N: usize>(
     lhs: &GarbledUint<N>,
     rhs: &GarbledUint<N>,
@@ -542,12 +1129,28 @@ pub(crate) fn build_and_execute_equality<const N: usize>(
 pub(crate) fn build_and_execute_comparator<const N: usize>(
     lhs: &GarbledUint<N>,
     rhs: &GarbledUint<N>,
+) -> Ordering {
+    build_and_execute_ordering(lhs, rhs, CircuitBuilder::compare)
+}
+
+// Compares two's complement values
+pub(crate) fn build_and_execute_signed_comparator<const N: usize>(
+    lhs: &GarbledUint<N>,
+    rhs: &GarbledUint<N>,
+) -> Ordering {
+    build_and_execute_ordering(lhs, rhs, CircuitBuilder::compare_signed)
+}
+
+fn build_and_execute_ordering<const N: usize>(
+    lhs: &GarbledUint<N>,
+    rhs: &GarbledUint<N>,
+    compare: fn(&mut CircuitBuilder, &GateIndexVec, &GateIndexVec) -> (GateIndex, GateIndex),
 ) -> Ordering {
     let mut builder = CircuitBuilder::default();
     let a = builder.input(lhs);
     let b = builder.input(rhs);
 
-    let (lt_output, eq_output) = builder.compare(&a, &b);
+    let (lt_output, eq_output) = compare(&mut builder, &a, &b);
 
     let result = builder
         .compile_and_execute::<2>(&vec![lt_output, eq_output].into())
@@ -593,15 +1196,6 @@ pub(crate) fn build_and_execute_mux<const N: usize>(
     let s = builder.input(condition);
 
     // Add MUX gates for each bit
-    /*
-    let mut output_indices = Vec::with_capacity(a.len());
-    let n = a.len() as u32;
-    for i in 0..n {
-        let mux_gate = builder.push_mux(&i, &(n + i), &(2 * n));
-        output_indices.push(mux_gate);
-    }
-    */
-
     let output = builder.mux(&s[0], &a, &b);
 
     // Simulate the circuit
@@ -818,6 +1412,491 @@ mod tests {
         assert!(!result_value);
     }
 
+    #[test]
+    fn test_eval_inputs() {
+        let a: GarbledUint8 = 42_u8.into();
+        let b: GarbledUint8 = 100_u8.into();
+
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&a);
+        let b = builder.input_eval(&b);
+
+        let output = builder.add(&a, &b);
+
+        let circuit = builder.compile(&output);
+        assert_eq!(circuit.contrib_inputs(), 8);
+        assert_eq!(circuit.eval_inputs(), 8);
+        assert_eq!(builder.inputs().len(), 8);
+        assert_eq!(builder.eval_inputs().len(), 8);
+
+        let result = builder
+            .execute::<8>(&circuit)
+            .expect("Failed to execute addition circuit");
+        let result_value: u8 = result.into();
+        assert_eq!(result_value, 142);
+    }
+
+    #[test]
+    fn test_constants() {
+        let a: GarbledUint8 = 42_u8.into();
+
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&a);
+        let b = builder.constant::<8>(&58_u8.into());
+
+        let output = builder.add(&a, &b);
+
+        let circuit = builder.compile(&output);
+        assert_eq!(circuit.contrib_inputs(), 8);
+        assert_eq!(builder.inputs().len(), 8);
+
+        let result = builder
+            .execute::<8>(&circuit)
+            .expect("Failed to execute addition circuit");
+        let result_value: u8 = result.into();
+        assert_eq!(result_value, 100);
+    }
+
+    #[test]
+    fn test_constant_propagation() {
+        let a: GarbledUint8 = 42_u8.into();
+
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&a);
+        let zero = builder.constant::<8>(&0_u8.into());
+        let ones = builder.constant::<8>(&u8::MAX.into());
+
+        let gates = builder.len();
+        // a & 0 = 0, a ^ 0 = a and a & 0xff = a without emitting any gates
+        assert_eq!(builder.and(&a, &zero), zero);
+        assert_eq!(builder.xor(&a, &zero), a);
+        assert_eq!(builder.and(&a, &ones), a);
+        assert_eq!(builder.not(&zero), ones);
+        assert_eq!(builder.len(), gates);
+
+        // a ^ a folds to the zero wire
+        assert_eq!(builder.xor(&a, &a), zero);
+    }
+
+    #[test]
+    fn test_constant_without_inputs() {
+        let mut builder = CircuitBuilder::default();
+        let a = builder.constant::<8>(&7_u8.into());
+        let b = builder.constant::<8>(&6_u8.into());
+
+        let output = builder.mul(&a, &b);
+
+        let circuit = builder.compile(&output);
+        let result = builder
+            .execute::<8>(&circuit)
+            .expect("Failed to execute multiplication circuit");
+        let result_value: u8 = result.into();
+        assert_eq!(result_value, 42);
+    }
+
+    #[test]
+    fn test_compile_optimizes() {
+        let a: GarbledUint8 = 12_u8.into();
+        let b: GarbledUint8 = 10_u8.into();
+
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&a);
+        let b = builder.input(&b);
+
+        // both branches are computed, but only the sum is used
+        let _unused = builder.mul(&a, &b);
+        let output = builder.add(&a, &b);
+
+        let (circuit, stats) = builder.compile_with_stats(&output);
+        let unoptimized = builder.compile_unoptimized(&output);
+
+        assert_eq!(stats.gates_before, unoptimized.gates().len());
+        assert_eq!(stats.and_gates_before, unoptimized.and_gates());
+        assert_eq!(stats.gates_after, circuit.gates().len());
+        assert_eq!(stats.and_gates_after, circuit.and_gates());
+        assert!(stats.and_gates_after < stats.and_gates_before);
+        assert_eq!(circuit.contrib_inputs(), unoptimized.contrib_inputs());
+
+        let result = builder
+            .execute::<8>(&circuit)
+            .expect("Failed to execute addition circuit");
+        let result_value: u8 = result.into();
+        assert_eq!(result_value, 22);
+    }
+
+    #[test]
+    fn test_karatsuba_multiplication() {
+        let values = [
+            (0_u64, 0_u64),
+            (1, u64::MAX),
+            (u64::MAX, u64::MAX),
+            (0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210),
+            (12297829382473034410, 424242424242),
+        ];
+
+        for (x, y) in values {
+            let mut builder = CircuitBuilder::default();
+            let a = builder.input(&GarbledUint64::from(x));
+            let b = builder.input(&GarbledUint64::from(y));
+
+            let mut output = builder.mul(&a, &b);
+            output.push_all(&builder.widening_mul(&a, &b));
+
+            let result = builder
+                .compile_and_execute::<192>(&output)
+                .expect("Failed to execute multiplication circuit");
+
+            let low: u64 = GarbledUint64::new(result.bits[..64].to_vec()).into();
+            let wide: u128 = GarbledUint::<128>::new(result.bits[64..].to_vec()).into();
+            assert_eq!(low, x.wrapping_mul(y));
+            assert_eq!(wide, x as u128 * y as u128);
+        }
+    }
+
+    #[test]
+    fn test_karatsuba_and_gates() {
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&GarbledUint64::from(0_u64));
+        let b = builder.input(&GarbledUint64::from(0_u64));
+
+        let karatsuba = builder.mul(&a, &b);
+        let schoolbook = builder.mul_schoolbook(&a, &b);
+
+        let (_, karatsuba) = builder.compile_with_stats(&karatsuba);
+        let (_, schoolbook) = builder.compile_with_stats(&schoolbook);
+        assert!(karatsuba.and_gates_after < schoolbook.and_gates_after);
+    }
+
+    #[test]
+    fn test_constant_multiplication() {
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&GarbledUint32::from(123456_u32));
+        let b = builder.constant(&GarbledUint32::from(10_u32));
+
+        let product = builder.mul(&a, &b);
+        let mut output = product.clone();
+        output.push_all(&builder.widening_mul(&b, &a));
+
+        // 10 has two set bits, so only a single addition above the lowest shift is needed
+        let circuit = builder.compile(&product);
+        assert!(circuit.and_gates() <= 32);
+
+        let result = builder
+            .compile_and_execute::<96>(&output)
+            .expect("Failed to execute multiplication circuit");
+
+        let low: u32 = GarbledUint32::new(result.bits[..32].to_vec()).into();
+        let wide: u64 = GarbledUint64::new(result.bits[32..].to_vec()).into();
+        assert_eq!(low, 1234560);
+        assert_eq!(wide, 1234560);
+    }
+
+    #[test]
+    fn test_unsigned_division_exhaustive() {
+        for x in 0..=u8::MAX {
+            let mut builder = CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(x));
+
+            let mut output = GateIndexVec::default();
+            for y in 1..=u8::MAX {
+                let b = builder.input(&GarbledUint8::from(y));
+                let (quotient, remainder) = builder.div_inner(&a, &b);
+                output.push_all(&quotient);
+                output.push_all(&remainder);
+            }
+
+            let circuit = builder.compile_unoptimized(&output);
+            let result = builder
+                .execute::<4080>(&circuit)
+                .expect("Failed to execute division circuit");
+
+            for (chunk, y) in result.bits.chunks(16).zip(1..=u8::MAX) {
+                let quotient: u8 = GarbledUint8::new(chunk[..8].to_vec()).into();
+                let remainder: u8 = GarbledUint8::new(chunk[8..].to_vec()).into();
+                assert_eq!(quotient, x / y, "{} / {}", x, y);
+                assert_eq!(remainder, x % y, "{} % {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_division_exhaustive() {
+        for x in i8::MIN..=i8::MAX {
+            let mut builder = CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(x as u8));
+
+            let divisors: Vec<i8> = (i8::MIN..=i8::MAX).filter(|&y| y != 0).collect();
+            let mut output = GateIndexVec::default();
+            for &y in divisors.iter() {
+                let b = builder.input(&GarbledUint8::from(y as u8));
+                let (quotient, remainder) = builder.div_signed_inner(&a, &b);
+                output.push_all(&quotient);
+                output.push_all(&remainder);
+            }
+
+            let circuit = builder.compile_unoptimized(&output);
+            let result = builder
+                .execute::<4080>(&circuit)
+                .expect("Failed to execute signed division circuit");
+
+            for (chunk, &y) in result.bits.chunks(16).zip(divisors.iter()) {
+                let quotient: u8 = GarbledUint8::new(chunk[..8].to_vec()).into();
+                let remainder: u8 = GarbledUint8::new(chunk[8..].to_vec()).into();
+                assert_eq!(quotient as i8, x.wrapping_div(y), "{} / {}", x, y);
+                assert_eq!(remainder as i8, x.wrapping_rem(y), "{} % {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_comparisons_exhaustive() {
+        for x in i8::MIN..=i8::MAX {
+            let mut builder = CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(x as u8));
+
+            let mut output = GateIndexVec::default();
+            for y in i8::MIN..=i8::MAX {
+                let b = builder.input(&GarbledUint8::from(y as u8));
+                let lt = builder.lt_signed(&a, &b);
+                let gt = builder.gt_signed(&a, &b);
+                let le = builder.le_signed(&a, &b);
+                let ge = builder.ge_signed(&a, &b);
+                let (cmp_lt, cmp_eq) = builder.compare_signed(&a, &b);
+                output.push_all(&vec![lt, gt, le, ge, cmp_lt, cmp_eq].into());
+            }
+
+            let circuit = builder.compile_unoptimized(&output);
+            let result = builder
+                .execute::<1536>(&circuit)
+                .expect("Failed to execute signed comparison circuit");
+
+            for (chunk, y) in result.bits.chunks(6).zip(i8::MIN..=i8::MAX) {
+                assert_eq!(
+                    chunk,
+                    [x < y, x > y, x <= y, x >= y, x < y, x == y],
+                    "comparing {} and {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_unary_exhaustive() {
+        let mut builder = CircuitBuilder::default();
+
+        let mut output = GateIndexVec::default();
+        for x in i8::MIN..=i8::MAX {
+            let a = builder.input(&GarbledUint8::from(x as u8));
+            let neg = builder.neg(&a);
+            let abs = builder.abs(&a);
+            let signum = builder.signum(&a);
+            output.push_all(&neg);
+            output.push_all(&abs);
+            output.push_all(&signum);
+        }
+
+        let circuit = builder.compile_unoptimized(&output);
+        let result = builder
+            .execute::<6144>(&circuit)
+            .expect("Failed to execute signed circuit");
+
+        for (chunk, x) in result.bits.chunks(24).zip(i8::MIN..=i8::MAX) {
+            let neg: u8 = GarbledUint8::new(chunk[..8].to_vec()).into();
+            let abs: u8 = GarbledUint8::new(chunk[8..16].to_vec()).into();
+            let signum: u8 = GarbledUint8::new(chunk[16..].to_vec()).into();
+            assert_eq!(neg as i8, x.wrapping_neg(), "-{}", x);
+            assert_eq!(abs as i8, x.wrapping_abs(), "abs({})", x);
+            assert_eq!(signum as i8, x.signum(), "signum({})", x);
+        }
+    }
+
+    #[test]
+    fn test_shifts_by_public_amount() {
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b1001_0110_u8));
+        let gates = builder.len();
+
+        let mut output = GateIndexVec::default();
+        for amount in 0..=8 {
+            let shl = builder.shl(&a, amount);
+            let shr = builder.shr(&a, amount);
+            let sar = builder.sar(&a, amount);
+            let rotate_left = builder.rotate_left(&a, amount);
+            let rotate_right = builder.rotate_right(&a, amount);
+            output.push_all(&shl);
+            output.push_all(&shr);
+            output.push_all(&sar);
+            output.push_all(&rotate_left);
+            output.push_all(&rotate_right);
+        }
+
+        // only the constant zero wire is added
+        assert!(builder.len() <= gates + 1);
+
+        let circuit = builder.compile_unoptimized(&output);
+        let result = builder
+            .execute::<360>(&circuit)
+            .expect("Failed to execute shift circuit");
+
+        let x = 0b1001_0110_u8;
+        for (chunk, amount) in result.bits.chunks(40).zip(0..=8_u32) {
+            let values: Vec<u8> = chunk
+                .chunks(8)
+                .map(|bits| GarbledUint8::new(bits.to_vec()).into())
+                .collect();
+            assert_eq!(values[0], x.checked_shl(amount).unwrap_or(0));
+            assert_eq!(values[1], x.checked_shr(amount).unwrap_or(0));
+            assert_eq!(values[2] as i8, (x as i8) >> amount.min(7));
+            assert_eq!(values[3], x.rotate_left(amount));
+            assert_eq!(values[4], x.rotate_right(amount));
+        }
+    }
+
+    #[test]
+    fn test_shifts_by_secret_amount() {
+        let values = [0b1001_0110_u8, 0b0111_0001, 0xff, 1];
+        for x in values {
+            let mut builder = CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(x));
+
+            let mut output = GateIndexVec::default();
+            for amount in 0..8_u8 {
+                let amount = builder.input(&GarbledUint8::from(amount));
+                let shl = builder.shl_var(&a, &amount);
+                let shr = builder.shr_var(&a, &amount);
+                let sar = builder.sar_var(&a, &amount);
+                let rotate_left = builder.rotate_left_var(&a, &amount);
+                let rotate_right = builder.rotate_right_var(&a, &amount);
+                output.push_all(&shl);
+                output.push_all(&shr);
+                output.push_all(&sar);
+                output.push_all(&rotate_left);
+                output.push_all(&rotate_right);
+            }
+
+            let result = builder
+                .compile_and_execute::<320>(&output)
+                .expect("Failed to execute shift circuit");
+
+            for (chunk, amount) in result.bits.chunks(40).zip(0..8_u32) {
+                let values: Vec<u8> = chunk
+                    .chunks(8)
+                    .map(|bits| GarbledUint8::new(bits.to_vec()).into())
+                    .collect();
+                assert_eq!(values[0], x << amount);
+                assert_eq!(values[1], x >> amount);
+                assert_eq!(values[2] as i8, (x as i8) >> amount);
+                assert_eq!(values[3], x.rotate_left(amount));
+                assert_eq!(values[4], x.rotate_right(amount));
+            }
+        }
+    }
+
+    #[test]
+    fn test_shift_by_constant_amount() {
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b1001_0110_u8));
+        let amount = builder.constant(&GarbledUint8::from(3_u8));
+
+        let output = builder.shl_var(&a, &amount);
+        let circuit = builder.compile_unoptimized(&output);
+
+        // a constant amount is rewired without any MUX layers
+        assert_eq!(circuit.and_gates(), 0);
+    }
+
+    #[test]
+    fn test_shifts_of_empty_vector() {
+        let mut builder = CircuitBuilder::default();
+        let empty = GateIndexVec::default();
+        let amount = builder.input(&GarbledUint8::from(3_u8));
+
+        assert!(builder.shl(&empty, 3).is_empty());
+        assert!(builder.shr(&empty, 3).is_empty());
+        assert!(builder.sar(&empty, 3).is_empty());
+        assert!(builder.rotate_left(&empty, 3).is_empty());
+        assert!(builder.rotate_right(&empty, 3).is_empty());
+        assert!(builder.shl_var(&empty, &amount).is_empty());
+        assert!(builder.rotate_left_var(&empty, &amount).is_empty());
+        assert!(builder.rotate_right_var(&empty, &amount).is_empty());
+    }
+
+    #[test]
+    fn test_mux_and_gates() {
+        let a: GarbledUint8 = 12_u8.into();
+        let b: GarbledUint8 = 10_u8.into();
+        let s: GarbledBit = true.into();
+
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&a);
+        let b = builder.input(&b);
+        let s = builder.input(&s);
+
+        let output = builder.mux(&s[0], &a, &b);
+        let circuit = builder.compile_unoptimized(&output);
+
+        // one AND gate per selected bit
+        assert_eq!(circuit.and_gates(), 8);
+    }
+
+    #[test]
+    fn test_comparison_and_gates() {
+        let a: GarbledUint8 = 12_u8.into();
+        let b: GarbledUint8 = 10_u8.into();
+
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&a);
+        let b = builder.input(&b);
+
+        let lt = builder.lt(&a, &b);
+        let circuit = builder.compile_unoptimized(&vec![lt].into());
+
+        // one AND gate per bit of the borrow chain
+        assert_eq!(circuit.and_gates(), 8);
+
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(12_u8));
+        let b = builder.input(&GarbledUint8::from(10_u8));
+
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile_unoptimized(&sum);
+
+        // one AND gate per bit of the carry chain
+        assert_eq!(circuit.and_gates(), 8);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let values = [0_u8, 1, 2, 127, 128, 129, 200, 254, 255];
+        for &x in values.iter() {
+            for &y in values.iter() {
+                let mut builder = CircuitBuilder::default();
+                let a = builder.input(&GarbledUint8::from(x));
+                let b = builder.input(&GarbledUint8::from(y));
+
+                let lt = builder.lt(&a, &b);
+                let gt = builder.gt(&a, &b);
+                let le = builder.le(&a, &b);
+                let ge = builder.ge(&a, &b);
+                let (cmp_lt, cmp_eq) = builder.compare(&a, &b);
+
+                let result = builder
+                    .compile_and_execute::<6>(&vec![lt, gt, le, ge, cmp_lt, cmp_eq].into())
+                    .expect("Failed to execute comparison circuit");
+
+                assert_eq!(
+                    result.bits,
+                    vec![x < y, x > y, x <= y, x >= y, x < y, x == y],
+                    "comparing {} and {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_build_and_execute_mixed() {
         fn build_and_execute_mixed<const N: usize>(
@@ -1,2 +1,3 @@
 pub mod builder;
+pub mod optimizer;
 pub mod types;
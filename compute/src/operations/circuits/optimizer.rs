@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use tandem::{Circuit, Gate, GateIndex};
+
+/// Upper bound on the number of simplification rounds, each round only ever shrinks the circuit
+const MAX_ROUNDS: usize = 8;
+
+/// Gate counts before and after running the optimizer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptimizationStats {
+    pub gates_before: usize,
+    pub gates_after: usize,
+    pub and_gates_before: usize,
+    pub and_gates_after: usize,
+}
+
+impl Display for OptimizationStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "gates: {} -> {}, and gates: {} -> {}",
+            self.gates_before, self.gates_after, self.and_gates_before, self.and_gates_after
+        )
+    }
+}
+
+/// Runs the optimization pipeline over a gate list.
+///
+/// Every round rebuilds the circuit with constant folding, double-negation removal, common
+/// subexpression elimination and AND-minimizing rewrites, then drops gates that no output depends
+/// on. Input gates are always kept in their original order, so the optimized circuit accepts
+/// exactly the same contributor and evaluator inputs.
+pub fn optimize(
+    gates: &[Gate],
+    output_gates: &[GateIndex],
+) -> (Vec<Gate>, Vec<GateIndex>, OptimizationStats) {
+    let mut stats = OptimizationStats {
+        gates_before: gates.len(),
+        and_gates_before: count_and_gates(gates),
+        ..Default::default()
+    };
+
+    let mut gates = gates.to_vec();
+    let mut outputs = output_gates.to_vec();
+
+    for _ in 0..MAX_ROUNDS {
+        let size = (gates.len(), count_and_gates(&gates));
+
+        let (simplified, simplified_outputs) = rebuild(&gates, &outputs, false);
+        let (simplified, simplified_outputs) =
+            eliminate_dead_gates(&simplified, &simplified_outputs);
+        let (distributed, distributed_outputs) = rebuild(&simplified, &simplified_outputs, true);
+        (gates, outputs) = eliminate_dead_gates(&distributed, &distributed_outputs);
+
+        if (gates.len(), count_and_gates(&gates)) == size {
+            break;
+        }
+    }
+
+    stats.gates_after = gates.len();
+    stats.and_gates_after = count_and_gates(&gates);
+    (gates, outputs, stats)
+}
+
+/// Optimizes an already compiled circuit, e.g. one received from a peer
+pub fn optimize_circuit(circuit: &Circuit) -> (Circuit, OptimizationStats) {
+    let (gates, outputs, stats) = optimize(circuit.gates(), circuit.output_gates());
+    (Circuit::new(gates, outputs), stats)
+}
+
+pub fn count_and_gates(gates: &[Gate]) -> usize {
+    gates
+        .iter()
+        .filter(|gate| matches!(gate, Gate::And(_, _)))
+        .count()
+}
+
+/// Key used for hash-consing; operands of commutative gates are stored in ascending order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum GateKey {
+    Xor(GateIndex, GateIndex),
+    And(GateIndex, GateIndex),
+    Not(GateIndex),
+}
+
+/// Builds a new gate list, simplifying every gate as it is added
+#[derive(Default)]
+struct Rebuilder {
+    gates: Vec<Gate>,
+    constants: Vec<Option<bool>>,
+    cache: HashMap<GateKey, GateIndex>,
+    zero: Option<GateIndex>,
+    one: Option<GateIndex>,
+}
+
+impl Rebuilder {
+    fn push(&mut self, gate: Gate, constant: Option<bool>) -> GateIndex {
+        let index = self.gates.len() as GateIndex;
+        self.gates.push(gate);
+        self.constants.push(constant);
+        index
+    }
+
+    fn input(&mut self, gate: Gate) -> GateIndex {
+        self.push(gate, None)
+    }
+
+    fn constant(&self, a: GateIndex) -> Option<bool> {
+        self.constants[a as usize]
+    }
+
+    /// Returns `x` if `a` is `NOT(x)`
+    fn negation_of(&self, a: GateIndex) -> Option<GateIndex> {
+        match self.gates[a as usize] {
+            Gate::Not(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn and_operands(&self, a: GateIndex) -> Option<(GateIndex, GateIndex)> {
+        match self.gates[a as usize] {
+            Gate::And(x, y) => Some((x, y)),
+            _ => None,
+        }
+    }
+
+    fn cached(&mut self, key: GateKey, gate: Gate) -> GateIndex {
+        if let Some(index) = self.cache.get(&key) {
+            return *index;
+        }
+        let index = self.push(gate, None);
+        self.cache.insert(key, index);
+        index
+    }
+
+    fn zero(&mut self) -> GateIndex {
+        if let Some(zero) = self.zero {
+            return zero;
+        }
+        // the first gate of a valid circuit is always an input
+        let zero = self.push(Gate::Xor(0, 0), Some(false));
+        self.zero = Some(zero);
+        zero
+    }
+
+    fn one(&mut self) -> GateIndex {
+        if let Some(one) = self.one {
+            return one;
+        }
+        let zero = self.zero();
+        let one = self.push(Gate::Not(zero), Some(true));
+        self.one = Some(one);
+        one
+    }
+
+    fn not(&mut self, a: GateIndex) -> GateIndex {
+        match self.constant(a) {
+            Some(true) => return self.zero(),
+            Some(false) => return self.one(),
+            None => {}
+        }
+        // double negation: !!x = x
+        if let Some(x) = self.negation_of(a) {
+            return x;
+        }
+        self.cached(GateKey::Not(a), Gate::Not(a))
+    }
+
+    fn xor(&mut self, a: GateIndex, b: GateIndex) -> GateIndex {
+        match (self.constant(a), self.constant(b)) {
+            (Some(x), Some(y)) => return if x ^ y { self.one() } else { self.zero() },
+            (Some(false), _) => return b,
+            (_, Some(false)) => return a,
+            (Some(true), _) => return self.not(b),
+            (_, Some(true)) => return self.not(a),
+            _ => {}
+        }
+        if a == b {
+            return self.zero();
+        }
+        match (self.negation_of(a), self.negation_of(b)) {
+            // !x ^ !y = x ^ y
+            (Some(x), Some(y)) => return self.xor(x, y),
+            // !x ^ x = 1
+            (Some(x), _) if x == b => return self.one(),
+            (_, Some(y)) if y == a => return self.one(),
+            _ => {}
+        }
+        let (a, b) = (a.min(b), a.max(b));
+        self.cached(GateKey::Xor(a, b), Gate::Xor(a, b))
+    }
+
+    fn and(&mut self, a: GateIndex, b: GateIndex) -> GateIndex {
+        match (self.constant(a), self.constant(b)) {
+            (Some(false), _) | (_, Some(false)) => return self.zero(),
+            (Some(true), _) => return b,
+            (_, Some(true)) => return a,
+            _ => {}
+        }
+        if a == b {
+            return a;
+        }
+        // x & !x = 0
+        if self.negation_of(a) == Some(b) || self.negation_of(b) == Some(a) {
+            return self.zero();
+        }
+        // absorption: x & (x & y) = x & y
+        if let Some((x, y)) = self.and_operands(b) {
+            if x == a || y == a {
+                return b;
+            }
+        }
+        if let Some((x, y)) = self.and_operands(a) {
+            if x == b || y == b {
+                return a;
+            }
+        }
+        let (a, b) = (a.min(b), a.max(b));
+        self.cached(GateKey::And(a, b), Gate::And(a, b))
+    }
+}
+
+/// Rebuilds the gate list through a [`Rebuilder`].
+///
+/// With `distribute` set, `(x & y) ^ (x & z)` is rewritten to `x & (y ^ z)` whenever both AND
+/// gates feed nothing but that XOR, saving one AND gate per match.
+fn rebuild(gates: &[Gate], outputs: &[GateIndex], distribute: bool) -> (Vec<Gate>, Vec<GateIndex>) {
+    let fanout = if distribute {
+        count_fanout(gates, outputs)
+    } else {
+        vec![]
+    };
+
+    let mut rebuilder = Rebuilder::default();
+    let mut remap: Vec<GateIndex> = Vec::with_capacity(gates.len());
+
+    for gate in gates {
+        let index = match gate {
+            Gate::InContrib | Gate::InEval => rebuilder.input(gate.clone()),
+            Gate::Not(a) => rebuilder.not(remap[*a as usize]),
+            Gate::And(a, b) => rebuilder.and(remap[*a as usize], remap[*b as usize]),
+            Gate::Xor(a, b) => {
+                let shared = if distribute && fanout[*a as usize] == 1 && fanout[*b as usize] == 1 {
+                    shared_and_operand(gates, *a, *b)
+                } else {
+                    None
+                };
+
+                match shared {
+                    Some((x, y, z)) => {
+                        let (x, y, z) = (remap[x as usize], remap[y as usize], remap[z as usize]);
+                        let y_xor_z = rebuilder.xor(y, z);
+                        rebuilder.and(x, y_xor_z)
+                    }
+                    None => rebuilder.xor(remap[*a as usize], remap[*b as usize]),
+                }
+            }
+        };
+        remap.push(index);
+    }
+
+    let outputs = outputs
+        .iter()
+        .map(|output| remap[*output as usize])
+        .collect();
+    (rebuilder.gates, outputs)
+}
+
+/// For `a = x & y` and `b = x & z` returns `(x, y, z)`
+fn shared_and_operand(
+    gates: &[Gate],
+    a: GateIndex,
+    b: GateIndex,
+) -> Option<(GateIndex, GateIndex, GateIndex)> {
+    if a == b {
+        return None;
+    }
+    match (&gates[a as usize], &gates[b as usize]) {
+        (Gate::And(a1, a2), Gate::And(b1, b2)) => {
+            if a1 == b1 {
+                Some((*a1, *a2, *b2))
+            } else if a1 == b2 {
+                Some((*a1, *a2, *b1))
+            } else if a2 == b1 {
+                Some((*a2, *a1, *b2))
+            } else if a2 == b2 {
+                Some((*a2, *a1, *b1))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn count_fanout(gates: &[Gate], outputs: &[GateIndex]) -> Vec<u32> {
+    let mut fanout = vec![0; gates.len()];
+    for gate in gates {
+        match gate {
+            Gate::Xor(a, b) | Gate::And(a, b) => {
+                fanout[*a as usize] += 1;
+                fanout[*b as usize] += 1;
+            }
+            Gate::Not(a) => fanout[*a as usize] += 1,
+            Gate::InContrib | Gate::InEval => {}
+        }
+    }
+    for output in outputs {
+        fanout[*output as usize] += 1;
+    }
+    fanout
+}
+
+/// Removes every gate no output depends on, input gates are always kept
+fn eliminate_dead_gates(gates: &[Gate], outputs: &[GateIndex]) -> (Vec<Gate>, Vec<GateIndex>) {
+    let mut live = vec![false; gates.len()];
+    for output in outputs {
+        live[*output as usize] = true;
+    }
+    for (index, gate) in gates.iter().enumerate().rev() {
+        match gate {
+            Gate::InContrib | Gate::InEval => live[index] = true,
+            Gate::Xor(a, b) | Gate::And(a, b) if live[index] => {
+                live[*a as usize] = true;
+                live[*b as usize] = true;
+            }
+            Gate::Not(a) if live[index] => live[*a as usize] = true,
+            _ => {}
+        }
+    }
+
+    let mut remap = vec![0; gates.len()];
+    let mut kept = Vec::with_capacity(gates.len());
+    for (index, gate) in gates.iter().enumerate() {
+        if !live[index] {
+            continue;
+        }
+        remap[index] = kept.len() as GateIndex;
+        kept.push(match gate {
+            Gate::InContrib => Gate::InContrib,
+            Gate::InEval => Gate::InEval,
+            Gate::Xor(a, b) => Gate::Xor(remap[*a as usize], remap[*b as usize]),
+            Gate::And(a, b) => Gate::And(remap[*a as usize], remap[*b as usize]),
+            Gate::Not(a) => Gate::Not(remap[*a as usize]),
+        });
+    }
+
+    let outputs = outputs
+        .iter()
+        .map(|output| remap[*output as usize])
+        .collect();
+    (kept, outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::get_executor;
+
+    fn evaluate(
+        gates: &[Gate],
+        outputs: &[GateIndex],
+        contrib: &[bool],
+        eval: &[bool],
+    ) -> Vec<bool> {
+        let circuit = Circuit::new(gates.to_vec(), outputs.to_vec());
+        get_executor()
+            .execute(&circuit, contrib, eval)
+            .expect("Failed to execute circuit")
+    }
+
+    #[test]
+    fn test_dead_gates() {
+        let gates = vec![
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::And(0, 1),
+            Gate::Xor(0, 1),
+            Gate::Not(2),
+        ];
+        let (optimized, outputs, stats) = optimize(&gates, &[3]);
+
+        assert_eq!(
+            optimized,
+            vec![Gate::InContrib, Gate::InContrib, Gate::Xor(0, 1)]
+        );
+        assert_eq!(outputs, vec![2]);
+        assert_eq!(stats.gates_before, 5);
+        assert_eq!(stats.gates_after, 3);
+        assert_eq!(stats.and_gates_before, 1);
+        assert_eq!(stats.and_gates_after, 0);
+    }
+
+    #[test]
+    fn test_unused_inputs_are_kept() {
+        let gates = vec![Gate::InContrib, Gate::InEval, Gate::InContrib, Gate::Not(0)];
+        let (optimized, outputs, _) = optimize(&gates, &[3]);
+
+        assert_eq!(
+            optimized,
+            vec![Gate::InContrib, Gate::InEval, Gate::InContrib, Gate::Not(0)]
+        );
+        assert_eq!(outputs, vec![3]);
+    }
+
+    #[test]
+    fn test_common_subexpressions() {
+        let gates = vec![
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::And(0, 1),
+            Gate::And(1, 0),
+            Gate::Xor(2, 0),
+            Gate::Xor(3, 0),
+        ];
+        let (optimized, outputs, stats) = optimize(&gates, &[4, 5]);
+
+        assert_eq!(stats.and_gates_after, 1);
+        assert_eq!(optimized.len(), 4);
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn test_double_negation() {
+        let gates = vec![
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::Not(0),
+            Gate::Not(2),
+            Gate::And(3, 1),
+        ];
+        let (optimized, outputs, _) = optimize(&gates, &[4]);
+
+        assert_eq!(
+            optimized,
+            vec![Gate::InContrib, Gate::InContrib, Gate::And(0, 1)]
+        );
+        assert_eq!(outputs, vec![2]);
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let gates = vec![
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::Xor(0, 0), // 0
+            Gate::Not(2),    // 1
+            Gate::And(1, 3), // x & 1 = x
+            Gate::And(1, 2), // x & 0 = 0
+            Gate::Xor(4, 5), // x ^ 0 = x
+        ];
+        let (optimized, outputs, stats) = optimize(&gates, &[6]);
+
+        assert_eq!(optimized, vec![Gate::InContrib, Gate::InContrib]);
+        assert_eq!(outputs, vec![1]);
+        assert_eq!(stats.and_gates_after, 0);
+    }
+
+    #[test]
+    fn test_distribute_and() {
+        // (a & b) ^ (a & c) = a & (b ^ c)
+        let gates = vec![
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::And(0, 1),
+            Gate::And(2, 0),
+            Gate::Xor(3, 4),
+        ];
+        let (optimized, outputs, stats) = optimize(&gates, &[5]);
+
+        assert_eq!(stats.and_gates_before, 2);
+        assert_eq!(stats.and_gates_after, 1);
+
+        for input in 0..8u8 {
+            let contrib: Vec<bool> = (0..3).map(|i| (input >> i) & 1 == 1).collect();
+            assert_eq!(
+                evaluate(&gates, &[5], &contrib, &[]),
+                evaluate(&optimized, &outputs, &contrib, &[])
+            );
+        }
+    }
+
+    #[test]
+    fn test_shared_and_is_not_distributed() {
+        // the AND gates are outputs themselves, rewriting would add an AND gate
+        let gates = vec![
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::InContrib,
+            Gate::And(0, 1),
+            Gate::And(0, 2),
+            Gate::Xor(3, 4),
+        ];
+        let (_, _, stats) = optimize(&gates, &[3, 4, 5]);
+        assert_eq!(stats.and_gates_after, 2);
+    }
+}
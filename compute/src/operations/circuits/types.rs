@@ -44,6 +44,10 @@ impl GateIndexVec {
     pub fn truncate(&mut self, len: usize) {
         self.0.truncate(len);
     }
+
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Self {
+        Self(self.0[range].to_vec())
+    }
 }
 
 // Implement indexing for GateVector
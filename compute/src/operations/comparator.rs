@@ -1,6 +1,6 @@
 use crate::int::GarbledInt;
 use crate::operations::circuits::builder::{
-    build_and_execute_comparator, build_and_execute_equality,
+    build_and_execute_comparator, build_and_execute_equality, build_and_execute_signed_comparator,
 };
 use crate::uint::GarbledUint;
 use std::cmp::Ordering;
@@ -36,7 +36,7 @@ impl<const N: usize> Ord for GarbledUint<N> {
 // Implementing comparison operators for GarbledUint
 impl<const N: usize> PartialEq<&GarbledUint<N>> for GarbledUint<N> {
     fn eq(&self, other: &&Self) -> bool {
-        !build_and_execute_equality(self, other)
+        build_and_execute_equality(self, other)
     }
 }
 
@@ -51,7 +51,7 @@ impl<const N: usize> PartialOrd<&GarbledUint<N>> for GarbledUint<N> {
 impl<const N: usize> PartialEq for GarbledInt<N> {
     fn eq(&self, other: &Self) -> bool {
         matches!(
-            build_and_execute_comparator(&self.into(), &other.into()),
+            build_and_execute_signed_comparator(&self.into(), &other.into()),
             Ordering::Equal
         )
     }
@@ -67,13 +67,16 @@ impl<const N: usize> Eq for GarbledInt<N> {
 #[allow(clippy::non_canonical_partial_ord_impl)]
 impl<const N: usize> PartialOrd for GarbledInt<N> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(build_and_execute_comparator(&self.into(), &other.into()))
+        Some(build_and_execute_signed_comparator(
+            &self.into(),
+            &other.into(),
+        ))
     }
 }
 
 // Implementing comparison operators for GarbledInt
 impl<const N: usize> Ord for GarbledInt<N> {
     fn cmp(&self, other: &Self) -> Ordering {
-        build_and_execute_comparator(&self.into(), &other.into())
+        build_and_execute_signed_comparator(&self.into(), &other.into())
     }
 }
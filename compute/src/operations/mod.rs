@@ -3,3 +3,4 @@ pub mod bitwise;
 pub mod circuits;
 pub mod comparator;
 pub mod mux;
+pub mod util;
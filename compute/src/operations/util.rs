@@ -18,9 +18,9 @@ pub enum GateW {
     Not(GateIndex),
 }
 
-impl Into<Gate> for GateW {
-    fn into(self) -> Gate {
-        match self {
+impl From<&GateW> for Gate {
+    fn from(gate: &GateW) -> Self {
+        match *gate {
             GateW::InContrib => Gate::InContrib,
             GateW::InEval => Gate::InEval,
             GateW::Xor(a, b) => Gate::Xor(a, b),
@@ -30,9 +30,9 @@ impl Into<Gate> for GateW {
     }
 }
 
-impl From<Gate> for GateW {
-    fn from(gate: Gate) -> Self {
-        match gate {
+impl From<&Gate> for GateW {
+    fn from(gate: &Gate) -> Self {
+        match *gate {
             Gate::InContrib => GateW::InContrib,
             Gate::InEval => GateW::InEval,
             Gate::Xor(a, b) => GateW::Xor(a, b),
@@ -56,11 +56,7 @@ pub struct CircuitWrapper {
 impl From<&Circuit> for CircuitWrapper {
     fn from(circuit: &Circuit) -> Self {
         CircuitWrapper {
-            gates: circuit
-                .gates()
-                .iter()
-                .map(|gate| gate.clone().into())
-                .collect(),
+            gates: circuit.gates().iter().map(GateW::from).collect(),
             output_gates: circuit.output_gates().clone(),
             and_gates: circuit.and_gates(),
             eval_inputs: circuit.eval_inputs(),
@@ -69,11 +65,11 @@ impl From<&Circuit> for CircuitWrapper {
     }
 }
 
-impl Into<Circuit> for CircuitWrapper {
-    fn into(self) -> Circuit {
+impl From<CircuitWrapper> for Circuit {
+    fn from(wrapper: CircuitWrapper) -> Self {
         Circuit::new(
-            self.gates.iter().map(|gate| gate.clone().into()).collect(),
-            self.output_gates,
+            wrapper.gates.iter().map(Gate::from).collect(),
+            wrapper.output_gates,
         )
     }
 }
@@ -103,7 +99,7 @@ mod tests {
 
     #[test]
     fn test_serialize_deserialize_circuit_struct() -> anyhow::Result<()> {
-        #[circuit(compile)]
+        #[encrypted(compile)]
         fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> u8 {
             let res = a * b;
             let res = res + c;
@@ -111,7 +107,7 @@ mod tests {
         }
 
         // Initialize the evaluator instance with circuit and dummy input
-        let (circuit, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+        let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
 
         // Serialize the circuit
         let serialized_data = serialize_circuit(&circuit)?;
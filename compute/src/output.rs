@@ -0,0 +1,283 @@
+//! Typed circuit outputs, as sent by the party that learned them.
+//!
+//! An `Output` carries the output bits packed eight to a byte, first output bit in the least
+//! significant bit of the first byte, along with an `OutputSchema` describing how to read them.
+//! The receiver decodes it into the type it expects with `Output::decode`, which fails instead of
+//! misreading the bits when the schema does not match that type.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::int::GarbledInt;
+use crate::transport::{MessageKind, Transport};
+use crate::uint::GarbledUint;
+
+/// How the output bits of a circuit are read, starting from the first output bit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OutputSchema {
+    Bool,
+    /// An unsigned integer of the given width, least significant bit first.
+    Uint(usize),
+    /// A two's complement integer of the given width, least significant bit first.
+    Int(usize),
+    /// The outputs of the elements, one after another.
+    Tuple(Vec<OutputSchema>),
+}
+
+impl OutputSchema {
+    /// Number of output bits described by the schema.
+    pub fn width(&self) -> usize {
+        match self {
+            OutputSchema::Bool => 1,
+            OutputSchema::Uint(width) | OutputSchema::Int(width) => *width,
+            // saturating, as schemas received from the other party are not trusted
+            OutputSchema::Tuple(elements) => elements
+                .iter()
+                .map(OutputSchema::width)
+                .fold(0, usize::saturating_add),
+        }
+    }
+
+    /// The fields of a tuple schema, or the schema itself for any other schema.
+    pub fn elements(&self) -> &[OutputSchema] {
+        match self {
+            OutputSchema::Tuple(elements) => elements,
+            _ => std::slice::from_ref(self),
+        }
+    }
+}
+
+impl fmt::Display for OutputSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputSchema::Bool => write!(f, "bool"),
+            OutputSchema::Uint(width) => write!(f, "u{}", width),
+            OutputSchema::Int(width) => write!(f, "i{}", width),
+            OutputSchema::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Why an output could not be built or decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputError {
+    /// The output was decoded into a type with a different schema.
+    SchemaMismatch {
+        expected: OutputSchema,
+        found: OutputSchema,
+    },
+    /// The number of output bits differs from the width of the schema.
+    WidthMismatch { schema: OutputSchema, bits: usize },
+    /// The packed bits are not `width` bits padded with zeros to whole bytes.
+    InvalidPacking { width: usize, bytes: usize },
+    /// An output policy names a different number of recipients than the schema has elements.
+    PolicyMismatch {
+        schema: OutputSchema,
+        recipients: usize,
+    },
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputError::SchemaMismatch { expected, found } => write!(
+                f,
+                "Expected an output of type {} but received one of type {}",
+                expected, found
+            ),
+            OutputError::WidthMismatch { schema, bits } => write!(
+                f,
+                "An output of type {} has {} bits, not {}",
+                schema,
+                schema.width(),
+                bits
+            ),
+            OutputError::InvalidPacking { width, bytes } => write!(
+                f,
+                "{} bytes are not a valid packing of {} output bits",
+                bytes, width
+            ),
+            OutputError::PolicyMismatch { schema, recipients } => write!(
+                f,
+                "An output of type {} has {} elements, but the policy names {} recipients",
+                schema,
+                schema.elements().len(),
+                recipients
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+/// A type that outputs can be decoded into.
+///
+/// Implemented for `bool`, `GarbledUint<N>`, `GarbledInt<N>` and tuples of up to six of them, and
+/// can be implemented for other types that read their value from a fixed number of bits.
+pub trait FromOutput: Sized {
+    /// The schema of outputs holding a value of this type.
+    fn schema() -> OutputSchema;
+
+    /// Builds a value from exactly `schema().width()` bits.
+    fn from_bits(bits: &[bool]) -> Self;
+}
+
+impl FromOutput for bool {
+    fn schema() -> OutputSchema {
+        OutputSchema::Bool
+    }
+
+    fn from_bits(bits: &[bool]) -> Self {
+        bits[0]
+    }
+}
+
+impl<const N: usize> FromOutput for GarbledUint<N> {
+    fn schema() -> OutputSchema {
+        OutputSchema::Uint(N)
+    }
+
+    fn from_bits(bits: &[bool]) -> Self {
+        GarbledUint::new(bits.to_vec())
+    }
+}
+
+impl<const N: usize> FromOutput for GarbledInt<N> {
+    fn schema() -> OutputSchema {
+        OutputSchema::Int(N)
+    }
+
+    fn from_bits(bits: &[bool]) -> Self {
+        GarbledInt::new(bits.to_vec())
+    }
+}
+
+macro_rules! impl_from_output_for_tuple {
+    ($($element:ident),+) => {
+        impl<$($element: FromOutput),+> FromOutput for ($($element,)+) {
+            fn schema() -> OutputSchema {
+                OutputSchema::Tuple(vec![$($element::schema()),+])
+            }
+
+            fn from_bits(bits: &[bool]) -> Self {
+                let mut bits = bits.iter().copied();
+                ($({
+                    let width = $element::schema().width();
+                    let element: Vec<bool> = bits.by_ref().take(width).collect();
+                    $element::from_bits(&element)
+                },)+)
+            }
+        }
+    };
+}
+
+impl_from_output_for_tuple!(A);
+impl_from_output_for_tuple!(A, B);
+impl_from_output_for_tuple!(A, B, C);
+impl_from_output_for_tuple!(A, B, C, D);
+impl_from_output_for_tuple!(A, B, C, D, E);
+impl_from_output_for_tuple!(A, B, C, D, E, F);
+
+/// Output bits of a circuit together with their schema.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    schema: OutputSchema,
+    data: Vec<u8>,
+}
+
+impl Output {
+    /// Packs the output `bits` of a circuit, which must be as many as the schema's width.
+    pub fn new(schema: OutputSchema, bits: &[bool]) -> Result<Self, OutputError> {
+        if bits.len() != schema.width() {
+            return Err(OutputError::WidthMismatch {
+                schema,
+                bits: bits.len(),
+            });
+        }
+
+        let mut data = vec![0_u8; bits.len().div_ceil(8)];
+        for (i, bit) in bits.iter().enumerate() {
+            data[i / 8] |= u8::from(*bit) << (i % 8);
+        }
+        Ok(Output { schema, data })
+    }
+
+    pub fn schema(&self) -> &OutputSchema {
+        &self.schema
+    }
+
+    /// Number of output bits.
+    pub fn len(&self) -> usize {
+        self.schema.width()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The output bits in circuit order.
+    pub fn bits(&self) -> Vec<bool> {
+        (0..self.len())
+            .map(|i| self.data[i / 8] >> (i % 8) & 1 == 1)
+            .collect()
+    }
+
+    /// Decodes the output into `T`, failing if its schema is not the schema of `T`.
+    pub fn decode<T: FromOutput>(&self) -> Result<T, OutputError> {
+        let expected = T::schema();
+        if expected != self.schema {
+            return Err(OutputError::SchemaMismatch {
+                expected,
+                found: self.schema.clone(),
+            });
+        }
+        Ok(T::from_bits(&self.bits()))
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Deserializes an output written with `to_bytes`, checking that the bits match the schema.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let output: Output = bincode::deserialize(bytes)?;
+        let width = output.schema.width();
+        // the unused high bits of the last byte must be zero
+        let valid = output.data.len() == width.div_ceil(8)
+            && (width.is_multiple_of(8) || output.data[width / 8] >> (width % 8) == 0);
+        if !valid {
+            return Err(OutputError::InvalidPacking {
+                width,
+                bytes: output.data.len(),
+            }
+            .into());
+        }
+        Ok(output)
+    }
+}
+
+/// Sends `output` to the other party in an output message.
+pub async fn send_output<T: Transport>(mut transport: T, output: &Output) -> Result<()> {
+    transport
+        .send_message(MessageKind::Output, &output.to_bytes()?)
+        .await
+}
+
+/// Waits for the output sent by the other party with `send_output`.
+pub async fn recv_output<T: Transport>(mut transport: T) -> Result<Output> {
+    let data = transport.recv_kind(MessageKind::Output).await?;
+    Output::from_bytes(&data)
+}
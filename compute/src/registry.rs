@@ -0,0 +1,381 @@
+//! The circuits an evaluator is willing to run.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tandem::Circuit;
+
+use crate::delivery::OutputPolicy;
+use crate::handshake::HandshakeError;
+use crate::operations::util::deserialize_circuit;
+use crate::output::OutputSchema;
+
+/// A blake3 hash of a circuit, as returned by `Circuit::blake3_hash`.
+pub type CircuitHash = [u8; 32];
+
+/// File extension of serialized circuits picked up by `CircuitRegistry::load_dir`.
+pub const CIRCUIT_FILE_EXTENSION: &str = "circuit";
+
+// Upper bounds on the bincode encoding of a serialized circuit: the gate and output lengths and
+// the three counts take 8 bytes each, and every gate takes at most a 4 byte tag and two 4 byte
+// indices plus, for circuits with no more outputs than gates, a 4 byte output index
+const SERIALIZED_HEADER_BYTES: usize = 5 * 8;
+const SERIALIZED_GATE_BYTES: usize = 4 + 2 * 4 + 4;
+
+/// What a garbler needs to know about a registered circuit before running it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CircuitInfo {
+    pub id: String,
+    pub hash: CircuitHash,
+    /// Number of input bits provided by the garbler.
+    pub garbler_inputs: usize,
+    /// Number of input bits provided by the evaluator.
+    pub evaluator_inputs: usize,
+    /// How the input bits of each party split into parameters.
+    pub inputs: InputWidths,
+    /// Number of output bits.
+    pub outputs: usize,
+    /// How the output bits are read.
+    pub output: OutputSchema,
+    /// Which party learns each element of the output.
+    pub policy: OutputPolicy,
+    pub gates: usize,
+    pub and_gates: usize,
+}
+
+impl CircuitInfo {
+    /// Describes `circuit` with the `default_input_widths` and the `default_output_schema`,
+    /// shared with both parties.
+    pub fn new(id: impl Into<String>, circuit: &Circuit) -> Self {
+        Self::with_layout(
+            id,
+            circuit,
+            default_input_widths(circuit),
+            default_output_schema(circuit),
+            OutputPolicy::Shared,
+        )
+    }
+
+    pub fn with_layout(
+        id: impl Into<String>,
+        circuit: &Circuit,
+        inputs: InputWidths,
+        output: OutputSchema,
+        policy: OutputPolicy,
+    ) -> Self {
+        CircuitInfo {
+            id: id.into(),
+            hash: circuit.blake3_hash(),
+            garbler_inputs: circuit.contrib_inputs(),
+            evaluator_inputs: circuit.eval_inputs(),
+            inputs,
+            outputs: circuit.output_gates().len(),
+            output,
+            policy,
+            gates: circuit.gates().len(),
+            and_gates: circuit.and_gates(),
+        }
+    }
+}
+
+/// The bit widths of the parameters each party provides, in the order of the inputs.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputWidths {
+    pub garbler: Vec<usize>,
+    pub evaluator: Vec<usize>,
+}
+
+impl InputWidths {
+    pub fn new(garbler: impl Into<Vec<usize>>, evaluator: impl Into<Vec<usize>>) -> Self {
+        InputWidths {
+            garbler: garbler.into(),
+            evaluator: evaluator.into(),
+        }
+    }
+}
+
+/// The input widths of circuits registered without them: all input bits of a party as one
+/// parameter.
+pub fn default_input_widths(circuit: &Circuit) -> InputWidths {
+    let single = |bits: usize| if bits == 0 { vec![] } else { vec![bits] };
+    InputWidths::new(
+        single(circuit.contrib_inputs()),
+        single(circuit.eval_inputs()),
+    )
+}
+
+/// The schema of circuits registered without one: all output bits as one unsigned integer.
+pub fn default_output_schema(circuit: &Circuit) -> OutputSchema {
+    OutputSchema::Uint(circuit.output_gates().len())
+}
+
+/// Named circuits known to the evaluator, plus hashes of circuits that garblers may upload.
+#[derive(Debug, Default)]
+pub struct CircuitRegistry {
+    by_id: HashMap<String, Arc<Circuit>>,
+    by_hash: HashMap<CircuitHash, Arc<Circuit>>,
+    allowed_uploads: HashSet<CircuitHash>,
+    input_widths: HashMap<CircuitHash, InputWidths>,
+    output_schemas: HashMap<CircuitHash, OutputSchema>,
+    output_policies: HashMap<CircuitHash, OutputPolicy>,
+    max_gates: Option<usize>,
+}
+
+impl CircuitRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `circuit` under `id`, replacing any circuit registered under the same id.
+    pub fn register(&mut self, id: impl Into<String>, circuit: Circuit) -> Arc<Circuit> {
+        let circuit = Arc::new(circuit);
+        if let Some(previous) = self.by_id.insert(id.into(), circuit.clone()) {
+            // keep the hash if another id still refers to the replaced circuit
+            let hash = previous.blake3_hash();
+            if !self.by_id.values().any(|other| other.blake3_hash() == hash) {
+                self.by_hash.remove(&hash);
+                self.input_widths.remove(&hash);
+                self.output_schemas.remove(&hash);
+                self.output_policies.remove(&hash);
+            }
+        }
+        self.by_hash.insert(circuit.blake3_hash(), circuit.clone());
+        circuit
+    }
+
+    /// Registers the circuit serialized with `serialize_circuit` in the file at `path`.
+    ///
+    /// Fails if the circuit has more gates than `max_gates`.
+    pub fn load_file(
+        &mut self,
+        id: impl Into<String>,
+        path: impl AsRef<Path>,
+    ) -> Result<Arc<Circuit>> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read circuit file {}", path.display()))?;
+        let circuit = deserialize_circuit(&data)
+            .with_context(|| format!("Failed to deserialize circuit file {}", path.display()))?;
+        self.check_size(&circuit)
+            .with_context(|| format!("Failed to load circuit file {}", path.display()))?;
+        Ok(self.register(id, circuit))
+    }
+
+    /// Registers every `.circuit` file in `dir` under its file name without the extension.
+    ///
+    /// Returns the ids of the loaded circuits.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<Vec<String>> {
+        let dir = dir.as_ref();
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("Failed to read circuit directory {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_file()
+                && path.extension().and_then(|extension| extension.to_str())
+                    == Some(CIRCUIT_FILE_EXTENSION)
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut ids = Vec::with_capacity(paths.len());
+        for path in paths {
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .with_context(|| format!("Invalid circuit file name {}", path.display()))?
+                .to_string();
+            self.load_file(id.clone(), &path)?;
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    /// Sets the parameter widths of the circuit registered under `id`.
+    ///
+    /// Fails if no circuit is registered under `id`, a width is zero or the widths of a party do
+    /// not add up to its input bits.
+    pub fn set_input_widths(&mut self, id: &str, widths: InputWidths) -> Result<()> {
+        let Some(circuit) = self.by_id.get(id) else {
+            bail!("Unknown circuit '{}'", id);
+        };
+        for (party, widths, inputs) in [
+            ("garbler", &widths.garbler, circuit.contrib_inputs()),
+            ("evaluator", &widths.evaluator, circuit.eval_inputs()),
+        ] {
+            if widths.contains(&0) {
+                bail!("Circuit '{}' cannot have a {} input of width 0", id, party);
+            }
+            let total: usize = widths.iter().sum();
+            if total != inputs {
+                bail!(
+                    "Circuit '{}' has {} {} input bits, but the widths {:?} add up to {}",
+                    id,
+                    inputs,
+                    party,
+                    widths,
+                    total
+                );
+            }
+        }
+        self.input_widths.insert(circuit.blake3_hash(), widths);
+        Ok(())
+    }
+
+    /// How the inputs of `circuit` split into parameters, `default_input_widths` unless set for
+    /// its hash.
+    pub fn input_widths(&self, circuit: &Circuit) -> InputWidths {
+        self.input_widths
+            .get(&circuit.blake3_hash())
+            .cloned()
+            .unwrap_or_else(|| default_input_widths(circuit))
+    }
+
+    /// Sets how the outputs of the circuit registered under `id` are read.
+    ///
+    /// Fails if no circuit is registered under `id`, its output width differs from the schema's or
+    /// its output policy does not fit the schema.
+    pub fn set_output_schema(&mut self, id: &str, schema: OutputSchema) -> Result<()> {
+        let Some(circuit) = self.by_id.get(id) else {
+            bail!("Unknown circuit '{}'", id);
+        };
+        let outputs = circuit.output_gates().len();
+        if schema.width() != outputs {
+            bail!(
+                "Circuit '{}' has {} output bits, but {} has {}",
+                id,
+                outputs,
+                schema,
+                schema.width()
+            );
+        }
+        self.output_policy(circuit)
+            .recipients(&schema)
+            .with_context(|| format!("Invalid output schema for circuit '{}'", id))?;
+        self.output_schemas.insert(circuit.blake3_hash(), schema);
+        Ok(())
+    }
+
+    /// Sets which party learns each element of the output of the circuit registered under `id`.
+    ///
+    /// Set the output schema first, as the policy names one recipient per element of it.
+    pub fn set_output_policy(&mut self, id: &str, policy: OutputPolicy) -> Result<()> {
+        let Some(circuit) = self.by_id.get(id) else {
+            bail!("Unknown circuit '{}'", id);
+        };
+        policy
+            .recipients(&self.output_schema(circuit))
+            .with_context(|| format!("Invalid output policy for circuit '{}'", id))?;
+        self.output_policies.insert(circuit.blake3_hash(), policy);
+        Ok(())
+    }
+
+    /// How the outputs of `circuit` are read, `default_output_schema` unless set for its hash.
+    pub fn output_schema(&self, circuit: &Circuit) -> OutputSchema {
+        self.output_schemas
+            .get(&circuit.blake3_hash())
+            .cloned()
+            .unwrap_or_else(|| default_output_schema(circuit))
+    }
+
+    /// Who learns the outputs of `circuit`, `OutputPolicy::Shared` unless set for its hash.
+    pub fn output_policy(&self, circuit: &Circuit) -> OutputPolicy {
+        self.output_policies
+            .get(&circuit.blake3_hash())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn info_of(&self, id: &str, circuit: &Circuit) -> CircuitInfo {
+        CircuitInfo::with_layout(
+            id,
+            circuit,
+            self.input_widths(circuit),
+            self.output_schema(circuit),
+            self.output_policy(circuit),
+        )
+    }
+
+    /// Allows garblers to upload the circuit with `hash` without registering it by name.
+    pub fn allow_upload(&mut self, hash: CircuitHash) {
+        self.allowed_uploads.insert(hash);
+    }
+
+    /// Limits circuits loaded from files or uploaded by garblers to `max_gates` gates.
+    ///
+    /// Circuits passed to `register` directly are trusted and not limited.
+    pub fn set_max_gates(&mut self, max_gates: usize) {
+        self.max_gates = Some(max_gates);
+    }
+
+    pub fn max_gates(&self) -> Option<usize> {
+        self.max_gates
+    }
+
+    /// Fails with `HandshakeError::CircuitTooLarge` if `circuit` exceeds `max_gates`.
+    pub fn check_size(&self, circuit: &Circuit) -> Result<(), HandshakeError> {
+        let gates = circuit.gates().len();
+        match self.max_gates {
+            Some(max) if gates > max => Err(HandshakeError::CircuitTooLarge { gates, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails with `HandshakeError::UploadTooLarge` if an upload of `bytes` bytes cannot hold a
+    /// circuit within `max_gates`, so oversized uploads are refused before they are deserialized.
+    pub fn check_upload_size(&self, bytes: usize) -> Result<(), HandshakeError> {
+        match self.max_gates {
+            Some(max_gates) => {
+                let max = max_gates
+                    .saturating_mul(SERIALIZED_GATE_BYTES)
+                    .saturating_add(SERIALIZED_HEADER_BYTES);
+                if bytes > max {
+                    return Err(HandshakeError::UploadTooLarge { bytes, max });
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Arc<Circuit>> {
+        self.by_id.get(id)
+    }
+
+    pub fn get_by_hash(&self, hash: &CircuitHash) -> Option<&Arc<Circuit>> {
+        self.by_hash.get(hash)
+    }
+
+    /// Whether a circuit with `hash` may be run, either registered or allowed for upload.
+    pub fn is_allowed(&self, hash: &CircuitHash) -> bool {
+        self.by_hash.contains_key(hash) || self.allowed_uploads.contains(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    pub fn info(&self, id: &str) -> Option<CircuitInfo> {
+        self.by_id.get(id).map(|circuit| self.info_of(id, circuit))
+    }
+
+    /// Metadata of every registered circuit, ordered by id.
+    pub fn infos(&self) -> Vec<CircuitInfo> {
+        let mut infos: Vec<CircuitInfo> = self
+            .by_id
+            .iter()
+            .map(|(id, circuit)| self.info_of(id, circuit))
+            .collect();
+        infos.sort_by(|a, b| a.id.cmp(&b.id));
+        infos
+    }
+}
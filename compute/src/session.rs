@@ -0,0 +1,106 @@
+//! Reasons for aborting a session, as reported to the other party.
+//!
+//! A party that gives up on a session sends a `SessionError` in an error frame before closing its
+//! end, so the other party fails with the cause instead of a lost connection. `Transport::recv_kind`
+//! turns a received error frame into a `PeerError`. Only the kind of failure is sent; the full
+//! error stays in the local log, so no details of the reporting party reach the other one.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::handshake::HandshakeError;
+use crate::output::OutputError;
+use crate::transport::{FrameError, MessageKind, Transport};
+
+/// Upper bound on the time spent reporting an error, as the other party may not be reading.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Why a party aborted a session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    /// A message could not be decoded or arrived out of order.
+    InvalidMessage,
+    /// The garbling protocol failed, for example on garbled material that does not verify.
+    Protocol,
+    /// The session did not finish within the time limit of the sender.
+    Timeout(Duration),
+    /// The sender stopped the session before it finished.
+    Cancelled,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::InvalidMessage => write!(f, "Invalid message"),
+            SessionError::Protocol => write!(f, "Protocol error"),
+            SessionError::Timeout(limit) => {
+                write!(f, "The session did not finish within {:?}", limit)
+            }
+            SessionError::Cancelled => write!(f, "The session was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl SessionError {
+    /// The error to report to the other party for a failed session, if any.
+    ///
+    /// Nothing is reported for failures the other party already knows about: errors it sent,
+    /// rejected handshakes, which are answered in the handshake, and broken connections.
+    pub fn to_report(error: &anyhow::Error) -> Option<SessionError> {
+        if error.is::<PeerError>() || error.is::<HandshakeError>() || error.is::<std::io::Error>() {
+            return None;
+        }
+        if let Some(error) = error.downcast_ref::<SessionError>() {
+            return Some(error.clone());
+        }
+        if let Some(error) = error.downcast_ref::<FrameError>() {
+            return match error {
+                FrameError::Io(_) | FrameError::ConnectionClosed | FrameError::Truncated { .. } => {
+                    None
+                }
+                _ => Some(SessionError::InvalidMessage),
+            };
+        }
+        if error.is::<bincode::Error>() || error.is::<OutputError>() {
+            return Some(SessionError::InvalidMessage);
+        }
+        Some(SessionError::Protocol)
+    }
+}
+
+/// A `SessionError` received from the other party.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerError(pub SessionError);
+
+impl fmt::Display for PeerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The other party aborted the session: {}", self.0)
+    }
+}
+
+impl std::error::Error for PeerError {}
+
+/// Tells the other party why the session failed with `error`, if it does not know already.
+///
+/// Reporting is best effort: it gives up after a short time and ignores transport failures.
+/// The full error is logged locally. Returns the reported error.
+pub async fn report_error<T: Transport>(
+    mut transport: T,
+    error: &anyhow::Error,
+) -> Option<SessionError> {
+    let report = SessionError::to_report(error)?;
+    warn!("Aborting the session ({}): {:#}", report, error);
+    let _ = tokio::time::timeout(REPORT_TIMEOUT, send_error(&mut transport, &report)).await;
+    Some(report)
+}
+
+async fn send_error<T: Transport>(transport: &mut T, error: &SessionError) -> Result<()> {
+    transport
+        .send_message(MessageKind::Error, &bincode::serialize(error)?)
+        .await
+}
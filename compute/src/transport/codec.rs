@@ -0,0 +1,259 @@
+//! The frame format shared by every byte stream transport.
+//!
+//! A frame is a fixed header followed by the payload:
+//!
+//! | bytes | field                                   |
+//! |-------|-----------------------------------------|
+//! | 4     | payload length, little-endian u32       |
+//! | 1     | protocol version                        |
+//! | 1     | message kind                            |
+//! | 8     | session id, little-endian u64           |
+//!
+//! `FrameCodec::decode` works on a buffer of whatever bytes have arrived so far, so frames split
+//! across reads and several frames coalesced into one read are both handled.
+
+use bytes::{Buf, BufMut, BytesMut};
+use std::fmt;
+use std::io;
+
+/// Version of the frame format and of the messages carried in it.
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// Size of the header in front of every payload.
+pub const HEADER_SIZE: usize = 14;
+
+/// Largest payload accepted unless configured otherwise.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// What a frame carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// A message of the garbling protocol itself.
+    Protocol,
+    /// The circuit output, forwarded by the party that learned it.
+    Output,
+    /// Circuit negotiation before the protocol starts.
+    Handshake,
+    /// A `SessionError` telling the other party why the sender aborted the session.
+    Error,
+}
+
+impl MessageKind {
+    fn tag(self) -> u8 {
+        match self {
+            MessageKind::Protocol => 0,
+            MessageKind::Output => 1,
+            MessageKind::Handshake => 2,
+            MessageKind::Error => 3,
+        }
+    }
+}
+
+impl TryFrom<u8> for MessageKind {
+    type Error = FrameError;
+
+    fn try_from(tag: u8) -> Result<Self, FrameError> {
+        match tag {
+            0 => Ok(MessageKind::Protocol),
+            1 => Ok(MessageKind::Output),
+            2 => Ok(MessageKind::Handshake),
+            3 => Ok(MessageKind::Error),
+            _ => Err(FrameError::UnknownMessageKind(tag)),
+        }
+    }
+}
+
+impl fmt::Display for MessageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageKind::Protocol => write!(f, "protocol"),
+            MessageKind::Output => write!(f, "output"),
+            MessageKind::Handshake => write!(f, "handshake"),
+            MessageKind::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: MessageKind,
+    pub session: u64,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: MessageKind, session: u64, payload: Vec<u8>) -> Self {
+        Frame {
+            kind,
+            session,
+            payload,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    /// Reading from or writing to the underlying stream failed.
+    Io(io::Error),
+    /// The peer closed the stream between two frames.
+    ConnectionClosed,
+    /// The peer closed the stream in the middle of a frame.
+    Truncated { buffered: usize },
+    /// The payload is larger than the configured maximum.
+    FrameTooLarge { size: usize, max: usize },
+    /// The peer speaks a different version of the protocol.
+    UnsupportedVersion(u8),
+    /// The message kind tag is not known to this version.
+    UnknownMessageKind(u8),
+    /// The frame belongs to a different session than this transport.
+    SessionMismatch { expected: u64, received: u64 },
+    /// A valid frame arrived where a different kind of message was expected.
+    UnexpectedMessage {
+        expected: MessageKind,
+        received: MessageKind,
+    },
+    /// An error frame whose payload is not a `SessionError`.
+    InvalidErrorFrame(String),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Io(e) => write!(f, "I/O error: {}", e),
+            FrameError::ConnectionClosed => write!(f, "The peer closed the connection"),
+            FrameError::Truncated { buffered } => write!(
+                f,
+                "The peer closed the connection in the middle of a frame ({} bytes buffered)",
+                buffered
+            ),
+            FrameError::FrameTooLarge { size, max } => write!(
+                f,
+                "Frame of {} bytes exceeds the maximum of {} bytes",
+                size, max
+            ),
+            FrameError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported protocol version {} (expected {})",
+                version, PROTOCOL_VERSION
+            ),
+            FrameError::UnknownMessageKind(tag) => write!(f, "Unknown message kind {}", tag),
+            FrameError::SessionMismatch { expected, received } => write!(
+                f,
+                "Frame for session {} received on session {}",
+                received, expected
+            ),
+            FrameError::UnexpectedMessage { expected, received } => write!(
+                f,
+                "Expected a {} message but received a {} message",
+                expected, received
+            ),
+            FrameError::InvalidErrorFrame(reason) => write!(f, "Invalid error frame: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrameError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FrameError {
+    fn from(e: io::Error) -> Self {
+        FrameError::Io(e)
+    }
+}
+
+/// Encodes and decodes frames, rejecting payloads above `max_frame_size`.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameCodec {
+    max_frame_size: usize,
+}
+
+impl Default for FrameCodec {
+    fn default() -> Self {
+        FrameCodec::new(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl FrameCodec {
+    pub fn new(max_frame_size: usize) -> Self {
+        // the length prefix is a u32, so larger limits could not be honoured anyway
+        let max_frame_size = max_frame_size.min(u32::MAX as usize);
+        FrameCodec { max_frame_size }
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Appends `frame` to `buffer`.
+    pub fn encode(&self, frame: &Frame, buffer: &mut BytesMut) -> Result<(), FrameError> {
+        self.encode_parts(frame.kind, frame.session, &frame.payload, buffer)
+    }
+
+    /// Appends a frame built from its parts to `buffer`, without copying `payload` into a `Frame`.
+    pub fn encode_parts(
+        &self,
+        kind: MessageKind,
+        session: u64,
+        payload: &[u8],
+        buffer: &mut BytesMut,
+    ) -> Result<(), FrameError> {
+        let size = payload.len();
+        if size > self.max_frame_size {
+            return Err(FrameError::FrameTooLarge {
+                size,
+                max: self.max_frame_size,
+            });
+        }
+
+        buffer.reserve(HEADER_SIZE + size);
+        buffer.put_u32_le(size as u32);
+        buffer.put_u8(PROTOCOL_VERSION);
+        buffer.put_u8(kind.tag());
+        buffer.put_u64_le(session);
+        buffer.put_slice(payload);
+        Ok(())
+    }
+
+    /// Removes the first complete frame from `buffer`.
+    ///
+    /// Returns `Ok(None)` and leaves `buffer` untouched when the frame has not fully arrived yet.
+    /// The header is validated as soon as it is complete, so an oversized or foreign frame is
+    /// rejected before its payload is buffered.
+    pub fn decode(&self, buffer: &mut BytesMut) -> Result<Option<Frame>, FrameError> {
+        if buffer.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let mut header = &buffer[..HEADER_SIZE];
+        let size = header.get_u32_le() as usize;
+        let version = header.get_u8();
+        let tag = header.get_u8();
+        let session = header.get_u64_le();
+
+        if size > self.max_frame_size {
+            return Err(FrameError::FrameTooLarge {
+                size,
+                max: self.max_frame_size,
+            });
+        }
+        if version != PROTOCOL_VERSION {
+            return Err(FrameError::UnsupportedVersion(version));
+        }
+        let kind = MessageKind::try_from(tag)?;
+
+        if buffer.len() < HEADER_SIZE + size {
+            buffer.reserve(HEADER_SIZE + size - buffer.len());
+            return Ok(None);
+        }
+
+        buffer.advance(HEADER_SIZE);
+        let payload = buffer.split_to(size).to_vec();
+        Ok(Some(Frame::new(kind, session, payload)))
+    }
+}
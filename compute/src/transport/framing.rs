@@ -0,0 +1,87 @@
+//! Reading and writing frames on byte streams, shared by the stream and QUIC transports.
+
+use bytes::BytesMut;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::codec::{Frame, FrameCodec, FrameError, MessageKind};
+
+/// Frame state of one end of a connection: the codec, the session id written into every frame
+/// and the bytes read but not decoded yet.
+#[derive(Debug, Default)]
+pub struct Framing {
+    codec: FrameCodec,
+    session: u64,
+    read_buffer: BytesMut,
+}
+
+impl Framing {
+    pub fn new(codec: FrameCodec, session: u64) -> Self {
+        Framing {
+            codec,
+            session,
+            read_buffer: BytesMut::new(),
+        }
+    }
+
+    pub fn codec(&self) -> FrameCodec {
+        self.codec
+    }
+
+    pub fn set_codec(&mut self, codec: FrameCodec) {
+        self.codec = codec;
+    }
+
+    pub fn session(&self) -> u64 {
+        self.session
+    }
+
+    pub fn set_session(&mut self, session: u64) {
+        self.session = session;
+    }
+
+    /// Writes `payload` as one frame of this session and flushes the writer.
+    pub async fn write<W>(
+        &mut self,
+        writer: &mut W,
+        kind: MessageKind,
+        payload: &[u8],
+    ) -> Result<(), FrameError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut buffer = BytesMut::new();
+        self.codec
+            .encode_parts(kind, self.session, payload, &mut buffer)?;
+        writer.write_all(&buffer).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Reads the next frame of this session, buffering any bytes that follow it.
+    pub async fn read<R>(&mut self, reader: &mut R) -> Result<Frame, FrameError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        loop {
+            if let Some(frame) = self.codec.decode(&mut self.read_buffer)? {
+                if frame.session != self.session {
+                    return Err(FrameError::SessionMismatch {
+                        expected: self.session,
+                        received: frame.session,
+                    });
+                }
+                return Ok(frame);
+            }
+
+            if reader.read_buf(&mut self.read_buffer).await? == 0 {
+                return Err(if self.read_buffer.is_empty() {
+                    FrameError::ConnectionClosed
+                } else {
+                    FrameError::Truncated {
+                        buffered: self.read_buffer.len(),
+                    }
+                });
+            }
+        }
+    }
+}
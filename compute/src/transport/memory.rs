@@ -0,0 +1,62 @@
+//! Transports connecting two parties in the same process, mostly for tests and simulations.
+
+use anyhow::{anyhow, Result};
+use tokio::io::DuplexStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use super::codec::MessageKind;
+use super::{StreamTransport, Transport};
+
+/// Bytes buffered in each direction of a `MemoryTransport` before the writer waits for the reader.
+const MEMORY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// An in-memory byte pipe carrying the same frames as the network transports.
+pub type MemoryTransport = StreamTransport<DuplexStream>;
+
+impl StreamTransport<DuplexStream> {
+    /// Creates two connected ends, one for each party.
+    pub fn pair() -> (MemoryTransport, MemoryTransport) {
+        let (a, b) = tokio::io::duplex(MEMORY_BUFFER_SIZE);
+        (StreamTransport::new(a), StreamTransport::new(b))
+    }
+}
+
+/// One end of an in-process message channel, handing over whole messages without framing.
+#[derive(Debug)]
+pub struct ChannelTransport {
+    sender: UnboundedSender<(MessageKind, Vec<u8>)>,
+    receiver: UnboundedReceiver<(MessageKind, Vec<u8>)>,
+}
+
+impl ChannelTransport {
+    /// Creates two connected ends, one for each party.
+    pub fn pair() -> (ChannelTransport, ChannelTransport) {
+        let (sender_a, receiver_b) = unbounded_channel();
+        let (sender_b, receiver_a) = unbounded_channel();
+        (
+            ChannelTransport {
+                sender: sender_a,
+                receiver: receiver_a,
+            },
+            ChannelTransport {
+                sender: sender_b,
+                receiver: receiver_b,
+            },
+        )
+    }
+}
+
+impl Transport for ChannelTransport {
+    async fn send_message(&mut self, kind: MessageKind, message: &[u8]) -> Result<()> {
+        self.sender
+            .send((kind, message.to_vec()))
+            .map_err(|_| anyhow!("The other party closed the channel"))
+    }
+
+    async fn recv_message(&mut self) -> Result<(MessageKind, Vec<u8>)> {
+        self.receiver
+            .recv()
+            .await
+            .ok_or_else(|| anyhow!("The other party closed the channel"))
+    }
+}
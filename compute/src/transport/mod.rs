@@ -0,0 +1,139 @@
+//! Message transports between the garbler and the evaluator.
+//!
+//! The protocol is a sequence of complete messages exchanged in lockstep, so a transport only
+//! has to deliver whole messages in order. Every byte stream transport uses the frame format in
+//! `codec`, so the in-memory, TCP and QUIC transports are interchangeable on the wire.
+
+pub mod codec;
+pub mod framing;
+pub mod memory;
+pub mod quic;
+pub mod tcp;
+pub mod tls;
+
+use anyhow::Result;
+use std::future::Future;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::session::{PeerError, SessionError};
+use framing::Framing;
+
+pub use codec::{Frame, FrameCodec, FrameError, MessageKind};
+pub use memory::{ChannelTransport, MemoryTransport};
+pub use quic::QuicTransport;
+pub use tcp::TcpTransport;
+
+/// Delivers complete messages to the other party, in order.
+pub trait Transport: Send {
+    /// Sends one message of the given kind to the other party.
+    fn send_message(
+        &mut self,
+        kind: MessageKind,
+        message: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Waits for the next message from the other party, whatever its kind.
+    fn recv_message(&mut self) -> impl Future<Output = Result<(MessageKind, Vec<u8>)>> + Send;
+
+    /// Waits for the next message, failing unless it is of the `expected` kind.
+    ///
+    /// An error frame from the other party fails with the `PeerError` it carries.
+    fn recv_kind(&mut self, expected: MessageKind) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        async move {
+            let (kind, message) = self.recv_message().await?;
+            if kind == MessageKind::Error && expected != MessageKind::Error {
+                let error: SessionError = bincode::deserialize(&message)
+                    .map_err(|e| FrameError::InvalidErrorFrame(e.to_string()))?;
+                return Err(PeerError(error).into());
+            }
+            if kind != expected {
+                return Err(FrameError::UnexpectedMessage {
+                    expected,
+                    received: kind,
+                }
+                .into());
+            }
+            Ok(message)
+        }
+    }
+
+    /// Sends one message of the garbling protocol to the other party.
+    fn send(&mut self, message: &[u8]) -> impl Future<Output = Result<()>> + Send {
+        self.send_message(MessageKind::Protocol, message)
+    }
+
+    /// Waits for the next message of the garbling protocol from the other party.
+    fn recv(&mut self) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        self.recv_kind(MessageKind::Protocol)
+    }
+}
+
+impl<T: Transport> Transport for &mut T {
+    fn send_message(
+        &mut self,
+        kind: MessageKind,
+        message: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        (**self).send_message(kind, message)
+    }
+
+    fn recv_message(&mut self) -> impl Future<Output = Result<(MessageKind, Vec<u8>)>> + Send {
+        (**self).recv_message()
+    }
+}
+
+/// Sends framed messages over any byte stream.
+#[derive(Debug)]
+pub struct StreamTransport<S> {
+    stream: S,
+    framing: Framing,
+}
+
+impl<S> StreamTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    pub fn new(stream: S) -> Self {
+        StreamTransport {
+            stream,
+            framing: Framing::default(),
+        }
+    }
+
+    /// Tags every frame with `session` and rejects frames of any other session.
+    pub fn with_session(mut self, session: u64) -> Self {
+        self.framing.set_session(session);
+        self
+    }
+
+    /// Rejects frames whose payload is larger than `max_frame_size` bytes.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.framing.set_codec(FrameCodec::new(max_frame_size));
+        self
+    }
+
+    pub fn session(&self) -> u64 {
+        self.framing.session()
+    }
+
+    /// Returns the underlying stream.
+    ///
+    /// Bytes that were already read but not decoded into a frame are lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Transport for StreamTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn send_message(&mut self, kind: MessageKind, message: &[u8]) -> Result<()> {
+        Ok(self.framing.write(&mut self.stream, kind, message).await?)
+    }
+
+    async fn recv_message(&mut self) -> Result<(MessageKind, Vec<u8>)> {
+        let frame = self.framing.read(&mut self.stream).await?;
+        Ok((frame.kind, frame.payload))
+    }
+}
@@ -0,0 +1,249 @@
+//! Framed messages over a QUIC bidirectional stream, using quinn with rustls.
+//!
+//! Each protocol session runs on its own stream, so one connection can carry several sessions.
+
+use anyhow::{anyhow, Context, Result};
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+use quinn::rustls::crypto::CryptoProvider;
+use quinn::rustls::pki_types::pem::PemObject;
+use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use quinn::rustls::server::danger::ClientCertVerifier;
+use quinn::rustls::{self, RootCertStore};
+use quinn::{
+    ClientConfig, Connection, Endpoint, RecvStream, SendStream, ServerConfig, TransportConfig,
+};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::codec::{FrameCodec, MessageKind};
+use super::framing::Framing;
+use super::tls::Identity;
+use super::Transport;
+
+/// Default interval of keep-alive packets, so garbling a large circuit does not idle out
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time without any packets after which a connection is closed.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connection settings of a QUIC endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuicConfig {
+    /// Time without any packets after which a connection is closed.
+    pub idle_timeout: Duration,
+    /// Interval of keep-alive packets sent while a connection is otherwise quiet, if any. It is
+    /// shortened to half the idle timeout, so keep-alives always arrive in time.
+    pub keep_alive_interval: Option<Duration>,
+}
+
+impl Default for QuicConfig {
+    fn default() -> Self {
+        QuicConfig {
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            keep_alive_interval: Some(KEEP_ALIVE_INTERVAL),
+        }
+    }
+}
+
+impl QuicConfig {
+    fn transport(&self) -> Result<TransportConfig> {
+        let mut transport = TransportConfig::default();
+        transport.max_idle_timeout(Some(self.idle_timeout.try_into()?));
+        transport.keep_alive_interval(
+            self.keep_alive_interval
+                .map(|interval| interval.min(self.idle_timeout / 2)),
+        );
+        Ok(transport)
+    }
+}
+
+pub struct QuicTransport {
+    send: SendStream,
+    recv: RecvStream,
+    framing: Framing,
+}
+
+impl QuicTransport {
+    pub fn new(send: SendStream, recv: RecvStream) -> Self {
+        QuicTransport {
+            send,
+            recv,
+            framing: Framing::default(),
+        }
+    }
+
+    /// Tags every frame with `session` and rejects frames of any other session.
+    pub fn with_session(mut self, session: u64) -> Self {
+        self.framing.set_session(session);
+        self
+    }
+
+    /// Rejects frames whose payload is larger than `max_frame_size` bytes.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.framing.set_codec(FrameCodec::new(max_frame_size));
+        self
+    }
+
+    pub fn session(&self) -> u64 {
+        self.framing.session()
+    }
+
+    /// Opens a new stream on `connection`.
+    ///
+    /// QUIC only announces a stream to the peer once data is written to it, so the party that
+    /// sends the first message (the garbler) has to be the one opening the stream.
+    pub async fn open(connection: &Connection) -> Result<Self> {
+        let (send, recv) = connection.open_bi().await?;
+        Ok(QuicTransport::new(send, recv))
+    }
+
+    /// Waits for the peer to open the next stream on `connection`.
+    pub async fn accept(connection: &Connection) -> Result<Self> {
+        let (send, recv) = connection.accept_bi().await?;
+        Ok(QuicTransport::new(send, recv))
+    }
+
+    /// Finishes the sending side and waits until the peer has received everything sent on it.
+    ///
+    /// Closing the connection right after the last `send` may otherwise discard that message.
+    pub async fn finish(mut self) -> Result<()> {
+        self.send.finish()?;
+        self.send.stopped().await?;
+        Ok(())
+    }
+}
+
+impl Transport for QuicTransport {
+    async fn send_message(&mut self, kind: MessageKind, message: &[u8]) -> Result<()> {
+        Ok(self.framing.write(&mut self.send, kind, message).await?)
+    }
+
+    async fn recv_message(&mut self) -> Result<(MessageKind, Vec<u8>)> {
+        let frame = self.framing.read(&mut self.recv).await?;
+        Ok((frame.kind, frame.payload))
+    }
+}
+
+/// Creates a QUIC endpoint accepting connections on `address` with the given TLS identity.
+pub fn server_endpoint(
+    address: SocketAddr,
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<Endpoint> {
+    server_endpoint_with_config(address, cert_chain, key, &QuicConfig::default())
+}
+
+/// Like `server_endpoint`, with the connection settings in `quic`.
+pub fn server_endpoint_with_config(
+    address: SocketAddr,
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    quic: &QuicConfig,
+) -> Result<Endpoint> {
+    let mut config = ServerConfig::with_single_cert(cert_chain, key)?;
+    config.transport_config(Arc::new(quic.transport()?));
+    Ok(Endpoint::server(config, address)?)
+}
+
+/// Like `server_endpoint_with_config`, but only accepts clients whose certificate passes
+/// `verifier`, such as a `tls::FingerprintVerifier`.
+pub fn server_endpoint_with_client_auth(
+    address: SocketAddr,
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    verifier: Arc<dyn ClientCertVerifier>,
+    quic: &QuicConfig,
+) -> Result<Endpoint> {
+    // the same settings as `ServerConfig::with_single_cert`, plus the client verifier
+    let mut crypto = rustls::ServerConfig::builder_with_provider(crypto_provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(cert_chain, key)?;
+    crypto.max_early_data_size = u32::MAX;
+
+    let mut config = ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(crypto)?));
+    config.transport_config(Arc::new(quic.transport()?));
+    Ok(Endpoint::server(config, address)?)
+}
+
+/// Creates a QUIC endpoint for outgoing connections that trusts only `roots`.
+pub fn client_endpoint(roots: &[CertificateDer<'static>]) -> Result<Endpoint> {
+    client_endpoint_with_config(roots, &QuicConfig::default())
+}
+
+/// Like `client_endpoint`, with the connection settings in `quic`.
+pub fn client_endpoint_with_config(
+    roots: &[CertificateDer<'static>],
+    quic: &QuicConfig,
+) -> Result<Endpoint> {
+    let mut config = ClientConfig::with_root_certificates(Arc::new(root_store(roots)?))?;
+    config.transport_config(Arc::new(quic.transport()?));
+    client(config)
+}
+
+/// Like `client_endpoint_with_config`, authenticating to servers with the certificate chain and
+/// key of `identity`.
+pub fn client_endpoint_with_identity(
+    roots: &[CertificateDer<'static>],
+    identity: Identity,
+    quic: &QuicConfig,
+) -> Result<Endpoint> {
+    // the same settings as `ClientConfig::with_root_certificates`, plus the client certificate
+    let mut crypto = rustls::ClientConfig::builder_with_provider(crypto_provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_root_certificates(root_store(roots)?)
+        .with_client_auth_cert(identity.cert_chain, identity.key)?;
+    crypto.enable_early_data = true;
+
+    let mut config = ClientConfig::new(Arc::new(QuicClientConfig::try_from(crypto)?));
+    config.transport_config(Arc::new(quic.transport()?));
+    client(config)
+}
+
+fn root_store(roots: &[CertificateDer<'static>]) -> Result<RootCertStore> {
+    let mut store = RootCertStore::empty();
+    for root in roots {
+        store.add(root.clone())?;
+    }
+    Ok(store)
+}
+
+fn client(config: ClientConfig) -> Result<Endpoint> {
+    let mut endpoint = Endpoint::client(SocketAddr::from(([0, 0, 0, 0], 0)))?;
+    endpoint.set_default_client_config(config);
+    Ok(endpoint)
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Generates a self-signed certificate and its private key for the given host names.
+pub fn self_signed_certificate(
+    subject_alt_names: Vec<String>,
+) -> Result<(CertificateDer<'static>, PrivateKeyDer<'static>)> {
+    let certified = rcgen::generate_simple_self_signed(subject_alt_names)?;
+    let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+    Ok((certified.cert.der().clone(), key.into()))
+}
+
+/// Loads every certificate from a PEM file.
+pub fn load_certificates(path: impl AsRef<Path>) -> Result<Vec<CertificateDer<'static>>> {
+    let path = path.as_ref();
+    let certificates = CertificateDer::pem_file_iter(path)
+        .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read certificates from {}", path.display()))?;
+    if certificates.is_empty() {
+        return Err(anyhow!("No certificates found in {}", path.display()));
+    }
+    Ok(certificates)
+}
+
+/// Loads the first private key from a PEM file.
+pub fn load_private_key(path: impl AsRef<Path>) -> Result<PrivateKeyDer<'static>> {
+    let path = path.as_ref();
+    PrivateKeyDer::from_pem_file(path)
+        .with_context(|| format!("Failed to read a private key from {}", path.display()))
+}
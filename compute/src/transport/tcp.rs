@@ -0,0 +1,32 @@
+//! Framed messages over a plain TCP connection.
+//!
+//! TCP offers no confidentiality, so this transport is meant for trusted networks or for running
+//! behind a TLS terminating proxy.
+
+use anyhow::Result;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use super::StreamTransport;
+
+pub type TcpTransport = StreamTransport<TcpStream>;
+
+impl StreamTransport<TcpStream> {
+    /// Connects to the other party at `address`.
+    pub async fn connect(address: impl ToSocketAddrs) -> Result<TcpTransport> {
+        let stream = TcpStream::connect(address).await?;
+        Self::from_tcp(stream)
+    }
+
+    /// Accepts the next connection on `listener`, returning the transport and the peer address.
+    pub async fn accept(listener: &TcpListener) -> Result<(TcpTransport, SocketAddr)> {
+        let (stream, peer) = listener.accept().await?;
+        Ok((Self::from_tcp(stream)?, peer))
+    }
+
+    fn from_tcp(stream: TcpStream) -> Result<TcpTransport> {
+        // The parties exchange small messages in lockstep, so Nagle's algorithm only adds latency
+        stream.set_nodelay(true)?;
+        Ok(StreamTransport::new(stream))
+    }
+}
@@ -0,0 +1,205 @@
+//! TLS identities of the parties and authentication of garblers by their public key.
+//!
+//! Each party identifies itself with a certificate, which can be a self-signed one generated on
+//! first run. An evaluator that only serves known garblers requires client certificates and
+//! accepts those whose public key fingerprint is on its allowlist, so no certificate authority is
+//! needed to authorize a garbler.
+
+use anyhow::{anyhow, bail, Context, Result};
+use quinn::rustls::client::danger::HandshakeSignatureValid;
+use quinn::rustls::crypto::{
+    verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms,
+};
+use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+use quinn::rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use quinn::rustls::{
+    self, CertificateError, DigitallySignedStruct, DistinguishedName, SignatureScheme,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use super::quic::{load_certificates, load_private_key};
+
+/// SHA-256 hash of the DER encoded public key of a certificate.
+pub type Fingerprint = [u8; 32];
+
+/// Returns the fingerprint of the public key in `certificate`.
+///
+/// The fingerprint only depends on the key, so it stays the same when a certificate is renewed
+/// with the same key.
+pub fn public_key_fingerprint(certificate: &CertificateDer<'_>) -> Result<Fingerprint> {
+    let certificate = rustls::server::ParsedCertificate::try_from(certificate)
+        .map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+    let public_key = certificate.subject_public_key_info();
+    let sha256 = rustls::crypto::ring::cipher_suite::TLS13_AES_128_GCM_SHA256
+        .tls13()
+        .expect("a TLS 1.3 cipher suite")
+        .common
+        .hash_provider;
+    let digest = sha256.hash(public_key.as_ref());
+    Ok(digest
+        .as_ref()
+        .try_into()
+        .expect("SHA-256 digests are 32 bytes"))
+}
+
+/// Parses a fingerprint written as 64 hex digits, optionally separated by colons.
+pub fn parse_fingerprint(text: &str) -> Result<Fingerprint> {
+    let digits: String = text.trim().chars().filter(|c| *c != ':').collect();
+    let bytes = hex::decode(&digits).with_context(|| format!("Invalid fingerprint '{}'", text))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow!(
+            "Invalid fingerprint '{}': expected 32 bytes, found {}",
+            text,
+            bytes.len()
+        )
+    })
+}
+
+/// A certificate chain and the private key of its first certificate.
+#[derive(Debug)]
+pub struct Identity {
+    pub cert_chain: Vec<CertificateDer<'static>>,
+    pub key: PrivateKeyDer<'static>,
+}
+
+impl Identity {
+    /// Loads an identity from PEM files.
+    pub fn load(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Identity {
+            cert_chain: load_certificates(cert_path)?,
+            key: load_private_key(key_path)?,
+        })
+    }
+
+    /// Loads an identity from PEM files, first generating a self-signed one for `names` if neither
+    /// file exists.
+    ///
+    /// Returns whether the identity was generated.
+    pub fn load_or_generate(
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+        names: Vec<String>,
+    ) -> Result<(Self, bool)> {
+        let (cert_path, key_path) = (cert_path.as_ref(), key_path.as_ref());
+        let generated = match (cert_path.exists(), key_path.exists()) {
+            (true, true) => false,
+            (false, false) => {
+                generate_self_signed(cert_path, key_path, names)?;
+                true
+            }
+            (true, false) => bail!("Certificate {} has no private key", cert_path.display()),
+            (false, true) => bail!("Private key {} has no certificate", key_path.display()),
+        };
+        Ok((Self::load(cert_path, key_path)?, generated))
+    }
+
+    /// Fingerprint of the public key of the identity's certificate.
+    pub fn fingerprint(&self) -> Result<Fingerprint> {
+        let certificate = self
+            .cert_chain
+            .first()
+            .ok_or_else(|| anyhow!("The identity has no certificate"))?;
+        public_key_fingerprint(certificate)
+    }
+}
+
+/// Generates a self-signed certificate for `names` and writes it and its private key as PEM files.
+///
+/// On Unix, the private key is only readable by its owner.
+pub fn generate_self_signed(
+    cert_path: impl AsRef<Path>,
+    key_path: impl AsRef<Path>,
+    names: Vec<String>,
+) -> Result<()> {
+    let (cert_path, key_path) = (cert_path.as_ref(), key_path.as_ref());
+    let certified = rcgen::generate_simple_self_signed(names)?;
+
+    for path in [cert_path, key_path] {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+    }
+    fs::write(cert_path, certified.cert.pem())
+        .with_context(|| format!("Failed to write certificate {}", cert_path.display()))?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(key_path)
+        .and_then(|mut file| file.write_all(certified.key_pair.serialize_pem().as_bytes()))
+        .with_context(|| format!("Failed to write private key {}", key_path.display()))?;
+    Ok(())
+}
+
+/// Requires a client certificate whose public key fingerprint is on an allowlist.
+///
+/// The certificates themselves are not checked against any authority, so self-signed ones work;
+/// the TLS handshake still proves that the client holds the private key.
+#[derive(Debug)]
+pub struct FingerprintVerifier {
+    allowed: HashSet<Fingerprint>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl FingerprintVerifier {
+    pub fn new(allowed: impl IntoIterator<Item = Fingerprint>) -> Self {
+        FingerprintVerifier {
+            allowed: allowed.into_iter().collect(),
+            algorithms: rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        }
+    }
+
+    pub fn is_allowed(&self, fingerprint: &Fingerprint) -> bool {
+        self.allowed.contains(fingerprint)
+    }
+}
+
+impl ClientCertVerifier for FingerprintVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        let fingerprint = public_key_fingerprint(end_entity)
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        if !self.is_allowed(&fingerprint) {
+            return Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
@@ -233,6 +233,78 @@ impl<const N: usize> From<GarbledUint<N>> for u128 {
     }
 }
 
+/// A function body generic over the width `N` its plaintext type `T` is encrypted with, which
+/// `Plaintext::instantiate` calls at the width of `T`.
+pub trait Instantiate<T> {
+    type Output;
+
+    fn call<const N: usize>(self) -> Self::Output;
+}
+
+/// The plaintext types a type parameter of an `#[encrypted]` function can stand for. The width
+/// is picked by the trait implementation when the caller is compiled, so calling such a function
+/// with any other type is a compile error.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot stand for the type parameter of an `#[encrypted]` function",
+    note = "use `bool` or an integer type such as `u8` or `i32`"
+)]
+pub trait Plaintext: Copy {
+    /// Whether the type is a two's complement integer, whose ordering, division, remainder and
+    /// `>>` use the signed circuits.
+    const SIGNED: bool;
+
+    fn instantiate<F: Instantiate<Self>>(body: F) -> F::Output;
+
+    /// The bits of `self` at the width `N` it is instantiated with, in two's complement if signed.
+    fn encode<const N: usize>(self) -> GarbledUint<N>;
+
+    fn decode<const N: usize>(bits: GarbledUint<N>) -> Self;
+}
+
+macro_rules! impl_plaintext {
+    ($($ty:ty => $bits:literal),*) => {
+        $(
+            impl Plaintext for $ty {
+                const SIGNED: bool = false;
+
+                fn instantiate<F: Instantiate<Self>>(body: F) -> F::Output {
+                    body.call::<$bits>()
+                }
+
+                fn encode<const N: usize>(self) -> GarbledUint<N> {
+                    self.into()
+                }
+
+                fn decode<const N: usize>(bits: GarbledUint<N>) -> Self {
+                    bits.into()
+                }
+            }
+        )*
+    };
+    (signed $($ty:ty => $bits:literal),*) => {
+        $(
+            impl Plaintext for $ty {
+                const SIGNED: bool = true;
+
+                fn instantiate<F: Instantiate<Self>>(body: F) -> F::Output {
+                    body.call::<$bits>()
+                }
+
+                fn encode<const N: usize>(self) -> GarbledUint<N> {
+                    GarbledInt::<N>::from(self).into()
+                }
+
+                fn decode<const N: usize>(bits: GarbledUint<N>) -> Self {
+                    GarbledInt::from(bits).into()
+                }
+            }
+        )*
+    };
+}
+
+impl_plaintext!(bool => 1, u8 => 8, u16 => 16, u32 => 32, u64 => 64, u128 => 128);
+impl_plaintext!(signed i8 => 8, i16 => 16, i32 => 32, i64 => 64, i128 => 128);
+
 /*
 impl From<GarbledBit> for bool {
     fn from(guint: GarbledUint<1>) -> Self {
@@ -345,6 +345,48 @@ fn test_uint_mul() {
     assert_eq!(result, 300_u16 * 7_u16); // Expected result of multiplication between 1010101010101011 and 0101010101010101
 }
 
+#[test]
+fn test_uint_mul_wide() {
+    let a: GarbledUint64 = 12297829382473034410_u64.into();
+    let b: GarbledUint64 = 424242424242_u64.into();
+
+    let result: u64 = (a * b).into();
+    assert_eq!(
+        result,
+        12297829382473034410_u64.wrapping_mul(424242424242_u64)
+    );
+
+    let a: GarbledUint128 = 12297829382473034410_u128.into();
+    let b: GarbledUint128 = 424242424242424242_u128.into();
+
+    let result: u128 = (a * b).into();
+    assert_eq!(
+        result,
+        12297829382473034410_u128.wrapping_mul(424242424242424242_u128)
+    );
+}
+
+#[test]
+fn test_uint_widening_mul() {
+    let a: GarbledUint8 = 200_u8.into();
+    let b: GarbledUint8 = 100_u8.into();
+
+    let (low, high) = a.widening_mul(&b);
+    let low: u8 = low.into();
+    let high: u8 = high.into();
+    assert_eq!(u16::from_le_bytes([low, high]), 200_u16 * 100_u16);
+
+    let a: GarbledUint64 = 12297829382473034410_u64.into();
+    let b: GarbledUint64 = 424242424242_u64.into();
+
+    let (low, high) = a.widening_mul(&b);
+    let low: u64 = low.into();
+    let high: u64 = high.into();
+    let expected = 12297829382473034410_u128 * 424242424242_u128;
+    assert_eq!(low, expected as u64);
+    assert_eq!(high, (expected >> 64) as u64);
+}
+
 #[test]
 fn test_int_mul() {
     let a: GarbledInt8 = 3_i8.into();
@@ -438,6 +480,18 @@ fn test_int_div() {
 
     let result: i16 = (a / b).into();
     assert_eq!(result, 134_i16 / 85_i16);
+
+    let a: GarbledInt8 = (-7_i8).into();
+    let b: GarbledInt8 = 2_i8.into();
+
+    let result: i8 = (a / b).into();
+    assert_eq!(result, -7_i8 / 2_i8);
+
+    let a: GarbledInt32 = 1000000_i32.into();
+    let b: GarbledInt32 = (-3_i32).into();
+
+    let result: i32 = (a / b).into();
+    assert_eq!(result, 1000000_i32 / -3_i32);
 }
 
 #[test]
@@ -500,6 +554,46 @@ fn test_int_rem() {
 
     let result: i16 = (a % b).into();
     assert_eq!(result, 134_i16 % 85_i16);
+
+    let a: GarbledInt8 = (-7_i8).into();
+    let b: GarbledInt8 = 2_i8.into();
+
+    let result: i8 = (a % b).into();
+    assert_eq!(result, -7_i8 % 2_i8);
+
+    let a: GarbledInt32 = 1000000_i32.into();
+    let b: GarbledInt32 = (-3_i32).into();
+
+    let result: i32 = (a % b).into();
+    assert_eq!(result, 1000000_i32 % -3_i32);
+}
+
+#[test]
+fn test_int_neg() {
+    let a: GarbledInt8 = 100_i8.into();
+    let result: i8 = (-a).into();
+    assert_eq!(result, -100_i8);
+
+    let a: GarbledInt32 = (-123456_i32).into();
+    let result: i32 = (-&a).into();
+    assert_eq!(result, 123456_i32);
+}
+
+#[test]
+fn test_int_abs_signum() {
+    let a: GarbledInt16 = (-1234_i16).into();
+    let abs: i16 = a.abs().into();
+    let signum: i16 = a.signum().into();
+    assert_eq!(abs, 1234);
+    assert_eq!(signum, -1);
+
+    let b: GarbledInt16 = 0_i16.into();
+    let signum: i16 = b.signum().into();
+    assert_eq!(signum, 0);
+
+    let c: GarbledInt16 = 42_i16.into();
+    let signum: i16 = c.signum().into();
+    assert_eq!(signum, 1);
 }
 
 #[test]
@@ -0,0 +1,71 @@
+mod common;
+
+use common::split_arithmetic;
+use compute::prelude::*;
+
+fn to_u8(bits: Vec<bool>) -> u8 {
+    GarbledUint8::new(bits).into()
+}
+
+#[tokio::test]
+async fn test_async_local_simulator() {
+    let (circuit, inputs, eval_inputs) = split_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+
+    let output = AsyncLocalSimulator
+        .execute(&circuit, &inputs, &eval_inputs)
+        .await
+        .unwrap();
+    assert_eq!(to_u8(output), 9);
+}
+
+#[tokio::test]
+async fn test_parties_over_channel() {
+    let (circuit, inputs, eval_inputs) = split_arithmetic(6_u8, 7_u8, 1_u8, 2_u8);
+    let (garbler_transport, evaluator_transport) = ChannelTransport::pair();
+
+    let garbler_circuit = circuit.clone();
+    let garbler =
+        tokio::spawn(
+            async move { run_garbler(garbler_transport, &garbler_circuit, &inputs).await },
+        );
+    let output = run_evaluator(evaluator_transport, &circuit, &eval_inputs)
+        .await
+        .unwrap();
+
+    garbler.await.unwrap().unwrap();
+    assert_eq!(to_u8(output), 41);
+}
+
+#[tokio::test]
+async fn test_parties_over_duplex_stream() {
+    let (circuit, inputs, eval_inputs) = split_arithmetic(3_u8, 3_u8, 10_u8, 1_u8);
+    let (garbler_stream, evaluator_stream) = tokio::io::duplex(64);
+
+    let garbler_circuit = circuit.clone();
+    let garbler = tokio::spawn(async move {
+        let transport = StreamTransport::new(garbler_stream);
+        run_garbler(transport, &garbler_circuit, &inputs).await
+    });
+    let mut transport = StreamTransport::new(evaluator_stream);
+    let output = run_evaluator(&mut transport, &circuit, &eval_inputs)
+        .await
+        .unwrap();
+
+    garbler.await.unwrap().unwrap();
+    assert_eq!(to_u8(output), 18);
+}
+
+#[tokio::test]
+async fn test_failing_party_does_not_hang() {
+    let (circuit, inputs, _) = split_arithmetic(1_u8, 1_u8, 1_u8, 1_u8);
+
+    // the evaluator inputs are missing, so the evaluator fails and the garbler must not wait forever
+    let result = AsyncLocalSimulator.execute(&circuit, &inputs, &[]).await;
+    assert!(result.is_err());
+
+    let (garbler_transport, evaluator_transport) = ChannelTransport::pair();
+    drop(evaluator_transport);
+    assert!(run_garbler(garbler_transport, &circuit, &inputs)
+        .await
+        .is_err());
+}
@@ -916,3 +916,72 @@ fn test_right_shift_int() {
     let result: i8 = (a >> 3).into(); // Perform right shift by 3
     assert_eq!(result, 0b0000_i8); // Binary 0000 (Right shift result of 0001)
 }
+
+#[test]
+fn test_int_arithmetic_right_shift() {
+    let a: GarbledInt8 = (-64_i8).into();
+    let result: i8 = (a >> 3).into();
+    assert_eq!(result, -64_i8 >> 3);
+
+    let a: GarbledInt32 = (-1234567_i32).into();
+    let result: i32 = (&a >> 7).into();
+    assert_eq!(result, -1234567_i32 >> 7);
+}
+
+#[test]
+fn test_uint_shift_by_secret_amount() {
+    for shift in 0..8_u8 {
+        let a: GarbledUint8 = 0b1011_0110_u8.into();
+        let amount: GarbledUint8 = shift.into();
+
+        let result: u8 = (&a << &amount).into();
+        assert_eq!(result, 0b1011_0110_u8 << shift);
+
+        let result: u8 = (a >> amount).into();
+        assert_eq!(result, 0b1011_0110_u8 >> shift);
+    }
+
+    let mut a: GarbledUint32 = 0xdead_beef_u32.into();
+    a <<= GarbledUint32::from(12_u32);
+    assert_eq!(
+        <GarbledUint32 as Into<u32>>::into(a.clone()),
+        0xdead_beef_u32 << 12
+    );
+
+    a >>= &GarbledUint32::from(20_u32);
+    assert_eq!(
+        <GarbledUint32 as Into<u32>>::into(a),
+        (0xdead_beef_u32 << 12) >> 20
+    );
+}
+
+#[test]
+fn test_int_shift_by_secret_amount() {
+    for shift in 0..8_u8 {
+        let a: GarbledInt8 = (-75_i8).into();
+        let amount: GarbledUint8 = shift.into();
+
+        let result: i8 = (&a << &amount).into();
+        assert_eq!(result, -75_i8 << shift);
+
+        let result: i8 = (a >> amount).into();
+        assert_eq!(result, -75_i8 >> shift);
+    }
+}
+
+#[test]
+fn test_rotate() {
+    let a: GarbledUint8 = 0b1000_0110_u8.into();
+    let result: u8 = a.rotate_left(3).into();
+    assert_eq!(result, 0b1000_0110_u8.rotate_left(3));
+
+    let result: u8 = a.rotate_right(3).into();
+    assert_eq!(result, 0b1000_0110_u8.rotate_right(3));
+
+    let b: GarbledInt16 = (-12345_i16).into();
+    let result: i16 = b.rotate_left(17).into();
+    assert_eq!(result, (-12345_i16).rotate_left(17));
+
+    let result: i16 = b.rotate_right(5).into();
+    assert_eq!(result, (-12345_i16).rotate_right(5));
+}
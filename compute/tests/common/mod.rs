@@ -0,0 +1,55 @@
+// Helpers shared by the integration tests; each test crate only uses some of them
+#![allow(dead_code)]
+
+use compute::prelude::*;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// The circuit the protocol tests run, with every input from the garbler.
+#[encrypted(compile)]
+pub fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> u8 {
+    let res = a * b;
+    let res = res + c;
+    res - d
+}
+
+/// `multi_arithmetic` with `b` and `d` from the evaluator.
+#[encrypted(compile)]
+pub fn split_arithmetic(a: u8, #[evaluator] b: u8, c: u8, #[evaluator] d: u8) -> u8 {
+    let res = a * b;
+    let res = res + c;
+    res - d
+}
+
+/// A fresh directory under the system temp dir, unique to the test and removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("compute-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
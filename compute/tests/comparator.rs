@@ -58,12 +58,33 @@ fn test_uint_larger_comparison() {
 
 // test signed integer comparison with different sizes
 #[test]
-#[ignore = "reason: int with negative values not implemented"]
 fn test_int_comparison() {
     let d8: GarbledInt8 = (-100_i8).into();
     let e8: GarbledInt8 = 100_i8.into();
 
     assert!(d8 < e8);
+    assert!(e8 > d8);
+
+    let f8: GarbledInt8 = (-1_i8).into();
+    let g8: GarbledInt8 = i8::MIN.into();
+    assert!(g8 < f8);
+    assert!(f8 >= g8);
+
+    let a32: GarbledInt32 = (-20000_i32).into();
+    let b32: GarbledInt32 = (-10000_i32).into();
+    assert!(a32 < b32);
+    assert_eq!(a32.cmp(&a32.clone()), std::cmp::Ordering::Equal);
+}
+
+#[test]
+#[allow(clippy::op_ref)]
+fn test_uint_reference_equality() {
+    let a: GarbledUint8 = 123_u8.into();
+    let b: GarbledUint8 = 123_u8.into();
+    let c: GarbledUint8 = 124_u8.into();
+
+    assert!(a == &b);
+    assert!(a != &c);
 }
 
 #[test]
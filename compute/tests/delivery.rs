@@ -0,0 +1,189 @@
+mod common;
+
+use common::multi_arithmetic;
+use compute::delivery::mask_outputs;
+use compute::handshake::HandshakeError;
+use compute::output::OutputError;
+use compute::prelude::*;
+
+// The 8-bit result read as its low and high nibble
+fn nibbles() -> OutputSchema {
+    OutputSchema::Tuple(vec![OutputSchema::Uint(4), OutputSchema::Uint(4)])
+}
+
+// Runs 6 * 7 + 1 - 2 = 0x29 under `policy`, returning the outputs of the garbler and evaluator
+async fn run_with_policy(policy: &OutputPolicy) -> (Option<Output>, Option<Output>) {
+    let (circuit, inputs, _) = multi_arithmetic(6_u8, 7_u8, 1_u8, 2_u8);
+    let (mut garbler, mut evaluator) = MemoryTransport::pair();
+    let schema = nibbles();
+
+    let garbler = run_garbler_with_policy(&mut garbler, &circuit, &inputs, &schema, policy);
+    let evaluator = run_evaluator_with_policy(&mut evaluator, &circuit, &[], &schema, policy);
+    let (garbler, evaluator) = tokio::join!(garbler, evaluator);
+    (garbler.unwrap(), evaluator.unwrap())
+}
+
+#[test]
+fn test_recipients() {
+    let schema = nibbles();
+    assert_eq!(
+        OutputPolicy::Shared.recipients(&schema).unwrap(),
+        [Recipient::Both, Recipient::Both]
+    );
+    let policy = OutputPolicy::PerElement(vec![Recipient::Garbler, Recipient::Evaluator]);
+    assert_eq!(policy.to_string(), "(garbler, evaluator)");
+    assert_eq!(
+        policy.masked_bits(&schema).unwrap(),
+        [true, true, true, true, false, false, false, false]
+    );
+
+    // one recipient per element of the schema
+    assert_eq!(
+        policy.recipients(&OutputSchema::Uint(8)).unwrap_err(),
+        OutputError::PolicyMismatch {
+            schema: OutputSchema::Uint(8),
+            recipients: 2
+        }
+    );
+
+    let both = OutputPolicy::PerElement(vec![Recipient::Both, Recipient::Both]);
+    assert!(both.agrees_with(&OutputPolicy::Shared, &schema));
+    assert!(!policy.agrees_with(&OutputPolicy::Shared, &schema));
+    assert!(!both.agrees_with(&OutputPolicy::Shared, &OutputSchema::Uint(8)));
+}
+
+#[test]
+fn test_mask_outputs() {
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let masked = [true, false, true, false, false, false, false, false];
+    let masked_circuit = mask_outputs(&circuit, &masked);
+
+    assert_eq!(
+        masked_circuit.contrib_inputs(),
+        circuit.contrib_inputs() + 2
+    );
+    assert_eq!(masked_circuit.gates().len(), circuit.gates().len() + 4);
+    assert_eq!(masked_circuit.and_gates(), circuit.and_gates());
+    // only the masked outputs are rewired
+    for (i, masked) in masked.iter().enumerate() {
+        let unchanged = masked_circuit.output_gates()[i] == circuit.output_gates()[i];
+        assert_eq!(unchanged, !masked);
+    }
+    assert!(masked_circuit.validate().is_ok());
+}
+
+#[tokio::test]
+async fn test_shared_output() {
+    let (garbler, evaluator) = run_with_policy(&OutputPolicy::Shared).await;
+    for output in [garbler.unwrap(), evaluator.unwrap()] {
+        let (low, high): (GarbledUint4, GarbledUint4) = output.decode().unwrap();
+        assert_eq!((u8::from(low), u8::from(high)), (9, 2));
+    }
+}
+
+#[tokio::test]
+async fn test_split_output() {
+    let policy = OutputPolicy::PerElement(vec![Recipient::Garbler, Recipient::Evaluator]);
+    let (garbler, evaluator) = run_with_policy(&policy).await;
+
+    // each party only receives its element, on its own
+    let garbler = garbler.unwrap();
+    assert_eq!(garbler.schema(), &OutputSchema::Uint(4));
+    assert_eq!(u8::from(garbler.decode::<GarbledUint4>().unwrap()), 9);
+    let evaluator = evaluator.unwrap();
+    assert_eq!(evaluator.schema(), &OutputSchema::Uint(4));
+    assert_eq!(u8::from(evaluator.decode::<GarbledUint4>().unwrap()), 2);
+}
+
+#[tokio::test]
+async fn test_single_recipient() {
+    let garbler_only = OutputPolicy::PerElement(vec![Recipient::Garbler, Recipient::Garbler]);
+    let (garbler, evaluator) = run_with_policy(&garbler_only).await;
+    let (low, high): (GarbledUint4, GarbledUint4) = garbler.unwrap().decode().unwrap();
+    assert_eq!((u8::from(low), u8::from(high)), (9, 2));
+    assert!(evaluator.is_none());
+
+    let evaluator_only = OutputPolicy::PerElement(vec![Recipient::Evaluator, Recipient::Evaluator]);
+    let (garbler, evaluator) = run_with_policy(&evaluator_only).await;
+    assert!(garbler.is_none());
+    let (low, high): (GarbledUint4, GarbledUint4) = evaluator.unwrap().decode().unwrap();
+    assert_eq!((u8::from(low), u8::from(high)), (9, 2));
+}
+
+#[tokio::test]
+async fn test_policy_handshake() {
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let policy = OutputPolicy::PerElement(vec![Recipient::Garbler, Recipient::Evaluator]);
+    let mut registry = CircuitRegistry::new();
+    registry.register("multi_arithmetic", circuit.clone());
+    registry
+        .set_output_schema("multi_arithmetic", nibbles())
+        .unwrap();
+    registry
+        .set_output_policy("multi_arithmetic", policy.clone())
+        .unwrap();
+    assert_eq!(registry.info("multi_arithmetic").unwrap().policy, policy);
+    let proposal = CircuitProposal::named("multi_arithmetic", &circuit);
+
+    // a garbler expecting the shared output is turned away
+    let (mut garbler, mut evaluator) = MemoryTransport::pair();
+    let (proposed, accepted) = tokio::join!(
+        propose_circuit(&mut garbler, &proposal),
+        accept_circuit(&mut evaluator, &registry)
+    );
+    let expected = HandshakeError::PolicyMismatch {
+        expected: policy.clone(),
+        proposed: OutputPolicy::Shared,
+    };
+    assert_eq!(
+        proposed.unwrap_err().downcast::<HandshakeError>().unwrap(),
+        expected
+    );
+    assert_eq!(
+        accepted.unwrap_err().downcast::<HandshakeError>().unwrap(),
+        expected
+    );
+
+    let (mut garbler, mut evaluator) = MemoryTransport::pair();
+    let (proposed, accepted) = tokio::join!(
+        propose_circuit_with_policy(&mut garbler, &proposal, &policy),
+        accept_circuit(&mut evaluator, &registry)
+    );
+    proposed.unwrap();
+    accepted.unwrap();
+}
+
+#[test]
+fn test_registry_policy() {
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let mut registry = CircuitRegistry::new();
+    registry.register("multi_arithmetic", circuit.clone());
+    assert_eq!(registry.output_policy(&circuit), OutputPolicy::Shared);
+
+    // the default schema has a single element
+    let split = OutputPolicy::PerElement(vec![Recipient::Garbler, Recipient::Evaluator]);
+    assert!(registry
+        .set_output_policy("multi_arithmetic", split.clone())
+        .is_err());
+    registry
+        .set_output_policy(
+            "multi_arithmetic",
+            OutputPolicy::PerElement(vec![Recipient::Evaluator]),
+        )
+        .unwrap();
+
+    // a schema must keep fitting the policy
+    assert!(registry
+        .set_output_schema("multi_arithmetic", nibbles())
+        .is_err());
+    registry
+        .set_output_policy("multi_arithmetic", OutputPolicy::Shared)
+        .unwrap();
+    registry
+        .set_output_schema("multi_arithmetic", nibbles())
+        .unwrap();
+    registry
+        .set_output_policy("multi_arithmetic", split.clone())
+        .unwrap();
+    assert_eq!(registry.output_policy(&circuit), split);
+}
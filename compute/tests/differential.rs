@@ -0,0 +1,212 @@
+use compute::prelude::*;
+
+type BinaryOp = fn(&mut CircuitBuilder, &GateIndexVec, &GateIndexVec) -> GateIndexVec;
+
+// Every binary circuit the builder can produce, including comparisons widened to a vector
+const BINARY_OPS: [(&str, BinaryOp); 30] = [
+    ("xor", |builder, a, b| builder.xor(a, b)),
+    ("and", |builder, a, b| builder.and(a, b)),
+    ("or", |builder, a, b| builder.or(a, b)),
+    ("nand", |builder, a, b| builder.nand(a, b)),
+    ("nor", |builder, a, b| builder.nor(a, b)),
+    ("xnor", |builder, a, b| builder.xnor(a, b)),
+    ("add", |builder, a, b| builder.add(a, b)),
+    ("sub", |builder, a, b| builder.sub(a, b)),
+    ("mul", |builder, a, b| builder.mul(a, b)),
+    ("widening_mul", |builder, a, b| builder.widening_mul(a, b)),
+    ("div", |builder, a, b| builder.div(a, b)),
+    ("rem", |builder, a, b| builder.rem(a, b)),
+    ("div_signed", |builder, a, b| builder.div_signed(a, b)),
+    ("rem_signed", |builder, a, b| builder.rem_signed(a, b)),
+    ("eq", |builder, a, b| builder.eq(a, b).into()),
+    ("ne", |builder, a, b| builder.ne(a, b).into()),
+    ("lt", |builder, a, b| builder.lt(a, b).into()),
+    ("le", |builder, a, b| builder.le(a, b).into()),
+    ("gt", |builder, a, b| builder.gt(a, b).into()),
+    ("ge", |builder, a, b| builder.ge(a, b).into()),
+    ("lt_signed", |builder, a, b| builder.lt_signed(a, b).into()),
+    ("ge_signed", |builder, a, b| builder.ge_signed(a, b).into()),
+    ("shl_var", |builder, a, b| builder.shl_var(a, b)),
+    ("shr_var", |builder, a, b| builder.shr_var(a, b)),
+    ("sar_var", |builder, a, b| builder.sar_var(a, b)),
+    ("rotate_left_var", |builder, a, b| {
+        builder.rotate_left_var(a, b)
+    }),
+    ("rotate_right_var", |builder, a, b| {
+        builder.rotate_right_var(a, b)
+    }),
+    ("mux", |builder, a, b| {
+        let s = builder.lt(a, b);
+        builder.mux(&s, a, b)
+    }),
+    ("neg", |builder, a, _| builder.neg(a)),
+    ("signum", |builder, a, _| builder.signum(a)),
+];
+
+const VALUES: [u16; 8] = [0, 1, 2, 3, 0x7f, 0x80, 0xb6, 0xffff];
+
+// Runs the circuit through the full protocol and the plaintext reference and compares them
+fn assert_executors_agree(name: &str, builder: &CircuitBuilder, output: &GateIndexVec) {
+    for circuit in [builder.compile(output), builder.compile_unoptimized(output)] {
+        let simulated = builder
+            .execute_with::<0>(&circuit, &LocalSimulator)
+            .expect("Failed to simulate circuit");
+        let plaintext = builder
+            .execute_with::<0>(&circuit, &PlaintextExecutor)
+            .expect("Failed to evaluate circuit");
+        assert_eq!(simulated.bits, plaintext.bits, "{} differs", name);
+    }
+}
+
+fn check_binary_ops<const N: usize>() {
+    for (name, op) in BINARY_OPS {
+        for x in VALUES {
+            for y in VALUES {
+                let mut builder = CircuitBuilder::default();
+                let a = builder.input(&GarbledUint::<N>::from(x));
+                let b = builder.input_eval(&GarbledUint::<N>::from(y));
+                let output = op(&mut builder, &a, &b);
+                assert_executors_agree(name, &builder, &output);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_differential_binary_ops_8() {
+    check_binary_ops::<8>();
+}
+
+#[test]
+fn test_differential_binary_ops_16() {
+    check_binary_ops::<16>();
+}
+
+const WIDE_VALUES: [u128; 5] = [
+    1,
+    1 << 16,
+    0xb6b6_b6b6_b6b6_b6b6,
+    u64::MAX as u128 - 2,
+    u128::MAX,
+];
+
+// Wide multiplications go through Karatsuba, so they are also checked against the plaintext
+// product rather than only between executors
+fn check_mul<const N: usize>() {
+    let mask = u128::MAX >> (128 - N);
+    for x in WIDE_VALUES.map(|x| x & mask) {
+        for y in WIDE_VALUES.map(|y| y & mask) {
+            let mut builder = CircuitBuilder::default();
+            let a = builder.input(&GarbledUint::<N>::from(x));
+            let b = builder.input_eval(&GarbledUint::<N>::from(y));
+            let product = builder.mul(&a, &b);
+            assert_executors_agree("mul", &builder, &product);
+            let result: u128 = builder.compile_and_execute::<N>(&product).unwrap().into();
+            assert_eq!(
+                result,
+                x.wrapping_mul(y) & mask,
+                "{} * {} on {} bits",
+                x,
+                y,
+                N
+            );
+
+            if N <= 64 {
+                let product = builder.widening_mul(&a, &b);
+                let result: u128 = builder.compile_and_execute::<128>(&product).unwrap().into();
+                assert_eq!(result, x * y, "{} * {} widened from {} bits", x, y, N);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_differential_mul_odd_widths() {
+    check_mul::<33>();
+    check_mul::<35>();
+    check_mul::<47>();
+    check_mul::<65>();
+}
+
+#[test]
+fn test_differential_mul_wide() {
+    check_mul::<32>();
+    check_mul::<64>();
+    check_mul::<128>();
+}
+
+#[test]
+fn test_differential_constants() {
+    for x in VALUES {
+        let mut builder = CircuitBuilder::default();
+        let a = builder.input(&GarbledUint16::from(x));
+        let c = builder.constant(&GarbledUint16::from(0x1234_u16));
+        let sum = builder.add(&a, &c);
+        let product = builder.mul(&sum, &c);
+        let shifted = builder.shl_var(&product, &c);
+        assert_executors_agree("constants", &builder, &shifted);
+    }
+}
+
+#[test]
+fn test_differential_mux_tree() {
+    let elements = [11_u8, 22, 33, 44, 55];
+    for index in [0_u8, 1, 3, 4, 5, 7, 8, 200] {
+        let mut builder = CircuitBuilder::default();
+        let wires: Vec<GateIndexVec> = elements
+            .iter()
+            .map(|element| builder.input(&GarbledUint8::from(*element)))
+            .collect();
+        let index_wires = builder.input_eval(&GarbledUint8::from(index));
+        let selected = builder.mux_tree(&wires, &index_wires);
+        assert_executors_agree("mux_tree", &builder, &selected);
+
+        let result: u8 = builder.compile_and_execute::<8>(&selected).unwrap().into();
+        let expected = elements.get(index as usize).copied().unwrap_or(0);
+        assert_eq!(result, expected, "index {}", index);
+    }
+}
+
+#[test]
+fn test_differential_macro() {
+    #[encrypted(compile)]
+    fn access_content(age: u8, #[evaluator] bonus: u8) -> u8 {
+        let level = match age {
+            1..=17 => 1,
+            18..=65 => 2,
+            66..=120 => 3,
+            _ => 0,
+        };
+        level * bonus
+    }
+
+    for age in [0_u8, 15, 25, 70, 125] {
+        let (circuit, inputs, eval_inputs) = access_content(age, 3_u8);
+        let simulated = LocalSimulator
+            .execute(&circuit, &inputs, &eval_inputs)
+            .expect("Failed to simulate circuit");
+        let plaintext = PlaintextExecutor
+            .execute(&circuit, &inputs, &eval_inputs)
+            .expect("Failed to evaluate circuit");
+        assert_eq!(simulated, plaintext);
+    }
+}
+
+#[test]
+fn test_plaintext_executor_rejects_wrong_inputs() {
+    let mut builder = CircuitBuilder::default();
+    let a = builder.input(&GarbledUint8::from(1_u8));
+    let b = builder.input_eval(&GarbledUint8::from(2_u8));
+    let output = builder.add(&a, &b);
+    let circuit = builder.compile(&output);
+
+    assert!(PlaintextExecutor
+        .execute(&circuit, &[true; 7], &[true; 8])
+        .is_err());
+    assert!(PlaintextExecutor
+        .execute(&circuit, &[true; 8], &[true; 9])
+        .is_err());
+    assert!(PlaintextExecutor
+        .execute(&circuit, &[true; 8], &[true; 8])
+        .is_ok());
+}
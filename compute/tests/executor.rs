@@ -0,0 +1,108 @@
+use compute::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Runs circuits on the LocalSimulator while counting how often it was used
+#[derive(Default)]
+struct CountingExecutor {
+    executions: AtomicUsize,
+}
+
+impl Executor for CountingExecutor {
+    fn execute(
+        &self,
+        circuit: &Circuit,
+        input_contributor: &[bool],
+        input_evaluator: &[bool],
+    ) -> anyhow::Result<Vec<bool>> {
+        self.executions.fetch_add(1, Ordering::SeqCst);
+        LocalSimulator.execute(circuit, input_contributor, input_evaluator)
+    }
+}
+
+#[test]
+fn test_set_executor_guard() {
+    let executor = Arc::new(CountingExecutor::default());
+
+    {
+        let _guard = set_executor(executor.clone());
+        let a: GarbledUint8 = 12_u8.into();
+        let b: GarbledUint8 = 10_u8.into();
+        let result: u8 = (a + b).into();
+        assert_eq!(result, 22);
+    }
+    assert_eq!(executor.executions.load(Ordering::SeqCst), 1);
+
+    // the guard is dropped, so the default executor is used again
+    let a: GarbledUint8 = 12_u8.into();
+    let b: GarbledUint8 = 10_u8.into();
+    let result: u8 = (a * b).into();
+    assert_eq!(result, 120);
+    assert_eq!(executor.executions.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_with_executor_macro() {
+    #[encrypted(execute)]
+    fn add(a: u8, b: u8) -> u8 {
+        a + b
+    }
+
+    let executor = Arc::new(CountingExecutor::default());
+    let result = with_executor(executor.clone(), || add(20_u8, 22_u8));
+    assert_eq!(result, 42);
+    assert_eq!(executor.executions.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_nested_executors() {
+    let outer = Arc::new(CountingExecutor::default());
+    let inner = Arc::new(CountingExecutor::default());
+    let a: GarbledUint8 = 12_u8.into();
+    let b: GarbledUint8 = 10_u8.into();
+
+    let _outer_guard = set_executor(outer.clone());
+    {
+        let _inner_guard = set_executor(inner.clone());
+        let _ = &a ^ &b;
+    }
+    let _ = &a & &b;
+
+    assert_eq!(inner.executions.load(Ordering::SeqCst), 1);
+    assert_eq!(outer.executions.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_executor_is_per_thread() {
+    let executor = Arc::new(CountingExecutor::default());
+    let _guard = set_executor(executor.clone());
+
+    std::thread::spawn(|| {
+        let a: GarbledUint8 = 12_u8.into();
+        let b: GarbledUint8 = 10_u8.into();
+        let result: u8 = (a - b).into();
+        assert_eq!(result, 2);
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(executor.executions.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_execute_with() {
+    let executor = CountingExecutor::default();
+
+    let mut builder = CircuitBuilder::default();
+    let a = builder.input(&GarbledUint8::from(12_u8));
+    let b = builder.input(&GarbledUint8::from(10_u8));
+    let output = builder.add(&a, &b);
+    let circuit = builder.compile(&output);
+
+    let result: u8 = builder
+        .execute_with::<8>(&circuit, &executor)
+        .unwrap()
+        .into();
+    assert_eq!(result, 22);
+    assert_eq!(executor.executions.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use common::multi_arithmetic;
+use compute::handshake::HandshakeError;
+use compute::operations::util::serialize_circuit;
+use compute::prelude::*;
+
+#[encrypted(compile)]
+fn addition(a: u8, b: u8) -> u8 {
+    a + b
+}
+
+fn registry() -> CircuitRegistry {
+    let mut registry = CircuitRegistry::new();
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    registry.register("multi_arithmetic", circuit);
+    registry
+}
+
+// Runs the handshake and the protocol, returning the evaluator's output
+async fn negotiate_and_run(
+    registry: &CircuitRegistry,
+    proposal: CircuitProposal,
+    circuit: &Circuit,
+    inputs: &[bool],
+) -> (anyhow::Result<()>, anyhow::Result<Vec<bool>>) {
+    let (mut garbler, mut evaluator) = MemoryTransport::pair();
+
+    let garbler = async {
+        propose_circuit(&mut garbler, &proposal).await?;
+        run_garbler(&mut garbler, circuit, inputs).await
+    };
+    let evaluator = async {
+        let circuit = accept_circuit(&mut evaluator, registry).await?;
+        run_evaluator(&mut evaluator, &circuit, &[]).await
+    };
+    tokio::join!(garbler, evaluator)
+}
+
+fn handshake_error(error: &anyhow::Error) -> &HandshakeError {
+    error
+        .downcast_ref::<HandshakeError>()
+        .expect("expected a handshake error")
+}
+
+#[tokio::test]
+async fn test_named_proposal() {
+    let (circuit, inputs, _) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+    let proposal = CircuitProposal::named("multi_arithmetic", &circuit);
+
+    let (garbler, evaluator) = negotiate_and_run(&registry(), proposal, &circuit, &inputs).await;
+    garbler.unwrap();
+    let result: u8 = GarbledUint8::new(evaluator.unwrap()).into();
+    assert_eq!(result, 9);
+}
+
+#[tokio::test]
+async fn test_hash_proposal() {
+    let (circuit, inputs, _) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+    let proposal = CircuitProposal::hash(&circuit);
+
+    let (garbler, evaluator) = negotiate_and_run(&registry(), proposal, &circuit, &inputs).await;
+    garbler.unwrap();
+    let result: u8 = GarbledUint8::new(evaluator.unwrap()).into();
+    assert_eq!(result, 9);
+}
+
+#[tokio::test]
+async fn test_upload_proposal() {
+    let (circuit, inputs, _) = addition(20_u8, 22_u8);
+    let proposal = CircuitProposal::upload(&circuit).unwrap();
+
+    // uploads are only accepted for allowed hashes
+    let (garbler, evaluator) =
+        negotiate_and_run(&registry(), proposal.clone(), &circuit, &inputs).await;
+    assert_eq!(
+        handshake_error(&garbler.unwrap_err()),
+        &HandshakeError::NotAllowed(circuit.blake3_hash())
+    );
+    assert!(evaluator.is_err());
+
+    let mut registry = registry();
+    registry.allow_upload(circuit.blake3_hash());
+    let (garbler, evaluator) = negotiate_and_run(&registry, proposal, &circuit, &inputs).await;
+    garbler.unwrap();
+    let result: u8 = GarbledUint8::new(evaluator.unwrap()).into();
+    assert_eq!(result, 42);
+}
+
+#[tokio::test]
+async fn test_rejected_proposals() {
+    let (circuit, inputs, _) = addition(1_u8, 2_u8);
+
+    // unknown id
+    let proposal = CircuitProposal::named("addition", &circuit);
+    let (garbler, evaluator) = negotiate_and_run(&registry(), proposal, &circuit, &inputs).await;
+    let error = garbler.unwrap_err();
+    assert_eq!(
+        handshake_error(&error),
+        &HandshakeError::UnknownCircuit("addition".into())
+    );
+    assert_eq!(error.to_string(), "Unknown circuit 'addition'");
+    assert_eq!(
+        handshake_error(&evaluator.unwrap_err()),
+        &HandshakeError::UnknownCircuit("addition".into())
+    );
+
+    // a different circuit under a known id
+    let proposal = CircuitProposal::named("multi_arithmetic", &circuit);
+    let (garbler, _) = negotiate_and_run(&registry(), proposal, &circuit, &inputs).await;
+    assert!(matches!(
+        handshake_error(&garbler.unwrap_err()),
+        HandshakeError::HashMismatch { .. }
+    ));
+
+    // an unknown hash
+    let proposal = CircuitProposal::hash(&circuit);
+    let (garbler, _) = negotiate_and_run(&registry(), proposal, &circuit, &inputs).await;
+    assert!(matches!(
+        handshake_error(&garbler.unwrap_err()),
+        HandshakeError::NotAllowed(_)
+    ));
+
+    // garbage instead of a circuit
+    let proposal = CircuitProposal::Upload(vec![1, 2, 3]);
+    let (garbler, _) = negotiate_and_run(&registry(), proposal, &circuit, &inputs).await;
+    assert!(matches!(
+        handshake_error(&garbler.unwrap_err()),
+        HandshakeError::InvalidCircuit(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_protocol_without_handshake() {
+    let (circuit, inputs, _) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+    let (garbler, mut evaluator) = MemoryTransport::pair();
+
+    let garbler = tokio::spawn(async move { run_garbler(garbler, &circuit, &inputs).await });
+    let error = accept_circuit(&mut evaluator, &registry())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Expected a handshake message"));
+
+    drop(evaluator);
+    assert!(garbler.await.unwrap().is_err());
+}
+
+#[tokio::test]
+async fn test_list_circuits_before_proposal() {
+    let (circuit, inputs, _) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+    let mut registry = registry();
+    let (addition_circuit, _, _) = addition(0_u8, 0_u8);
+    registry.register("addition", addition_circuit);
+    let (mut garbler, mut evaluator) = MemoryTransport::pair();
+
+    let garbler = async {
+        let circuits = list_circuits(&mut garbler).await?;
+        let circuits = list_circuits(&mut garbler).await.map(|again| {
+            assert_eq!(again, circuits);
+            circuits
+        })?;
+        let info = circuits
+            .iter()
+            .find(|info| info.id == "multi_arithmetic")
+            .unwrap()
+            .clone();
+        assert_eq!(info.hash, circuit.blake3_hash());
+        propose_circuit(
+            &mut garbler,
+            &CircuitProposal::Named {
+                id: info.id,
+                hash: info.hash,
+            },
+        )
+        .await?;
+        run_garbler(&mut garbler, &circuit, &inputs).await?;
+        Ok::<_, anyhow::Error>(circuits)
+    };
+    let evaluator = async {
+        let circuit = accept_circuit(&mut evaluator, &registry).await?;
+        run_evaluator(&mut evaluator, &circuit, &[]).await
+    };
+    let (circuits, output) = tokio::join!(garbler, evaluator);
+
+    let circuits = circuits.unwrap();
+    assert_eq!(circuits, registry.infos());
+    assert_eq!(circuits[0].id, "addition");
+    assert_eq!(circuits[1].garbler_inputs, 32);
+    assert_eq!(circuits[1].outputs, 8);
+    let result: u8 = GarbledUint8::new(output.unwrap()).into();
+    assert_eq!(result, 9);
+}
+
+#[tokio::test]
+async fn test_upload_too_large() {
+    let (circuit, inputs, _) = addition(20_u8, 22_u8);
+    let proposal = CircuitProposal::upload(&circuit).unwrap();
+    let gates = circuit.gates().len();
+
+    let mut registry = registry();
+    registry.allow_upload(circuit.blake3_hash());
+    registry.set_max_gates(gates - 1);
+    let (garbler, _) = negotiate_and_run(&registry, proposal.clone(), &circuit, &inputs).await;
+    assert_eq!(
+        handshake_error(&garbler.unwrap_err()),
+        &HandshakeError::CircuitTooLarge {
+            gates,
+            max: gates - 1
+        }
+    );
+
+    // uploads too long for the gate limit are refused without being deserialized
+    let upload = vec![0xff; serialize_circuit(&circuit).unwrap().len() * 4];
+    let bytes = upload.len();
+    let (garbler, _) = negotiate_and_run(
+        &registry,
+        CircuitProposal::Upload(upload),
+        &circuit,
+        &inputs,
+    )
+    .await;
+    assert!(matches!(
+        handshake_error(&garbler.unwrap_err()),
+        HandshakeError::UploadTooLarge { bytes: b, .. } if *b == bytes
+    ));
+
+    registry.set_max_gates(gates);
+    let (garbler, evaluator) = negotiate_and_run(&registry, proposal, &circuit, &inputs).await;
+    garbler.unwrap();
+    let result: u8 = GarbledUint8::new(evaluator.unwrap()).into();
+    assert_eq!(result, 42);
+
+    // registered circuits are trusted whatever their size
+    let (circuit, inputs, _) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+    assert!(circuit.gates().len() > gates);
+    let proposal = CircuitProposal::named("multi_arithmetic", &circuit);
+    let (garbler, evaluator) = negotiate_and_run(&registry, proposal, &circuit, &inputs).await;
+    garbler.unwrap();
+    let result: u8 = GarbledUint8::new(evaluator.unwrap()).into();
+    assert_eq!(result, 9);
+}
@@ -0,0 +1,115 @@
+use compute::lazy;
+use compute::prelude::*;
+
+#[test]
+fn test_lazy_arithmetic() {
+    let a: GarbledUint8 = 12_u8.into();
+    let b: GarbledUint8 = 10_u8.into();
+    let c: GarbledUint8 = 3_u8.into();
+
+    let a = a.lazy();
+    let b = b.lazy();
+    let c = c.lazy();
+
+    let result: u8 = ((&a + &b) * &c - &a).reveal().into();
+    assert_eq!(result, (12 + 10) * 3 - 12);
+
+    let result: u8 = (&a / &c + &b % &c).reveal().into();
+    assert_eq!(result, 12 / 3 + 10 % 3);
+}
+
+#[test]
+fn test_lazy_bitwise() {
+    let a: LazyUint16 = GarbledUint16::from(0xf0f0_u16).into();
+    let b: LazyUint16 = GarbledUint16::from(0x1234_u16).into();
+
+    let result: u16 = ((&a & &b) | !(&a ^ &b)).reveal().into();
+    assert_eq!(result, (0xf0f0 & 0x1234) | !(0xf0f0 ^ 0x1234));
+
+    let result: u16 = ((&a << 4) ^ (b >> 2)).reveal().into();
+    assert_eq!(result, (0xf0f0 << 4) ^ (0x1234 >> 2));
+}
+
+#[test]
+fn test_lazy_comparison_and_mux() {
+    let a = GarbledUint32::from(1000_u32).lazy();
+    let b = GarbledUint32::from(2000_u32).lazy();
+
+    let lt: bool = a.lt(&b).reveal().into();
+    let ge: bool = a.ge(&b).reveal().into();
+    let eq: bool = a.eq(&a.clone()).reveal().into();
+    assert!(lt);
+    assert!(!ge);
+    assert!(eq);
+
+    let max = LazyUint::mux(&a.gt(&b), &a, &b);
+    let result: u32 = max.reveal().into();
+    assert_eq!(result, 2000);
+}
+
+#[test]
+fn test_lazy_assign() {
+    let mut a = GarbledUint8::from(5_u8).lazy();
+    let b = GarbledUint8::from(7_u8).lazy();
+
+    a += &b;
+    a *= b;
+    let result: u8 = a.reveal().into();
+    assert_eq!(result, (5 + 7) * 7);
+}
+
+#[test]
+fn test_lazy_constant_and_evaluator_input() {
+    let a = GarbledUint8::from(40_u8).lazy();
+    let b = LazyUint8::input_eval(&GarbledUint8::from(2_u8));
+    let c = LazyUint8::constant(&GarbledUint8::from(50_u8));
+
+    let result: u8 = (a * b - c).reveal().into();
+    assert_eq!(result, 40 * 2 - 50);
+}
+
+#[test]
+#[should_panic(expected = "LazyUint used after the shared circuit was reset")]
+fn test_lazy_reset() {
+    let a = GarbledUint8::from(40_u8).lazy();
+    lazy::reset();
+
+    let b = GarbledUint8::from(2_u8).lazy();
+    let _ = a + b;
+}
+
+#[test]
+fn test_lazy_sessions_are_independent() {
+    let mut builder = CircuitBuilder::default();
+    let a = builder.input(&GarbledUint8::from(0_u8));
+    let b = builder.input(&GarbledUint8::from(0_u8));
+    let product = builder.mul(&a, &b);
+    let expected = builder.compile(&product);
+
+    for (x, y) in [(3_u8, 4_u8), (5, 6)] {
+        let _session = lazy_session();
+        let product = GarbledUint8::from(x).lazy() * GarbledUint8::from(y).lazy();
+
+        // only this session's inputs and gates end up in the circuit
+        let circuit = product.compile();
+        assert_eq!(circuit.contrib_inputs(), 16);
+        assert_eq!(circuit.blake3_hash(), expected.blake3_hash());
+
+        let result: u8 = product.reveal().into();
+        assert_eq!(result, x * y);
+    }
+
+    let result: u8 = with_lazy_session(|| {
+        let three = LazyUint8::constant(&GarbledUint8::from(3_u8));
+        (GarbledUint8::from(7_u8).lazy() * three).reveal()
+    })
+    .into();
+    assert_eq!(result, 21);
+}
+
+#[test]
+#[should_panic(expected = "LazyUint used after the shared circuit was reset")]
+fn test_lazy_value_outlives_session() {
+    let a = with_lazy_session(|| GarbledUint8::from(40_u8).lazy());
+    let _ = a.reveal();
+}
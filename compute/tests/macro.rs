@@ -3,7 +3,7 @@ use compute::prelude::*;
 #[test]
 fn test_macro_arithmetic_compiler() {
     #[encrypted(compile)]
-    fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> (Circuit, Vec<bool>) {
+    fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> (Circuit, Vec<bool>, Vec<bool>) {
         let res = a * b;
         let res = res + c;
         res - d
@@ -14,13 +14,58 @@ fn test_macro_arithmetic_compiler() {
     let c = 3_u8;
     let d = 4_u8;
 
-    let (circuit, inputs) = multi_arithmetic(a, b, c, d);
-    let result = get_executor().execute(&circuit, &inputs, &[]).unwrap();
+    let (circuit, inputs, eval_inputs) = multi_arithmetic(a, b, c, d);
+    assert!(eval_inputs.is_empty());
+    let result = get_executor()
+        .execute(&circuit, &inputs, &eval_inputs)
+        .unwrap();
     let result: GarbledUint<8> = GarbledUint::new(result);
     let result: u8 = result.into();
     assert_eq!(result, a * b + c - d);
 }
 
+#[test]
+fn test_macro_evaluator_inputs_compiler() {
+    #[encrypted(compile)]
+    fn multi_arithmetic(a: u8, #[evaluator] b: u8, c: u8, #[evaluator] d: u8) -> u8 {
+        let res = a * b;
+        let res = res + c;
+        res - d
+    }
+
+    let a = 2_u8;
+    let b = 5_u8;
+    let c = 3_u8;
+    let d = 4_u8;
+
+    // the garbler only knows its own values, the evaluator's are placeholders
+    let (circuit, garbler_inputs, _) = multi_arithmetic(a, 0, c, 0);
+    // the evaluator only knows its own values, the garbler's are placeholders
+    let (evaluator_circuit, _, evaluator_inputs) = multi_arithmetic(0, b, 0, d);
+
+    assert_eq!(circuit.blake3_hash(), evaluator_circuit.blake3_hash());
+    assert_eq!(circuit.contrib_inputs(), 16);
+    assert_eq!(circuit.eval_inputs(), 16);
+
+    let result = get_executor()
+        .execute(&circuit, &garbler_inputs, &evaluator_inputs)
+        .unwrap();
+    let result: GarbledUint<8> = GarbledUint::new(result);
+    let result: u8 = result.into();
+    assert_eq!(result, a * b + c - d);
+}
+
+#[test]
+fn test_macro_evaluator_inputs() {
+    #[encrypted(execute)]
+    fn threshold(#[evaluator] threshold: u16, value: u16) -> bool {
+        value >= threshold
+    }
+
+    assert!(threshold(100_u16, 150_u16));
+    assert!(!threshold(100_u16, 50_u16));
+}
+
 #[test]
 fn test_macro_arithmetic() {
     #[encrypted(execute)]
@@ -42,7 +87,7 @@ fn test_macro_arithmetic() {
 #[test]
 fn test_macro_arithmetic_u128() {
     #[encrypted(execute)]
-    fn multi_arithmetic_u128(a: u8, b: u8, c: u8, d: u8) -> u8 {
+    fn multi_arithmetic_u128(a: u128, b: u128, c: u128, d: u128) -> u128 {
         let res = a + b;
         let res = res + c;
         res - d
@@ -76,6 +121,26 @@ fn test_macro_mixed_arithmetic() {
     assert_eq!(result, ((a * b + c - d) * a));
 }
 
+#[test]
+fn test_macro_mixed_width_arithmetic() {
+    #[encrypted(execute)]
+    fn mixed_arithmetic(a: u8, b: u16, c: u32, d: u8) -> u32 {
+        let res = a * b;
+        let res = res + c;
+        let res = res - d;
+        res * a
+    }
+
+    let a = 200_u8;
+    let b = 300_u16;
+    let c = 70_000_u32;
+    let d = 4_u8;
+
+    // the u8 operands are widened, so the products do not wrap at 8 or 16 bits
+    let result = mixed_arithmetic(a, b, c, d);
+    assert_eq!(result, (200 * 300 + 70_000 - 4) * 200);
+}
+
 #[test]
 fn test_macro_addition() {
     #[encrypted(execute)]
@@ -133,6 +198,25 @@ fn test_macro_mux() {
     assert_eq!(result, b);
 }
 
+#[test]
+fn test_macro_mux_condition_variable() {
+    #[encrypted(execute)]
+    fn mux_circuit(a: u8, b: u8) -> u8 {
+        let condition = a == b;
+        if condition {
+            a
+        } else {
+            b
+        }
+    }
+
+    let a = 5_u8;
+    let b = 10_u8;
+
+    let result = mux_circuit(a, b);
+    assert_eq!(result, b);
+}
+
 #[test]
 fn test_macro_if_else() {
     #[encrypted(execute)]
@@ -219,7 +303,6 @@ fn test_macro_if_else4() {
     assert_eq!(result, (a + b) * (a + b));
 }
 
-#[ignore = "division not yet supported"]
 #[test]
 fn test_macro_division() {
     #[encrypted(execute)]
@@ -234,7 +317,6 @@ fn test_macro_division() {
     assert_eq!(result, a / b);
 }
 
-#[ignore = "modulo not yet supported"]
 #[test]
 fn test_macro_remainder() {
     #[encrypted(execute)]
@@ -606,6 +688,27 @@ fn test_macro_constants() {
     assert_eq!(result, 30_u8);
 }
 
+#[test]
+fn test_macro_constants_are_not_inputs() {
+    #[encrypted(compile)]
+    fn constants(a: u8) -> u8 {
+        let b = 100;
+        a * 3 + b
+    }
+
+    let (circuit, inputs, _) = constants(10_u8);
+    assert_eq!(inputs.len(), 8);
+    assert_eq!(circuit.contrib_inputs(), 8);
+
+    // the constants are baked into the circuit, so every input yields the same circuit
+    let (other_circuit, _, _) = constants(200_u8);
+    assert_eq!(circuit.blake3_hash(), other_circuit.blake3_hash());
+
+    let result = get_executor().execute(&circuit, &inputs, &[]).unwrap();
+    let result: u8 = GarbledUint::<8>::new(result).into();
+    assert_eq!(result, 130);
+}
+
 #[test]
 fn test_macro_embedded_constants() {
     #[encrypted(execute)]
@@ -759,6 +862,46 @@ fn test_bitxor_assign() {
     assert_eq!(result, 30_u8);
 }
 
+#[test]
+fn test_macro_shifts() {
+    #[encrypted(execute)]
+    fn shifts(a: u8, b: u8) -> u8 {
+        (a << 2) ^ (a >> b)
+    }
+
+    let a = 0b1011_0110_u8;
+    let b = 3_u8;
+    let result = shifts(a, b);
+    assert_eq!(result, (a << 2) ^ (a >> b));
+}
+
+#[test]
+fn test_shl_assign() {
+    #[encrypted(execute)]
+    fn shl_assign(a: u8, b: u8) -> u8 {
+        let c = a;
+        c <<= b
+    }
+
+    let a = 10_u8;
+    let b = 3_u8;
+    let result = shl_assign(a, b);
+    assert_eq!(result, 80_u8);
+}
+
+#[test]
+fn test_shr_assign() {
+    #[encrypted(execute)]
+    fn shr_assign(a: u8) -> u8 {
+        let c = a;
+        c >>= 2
+    }
+
+    let a = 200_u8;
+    let result = shr_assign(a);
+    assert_eq!(result, 50_u8);
+}
+
 #[test]
 fn test_if_elif_else() {
     #[encrypted(execute)]
@@ -1310,3 +1453,596 @@ fn test_macro_if() {
     let result = if_test(a);
     assert_eq!(result, 100);
 }
+
+#[test]
+fn test_macro_mixed_parameter_types() {
+    #[encrypted(execute)]
+    fn eligible(age: u8, income: u32) -> bool {
+        age >= 18 && income > 50_000
+    }
+
+    assert!(eligible(30, 60_000));
+    assert!(!eligible(17, 60_000));
+    assert!(!eligible(30, 40_000));
+}
+
+#[test]
+fn test_macro_mixed_widths_compiler() {
+    #[encrypted(compile)]
+    fn weighted(a: u8, #[evaluator] b: u32, flag: bool) -> u32 {
+        if flag {
+            a + b
+        } else {
+            b
+        }
+    }
+
+    let (circuit, inputs, eval_inputs) = weighted(255, 1_000, true);
+    assert_eq!(circuit.contrib_inputs(), 9);
+    assert_eq!(circuit.eval_inputs(), 32);
+    assert_eq!(circuit.output_gates().len(), 32);
+
+    let result = get_executor()
+        .execute(&circuit, &inputs, &eval_inputs)
+        .unwrap();
+    let result: u32 = GarbledUint::<32>::new(result).into();
+    assert_eq!(result, 1_255);
+}
+
+#[test]
+fn test_macro_casts() {
+    #[encrypted(execute)]
+    fn low_byte(a: u16, b: u16) -> u8 {
+        let sum = a + b;
+        sum as u8
+    }
+
+    #[encrypted(execute)]
+    fn widened(a: u8, b: u8) -> u16 {
+        let product: u16 = (a as u16) * (b as u16);
+        product + (a > b) as u16
+    }
+
+    assert_eq!(low_byte(0x1ff, 0x002), 0x01);
+    assert_eq!(widened(200, 100), 20_001);
+    assert_eq!(widened(2, 3), 6);
+}
+
+#[test]
+fn test_macro_literal_widths() {
+    #[encrypted(execute)]
+    fn scale(a: u8, b: u32) -> u32 {
+        // the literal takes the width of `b`, so it does not need to fit in a u8
+        let offset = b + 100_000;
+        let c = 3;
+        offset * c + a
+    }
+
+    assert_eq!(scale(7, 1), 300_010);
+}
+
+#[test]
+fn test_macro_assignment_in_branches() {
+    #[encrypted(execute)]
+    fn clamp(a: u8, limit: u16) -> u16 {
+        let mut value = 0;
+        if a > 100 {
+            value = limit;
+        } else {
+            value = a;
+        }
+        value
+    }
+
+    assert_eq!(clamp(150, 1_000), 1_000);
+    assert_eq!(clamp(50, 1_000), 50);
+}
+
+#[test]
+fn test_macro_signed_arithmetic() {
+    #[encrypted(execute)]
+    fn signed_arithmetic(a: i8, b: i8) -> i8 {
+        (a - b) * 3 / 2
+    }
+
+    assert_eq!(signed_arithmetic(-5, 3), -12);
+    assert_eq!(signed_arithmetic(5, 3), 3);
+    assert_eq!(signed_arithmetic(-7, -7), 0);
+}
+
+#[test]
+fn test_macro_signed_division() {
+    #[encrypted(execute)]
+    fn quotient(a: i16, b: i16) -> i16 {
+        a / b
+    }
+
+    #[encrypted(execute)]
+    fn remainder(a: i16, b: i16) -> i16 {
+        a % b
+    }
+
+    // like Rust, division truncates toward zero and the remainder has the sign of the dividend
+    for (a, b) in [(-7_i16, 2_i16), (7, -2), (-7, -2), (300, 7), (-300, 7)] {
+        assert_eq!(quotient(a, b), a / b);
+        assert_eq!(remainder(a, b), a % b);
+    }
+}
+
+#[test]
+fn test_macro_signed_comparisons() {
+    #[encrypted(execute)]
+    fn below(a: i32, b: i32) -> bool {
+        a < b
+    }
+
+    #[encrypted(execute)]
+    fn sign(a: i32) -> i8 {
+        if a > 0 {
+            1
+        } else if a < 0 {
+            -1
+        } else {
+            0
+        }
+    }
+
+    assert!(below(-1, 0));
+    assert!(!below(0, -1));
+    assert!(below(i32::MIN, i32::MAX));
+    assert_eq!(sign(-42), -1);
+    assert_eq!(sign(42), 1);
+    assert_eq!(sign(0), 0);
+}
+
+#[test]
+fn test_macro_signed_shifts() {
+    #[encrypted(execute)]
+    fn shifts(a: i16, b: u8) -> i16 {
+        (a >> 2) + (a >> b)
+    }
+
+    assert_eq!(shifts(-100, 3), (-100 >> 2) + (-100 >> 3));
+    assert_eq!(shifts(100, 1), (100 >> 2) + (100 >> 1));
+}
+
+#[test]
+fn test_macro_signed_widening() {
+    #[encrypted(execute)]
+    fn balance(delta: i8, total: i32) -> i32 {
+        // the i8 operand is sign-extended to 32 bits
+        total + delta - 1_000
+    }
+
+    #[encrypted(execute)]
+    fn magnitude(a: i16) -> u16 {
+        let negated = -a;
+        if a < 0 {
+            negated as u16
+        } else {
+            a as u16
+        }
+    }
+
+    assert_eq!(balance(-5, 100_000), 98_995);
+    assert_eq!(balance(127, -1), -874);
+    assert_eq!(magnitude(-300), 300);
+    assert_eq!(magnitude(300), 300);
+}
+
+#[test]
+fn test_macro_signed_compiler() {
+    #[encrypted(compile)]
+    fn difference(a: i64, #[evaluator] b: i64) -> i64 {
+        a - b
+    }
+
+    let (circuit, inputs, eval_inputs) = difference(-20, 22);
+    assert_eq!(circuit.contrib_inputs(), 64);
+    assert_eq!(circuit.eval_inputs(), 64);
+
+    let result = get_executor()
+        .execute(&circuit, &inputs, &eval_inputs)
+        .unwrap();
+    let result: i64 = GarbledInt::<64>::new(result).into();
+    assert_eq!(result, -42);
+}
+
+#[test]
+fn test_macro_type_parameter_widths() {
+    #[encrypted(execute)]
+    fn larger(a: T, b: T) -> T {
+        if a > b {
+            a
+        } else {
+            b
+        }
+    }
+
+    #[encrypted(execute)]
+    fn either(a: T, b: T) -> T {
+        a | b
+    }
+
+    // each call instantiates the body at the width of its argument type
+    assert_eq!(larger(200_u8, 100_u8), 200_u8);
+    assert_eq!(larger(1_000_u16, 60_000_u16), 60_000_u16);
+    assert_eq!(larger(u64::MAX, 1_u64), u64::MAX);
+    assert!(either(true, false));
+    assert!(!either(false, false));
+}
+
+#[test]
+fn test_macro_type_parameter_signed() {
+    #[encrypted(execute)]
+    fn larger(a: T, b: T) -> T {
+        if a > b {
+            a
+        } else {
+            b
+        }
+    }
+
+    #[encrypted(execute)]
+    fn divide(a: T, b: T) -> T {
+        a / b
+    }
+
+    #[encrypted(execute)]
+    fn remainder(a: T, b: T) -> T {
+        a % b
+    }
+
+    #[encrypted(execute)]
+    fn halve(a: T, b: T) -> T {
+        (a >> 1) + (a >> b) - b
+    }
+
+    // signed arguments order, divide and shift in two's complement
+    assert_eq!(larger(-5_i8, 3_i8), 3_i8);
+    assert_eq!(larger(-5_i16, -300_i16), -5_i16);
+    assert_eq!(larger(i32::MIN, -1_i32), -1_i32);
+    assert_eq!(larger(-1_i64, 1_i64), 1_i64);
+    assert_eq!(larger(i128::MIN + 1, i128::MIN), i128::MIN + 1);
+    assert_eq!(divide(-7_i8, 2_i8), -3_i8);
+    assert_eq!(divide(300_i16, -7_i16), 300 / -7);
+    assert_eq!(remainder(-7_i32, 2_i32), -1_i32);
+    assert_eq!(halve(-100_i16, 3_i16), (-100 >> 1) + (-100 >> 3) - 3);
+
+    // the same bodies stay unsigned for unsigned arguments
+    assert_eq!(larger(250_u8, 3_u8), 250_u8);
+    assert_eq!(divide(250_u8, 2_u8), 125_u8);
+    assert_eq!(halve(200_u8, 3_u8), (200 >> 1) + (200 >> 3) - 3);
+}
+
+#[test]
+fn test_macro_type_parameter_signed_compiler() {
+    #[encrypted(compile)]
+    fn smaller(a: T, #[evaluator] b: T) -> T {
+        if a < b {
+            a
+        } else {
+            b
+        }
+    }
+
+    let (circuit, inputs, eval_inputs) = smaller(-20_i16, 5_i16);
+    assert_eq!(circuit.contrib_inputs(), 16);
+    assert_eq!(circuit.eval_inputs(), 16);
+
+    let result = get_executor()
+        .execute(&circuit, &inputs, &eval_inputs)
+        .unwrap();
+    let result: i16 = GarbledInt::<16>::new(result).into();
+    assert_eq!(result, -20);
+}
+
+#[test]
+fn test_macro_type_parameter_compiler() {
+    #[encrypted(compile)]
+    fn total(a: T, #[evaluator] b: T) -> T {
+        a + b
+    }
+
+    let (circuit, inputs, eval_inputs) = total(40_u32, 2_u32);
+    assert_eq!(circuit.contrib_inputs(), 32);
+    assert_eq!(circuit.eval_inputs(), 32);
+
+    let result = get_executor()
+        .execute(&circuit, &inputs, &eval_inputs)
+        .unwrap();
+    let result: u32 = GarbledUint::<32>::new(result).into();
+    assert_eq!(result, 42);
+}
+
+#[test]
+fn test_macro_for_loop_sum() {
+    #[encrypted(execute)]
+    fn popcount(a: u16) -> u8 {
+        let mut count: u8 = 0;
+        for i in 0..16 {
+            // the index is a constant, so each shift is only wiring
+            count += ((a >> i) & 1) as u8;
+        }
+        count
+    }
+
+    assert_eq!(popcount(0), 0);
+    assert_eq!(popcount(0b1011_0000_0000_0101), 5);
+    assert_eq!(popcount(u16::MAX), 16);
+}
+
+#[test]
+fn test_macro_for_loop_nested() {
+    #[encrypted(execute)]
+    fn weighted(a: u32) -> u32 {
+        let mut total = 0;
+        for i in 1..=4 {
+            for j in 0..i {
+                total = total + a * j + i;
+            }
+        }
+        total
+    }
+
+    #[encrypted(execute)]
+    fn power(base: u32) -> u32 {
+        let mut result: u32 = 1;
+        for _ in 0..5 {
+            result *= base;
+        }
+        result
+    }
+
+    // sum over i in 1..=4 of (a * i * (i - 1) / 2 + i * i)
+    assert_eq!(weighted(3), 3 * 10 + 30);
+    assert_eq!(power(3), 243);
+}
+
+#[test]
+fn test_macro_for_loop_branches() {
+    #[encrypted(execute)]
+    fn clamp_steps(a: u8, step: u8) -> u8 {
+        let mut value = a;
+        for i in 0..4 {
+            if value >= 200 {
+                value = 200;
+            } else {
+                value = value + step + i;
+            }
+        }
+        value
+    }
+
+    assert_eq!(clamp_steps(10, 5), 10 + 20 + 6);
+    assert_eq!(clamp_steps(190, 10), 200);
+}
+
+#[test]
+fn test_macro_for_loop_unroll_limit() {
+    // 40 outer and 1280 inner iterations are more than the default limit allows
+    #[encrypted(execute, unroll = 2048)]
+    fn repeated_popcount(a: u16) -> u16 {
+        let mut total = 0;
+        for _ in 0..40 {
+            for i in 0..32 {
+                total += (a >> i) & 1;
+            }
+        }
+        total
+    }
+
+    assert_eq!(repeated_popcount(0b111), 120);
+    assert_eq!(repeated_popcount(u16::MAX), 640);
+}
+
+#[test]
+fn test_macro_for_loop_empty_ranges() {
+    // empty and reversed ranges run no iterations and count none against the limit
+    #[encrypted(execute, unroll = 2)]
+    fn untouched(a: u8) -> u8 {
+        let mut value = a;
+        for i in 0..0 {
+            value += i;
+        }
+        for i in 5..0 {
+            value += i;
+        }
+        for i in 5..=4 {
+            value += i;
+        }
+        for i in 0..2 {
+            value += i;
+        }
+        value
+    }
+
+    assert_eq!(untouched(7), 8);
+}
+
+#[test]
+fn test_macro_array_sum() {
+    #[encrypted(execute)]
+    fn total(amounts: [u16; 8]) -> u32 {
+        let mut total: u32 = 0;
+        for amount in amounts {
+            total += amount;
+        }
+        total
+    }
+
+    assert_eq!(total([1, 2, 3, 4, 5, 6, 7, 8]), 36);
+    assert_eq!(total([u16::MAX; 8]), 8 * u16::MAX as u32);
+}
+
+#[test]
+fn test_macro_array_constant_index() {
+    #[encrypted(execute)]
+    fn score(transactions: [u8; 16], threshold: u8) -> u8 {
+        let mut flagged = 0;
+        for i in 0..transactions.len() {
+            if transactions[i] > threshold {
+                flagged += 1;
+            } else {
+            }
+        }
+        // the first and last transactions count twice
+        flagged + (transactions[0] > threshold) as u8 + (transactions[15] > threshold) as u8
+    }
+
+    let mut transactions = [10_u8; 16];
+    transactions[0] = 200;
+    transactions[7] = 150;
+    assert_eq!(score(transactions, 100), 3);
+    assert_eq!(score(transactions, 160), 2);
+}
+
+#[test]
+fn test_macro_array_secret_index() {
+    #[encrypted(execute)]
+    fn lookup(table: [u8; 5], #[evaluator] index: u8) -> u8 {
+        table[index]
+    }
+
+    #[encrypted(execute)]
+    fn signed_lookup(table: [i16; 3], index: i8) -> i16 {
+        table[index] + 1
+    }
+
+    let table = [11, 22, 33, 44, 55];
+    for index in 0..5 {
+        assert_eq!(lookup(table, index), table[index as usize]);
+    }
+    // a secret index past the end reads zero
+    assert_eq!(lookup(table, 5), 0);
+    assert_eq!(lookup(table, 255), 0);
+    assert_eq!(signed_lookup([-5, 7, -300], 2), -299);
+    assert_eq!(signed_lookup([-5, 7, -300], -1), 1);
+}
+
+#[test]
+fn test_macro_array_assignment() {
+    #[encrypted(execute)]
+    fn histogram(values: [u8; 6]) -> [u8; 4] {
+        let mut buckets = [0_u8; 4];
+        for value in values {
+            // the bucket is secret, so every bucket is written through a multiplexer
+            buckets[value >> 6] += 1;
+        }
+        buckets
+    }
+
+    #[encrypted(execute)]
+    fn reversed(values: [i32; 4]) -> [i32; 4] {
+        let mut result: [i32; 4] = [0; 4];
+        for i in 0..4 {
+            result[3 - i] = values[i];
+        }
+        result
+    }
+
+    assert_eq!(histogram([0, 63, 64, 200, 255, 130]), [2, 1, 1, 2]);
+    assert_eq!(reversed([1, -2, 3, -4]), [-4, 3, -2, 1]);
+}
+
+#[test]
+fn test_macro_array_compound_assignment() {
+    #[encrypted(execute)]
+    fn bump(values: [u8; 4], start: u8) -> [u8; 4] {
+        let mut values = values;
+        let mut i = start;
+        // the index is evaluated once, so the element read is the one written
+        values[{
+            i += 1;
+            i
+        }] += 10;
+        values[0] += i;
+        values
+    }
+
+    assert_eq!(bump([1, 2, 3, 4], 1), [3, 2, 13, 4]);
+    assert_eq!(bump([1, 2, 3, 4], 2), [4, 2, 3, 14]);
+}
+
+#[test]
+fn test_macro_array_branches() {
+    #[encrypted(execute)]
+    fn sorted_pair(a: u8, b: u8) -> [u8; 2] {
+        let pair = [a, b];
+        if a > b {
+            [b, a]
+        } else {
+            pair
+        }
+    }
+
+    #[encrypted(execute)]
+    fn bubble_sort(values: [u8; 4]) -> [u8; 4] {
+        let mut values = values;
+        for i in 0..3 {
+            for j in 0..3 - i {
+                let low = values[j];
+                let high = values[j + 1];
+                if low > high {
+                    values[j] = high;
+                    values[j + 1] = low;
+                } else {
+                }
+            }
+        }
+        values
+    }
+
+    assert_eq!(sorted_pair(9, 3), [3, 9]);
+    assert_eq!(sorted_pair(3, 9), [3, 9]);
+    assert_eq!(bubble_sort([7, 1, 9, 4]), [1, 4, 7, 9]);
+}
+
+#[test]
+fn test_macro_array_compiler() {
+    #[encrypted(compile)]
+    fn dot(a: [u8; 3], #[evaluator] b: [u8; 3]) -> u16 {
+        let mut sum: u16 = 0;
+        for i in 0..3 {
+            sum += (a[i] as u16) * (b[i] as u16);
+        }
+        sum
+    }
+
+    let (circuit, inputs, eval_inputs) = dot([1, 2, 3], [4, 5, 6]);
+    assert_eq!(circuit.contrib_inputs(), 24);
+    assert_eq!(circuit.eval_inputs(), 24);
+    // arrays are flattened element by element, each least significant bit first
+    assert_eq!(
+        inputs[..8],
+        [true, false, false, false, false, false, false, false]
+    );
+    assert_eq!(
+        inputs[8..16],
+        [false, true, false, false, false, false, false, false]
+    );
+
+    let result = get_executor()
+        .execute(&circuit, &inputs, &eval_inputs)
+        .unwrap();
+    let result: u16 = GarbledUint::<16>::new(result).into();
+    assert_eq!(result, 32);
+}
+
+#[test]
+fn test_macro_array_type_parameter() {
+    #[encrypted(execute)]
+    fn largest(values: [T; 4]) -> T {
+        let mut largest = values[0];
+        for value in values {
+            if value > largest {
+                largest = value;
+            } else {
+            }
+        }
+        largest
+    }
+
+    assert_eq!(largest([3_u8, 250, 7, 1]), 250);
+    assert_eq!(largest([1_000_u32, 2, 70_000, 5]), 70_000);
+}
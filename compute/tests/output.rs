@@ -0,0 +1,131 @@
+mod common;
+
+use common::multi_arithmetic;
+use compute::output::OutputError;
+use compute::prelude::*;
+
+// The bits of `value`, least significant first
+fn bits(value: i128, width: usize) -> Vec<bool> {
+    (0..width).map(|i| (value >> i) & 1 == 1).collect()
+}
+
+#[test]
+fn test_decode_integers() {
+    let output = Output::new(OutputSchema::Uint(8), &bits(200, 8)).unwrap();
+    let result: GarbledUint8 = output.decode().unwrap();
+    assert_eq!(u8::from(result), 200);
+
+    let output = Output::new(OutputSchema::Int(16), &bits(-1234, 16)).unwrap();
+    let result: GarbledInt16 = output.decode().unwrap();
+    assert_eq!(i16::from(result), -1234);
+
+    // wider than the 128 bits the old client could read
+    let mut wide = vec![false; 256];
+    wide[255] = true;
+    let output = Output::new(OutputSchema::Uint(256), &wide).unwrap();
+    let result: GarbledUint256 = output.decode().unwrap();
+    assert_eq!(result.bits, wide);
+
+    let output = Output::new(OutputSchema::Bool, &[true]).unwrap();
+    assert!(output.decode::<bool>().unwrap());
+}
+
+#[test]
+fn test_decode_tuple() {
+    let schema = OutputSchema::Tuple(vec![
+        OutputSchema::Bool,
+        OutputSchema::Int(8),
+        OutputSchema::Uint(16),
+    ]);
+    assert_eq!(schema.to_string(), "(bool, i8, u16)");
+    assert_eq!(schema.width(), 25);
+
+    let mut output_bits = vec![true];
+    output_bits.extend(bits(-7, 8));
+    output_bits.extend(bits(40_000, 16));
+    let output = Output::new(schema, &output_bits).unwrap();
+    assert_eq!(output.bits(), output_bits);
+
+    let (decision, delta, score): (bool, GarbledInt8, GarbledUint16) = output.decode().unwrap();
+    assert!(decision);
+    assert_eq!(i8::from(delta), -7);
+    assert_eq!(u16::from(score), 40_000);
+}
+
+#[test]
+fn test_schema_mismatch() {
+    let output = Output::new(OutputSchema::Int(8), &bits(-1, 8)).unwrap();
+    assert_eq!(
+        output.decode::<GarbledUint8>().unwrap_err(),
+        OutputError::SchemaMismatch {
+            expected: OutputSchema::Uint(8),
+            found: OutputSchema::Int(8),
+        }
+    );
+    assert!(output.decode::<GarbledInt16>().is_err());
+    assert!(output.decode::<(GarbledInt8,)>().is_err());
+
+    assert_eq!(
+        Output::new(OutputSchema::Uint(8), &bits(1, 7)).unwrap_err(),
+        OutputError::WidthMismatch {
+            schema: OutputSchema::Uint(8),
+            bits: 7
+        }
+    );
+}
+
+#[test]
+fn test_bit_packing() {
+    let output = Output::new(OutputSchema::Uint(12), &bits(0xabc, 12)).unwrap();
+    let bytes = output.to_bytes().unwrap();
+    assert_eq!(Output::from_bytes(&bytes).unwrap(), output);
+    // the packed bits take two bytes at the end of the encoding
+    assert_eq!(bytes[bytes.len() - 2..], [0xbc, 0x0a]);
+
+    // nonzero padding
+    let mut padded = bytes.clone();
+    *padded.last_mut().unwrap() |= 0x10;
+    let error = Output::from_bytes(&padded).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<OutputError>(),
+        Some(&OutputError::InvalidPacking {
+            width: 12,
+            bytes: 2
+        })
+    );
+
+    // fewer bytes than the schema needs
+    let bytes = bincode::serialize(&(OutputSchema::Uint(16), vec![1_u8])).unwrap();
+    let error = Output::from_bytes(&bytes).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<OutputError>(),
+        Some(&OutputError::InvalidPacking {
+            width: 16,
+            bytes: 1
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_send_output() {
+    let (circuit, inputs, _) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+    let (mut garbler, mut evaluator) = MemoryTransport::pair();
+
+    let garbler = async {
+        run_garbler(&mut garbler, &circuit, &inputs).await?;
+        recv_output(&mut garbler).await
+    };
+    let evaluator = async {
+        let output = run_evaluator(&mut evaluator, &circuit, &[]).await?;
+        let output = Output::new(OutputSchema::Uint(8), &output)?;
+        send_output(&mut evaluator, &output).await
+    };
+    let (output, result) = tokio::join!(garbler, evaluator);
+
+    result.unwrap();
+    let output = output.unwrap();
+    assert_eq!(output.schema(), &OutputSchema::Uint(8));
+    assert_eq!(output.len(), 8);
+    let result: GarbledUint8 = output.decode().unwrap();
+    assert_eq!(u8::from(result), 9);
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::{multi_arithmetic, TempDir};
+use compute::operations::util::serialize_circuit;
+use compute::prelude::*;
+use std::fs;
+
+#[encrypted(compile)]
+fn weighted_sum(a: u16, #[evaluator] weight: u16) -> u16 {
+    a * weight
+}
+
+#[test]
+fn test_circuit_info() {
+    let mut registry = CircuitRegistry::new();
+    let (circuit, _, _) = weighted_sum(0_u16, 0_u16);
+    registry.register("weighted_sum", circuit.clone());
+
+    let info = registry.info("weighted_sum").unwrap();
+    assert_eq!(info.id, "weighted_sum");
+    assert_eq!(info.hash, circuit.blake3_hash());
+    assert_eq!(info.garbler_inputs, 16);
+    assert_eq!(info.evaluator_inputs, 16);
+    assert_eq!(info.inputs, InputWidths::new([16], [16]));
+    assert_eq!(info.outputs, 16);
+    assert_eq!(info.output, OutputSchema::Uint(16));
+    assert_eq!(info.gates, circuit.gates().len());
+    assert_eq!(info.and_gates, circuit.and_gates());
+    assert!(registry.info("missing").is_none());
+}
+
+#[test]
+fn test_input_widths() {
+    let mut registry = CircuitRegistry::new();
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    registry.register("multi_arithmetic", circuit.clone());
+    assert_eq!(registry.input_widths(&circuit), InputWidths::new([32], []));
+
+    let widths = InputWidths::new([8, 8, 8, 8], []);
+    registry
+        .set_input_widths("multi_arithmetic", widths.clone())
+        .unwrap();
+    assert_eq!(registry.info("multi_arithmetic").unwrap().inputs, widths);
+
+    // the widths must cover exactly the input bits of each party
+    let error = registry
+        .set_input_widths("multi_arithmetic", InputWidths::new([8, 8], []))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Circuit 'multi_arithmetic' has 32 garbler input bits, but the widths [8, 8] add up to 16"
+    );
+    assert!(registry
+        .set_input_widths("multi_arithmetic", InputWidths::new([8, 8, 16], [0]))
+        .is_err());
+    assert!(registry
+        .set_input_widths("missing", InputWidths::default())
+        .is_err());
+
+    // replacing the circuit drops its widths
+    let (other, _, _) = weighted_sum(0_u16, 0_u16);
+    registry.register("multi_arithmetic", other);
+    assert_eq!(registry.input_widths(&circuit), InputWidths::new([32], []));
+}
+
+#[test]
+fn test_output_schema() {
+    let mut registry = CircuitRegistry::new();
+    let (circuit, _, _) = weighted_sum(0_u16, 0_u16);
+    registry.register("weighted_sum", circuit.clone());
+
+    let schema = OutputSchema::Tuple(vec![OutputSchema::Int(8), OutputSchema::Uint(8)]);
+    registry
+        .set_output_schema("weighted_sum", schema.clone())
+        .unwrap();
+    assert_eq!(registry.output_schema(&circuit), schema);
+    assert_eq!(registry.info("weighted_sum").unwrap().output, schema);
+
+    // the schema must cover exactly the output bits
+    let error = registry
+        .set_output_schema("weighted_sum", OutputSchema::Int(8))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Circuit 'weighted_sum' has 16 output bits, but i8 has 8"
+    );
+    assert!(registry
+        .set_output_schema("missing", OutputSchema::Bool)
+        .is_err());
+
+    // replacing the circuit drops its schema
+    let (other, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    registry.register("weighted_sum", other);
+    assert_eq!(registry.output_schema(&circuit), OutputSchema::Uint(16));
+}
+
+#[test]
+fn test_infos_are_sorted() {
+    let mut registry = CircuitRegistry::new();
+    let (circuit, _, _) = weighted_sum(0_u16, 0_u16);
+    registry.register("weighted_sum", circuit);
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    registry.register("multi_arithmetic", circuit.clone());
+
+    // the same circuit may be registered under several ids
+    registry.register("arithmetic", circuit);
+
+    let ids: Vec<String> = registry.infos().into_iter().map(|info| info.id).collect();
+    assert_eq!(ids, ["arithmetic", "multi_arithmetic", "weighted_sum"]);
+    assert_eq!(registry.len(), 3);
+}
+
+#[test]
+fn test_load_dir() {
+    let dir = TempDir::new("registry-load-dir");
+    let (arithmetic, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let (weighted_sum, _, _) = weighted_sum(0_u16, 0_u16);
+    fs::write(
+        dir.join("multi_arithmetic.circuit"),
+        serialize_circuit(&arithmetic).unwrap(),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("weighted_sum.circuit"),
+        serialize_circuit(&weighted_sum).unwrap(),
+    )
+    .unwrap();
+    // files with another extension are ignored
+    fs::write(dir.join("notes.txt"), "not a circuit").unwrap();
+
+    let mut registry = CircuitRegistry::new();
+    let ids = registry.load_dir(&dir).unwrap();
+    assert_eq!(ids, ["multi_arithmetic", "weighted_sum"]);
+    assert_eq!(
+        registry.get("multi_arithmetic").unwrap().blake3_hash(),
+        arithmetic.blake3_hash()
+    );
+    assert!(registry.get_by_hash(&weighted_sum.blake3_hash()).is_some());
+}
+
+#[test]
+fn test_load_invalid_files() {
+    let dir = TempDir::new("registry-invalid");
+    fs::write(dir.join("broken.circuit"), [1, 2, 3]).unwrap();
+
+    let mut registry = CircuitRegistry::new();
+    let error = registry.load_dir(&dir).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Failed to deserialize circuit file"));
+    assert!(registry
+        .load_file("missing", dir.join("missing.circuit"))
+        .is_err());
+    assert!(registry.is_empty());
+
+    // files above the size limit are refused
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let path = dir.join("large.circuit");
+    fs::write(&path, serialize_circuit(&circuit).unwrap()).unwrap();
+    registry.set_max_gates(circuit.gates().len() - 1);
+    let error = registry.load_file("large", &path).unwrap_err();
+    assert!(format!("{:#}", error).contains("at most"));
+    registry.set_max_gates(circuit.gates().len());
+    registry.load_file("large", &path).unwrap();
+}
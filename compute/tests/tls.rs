@@ -0,0 +1,154 @@
+mod common;
+
+use common::TempDir;
+use compute::prelude::*;
+use compute::transport::quic;
+use compute::transport::tls::*;
+use quinn::Endpoint;
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+fn identity(name: &str) -> Identity {
+    let (certificate, key) = quic::self_signed_certificate(vec![name.into()]).unwrap();
+    Identity {
+        cert_chain: vec![certificate],
+        key,
+    }
+}
+
+// Starts a server that answers every message with the same message, allowing only `allowed`
+fn echo_server(server: &Identity, allowed: &[Fingerprint]) -> Endpoint {
+    let verifier = FingerprintVerifier::new(allowed.iter().copied());
+    let endpoint = quic::server_endpoint_with_client_auth(
+        "127.0.0.1:0".parse().unwrap(),
+        server.cert_chain.clone(),
+        server.key.clone_key(),
+        Arc::new(verifier),
+        &quic::QuicConfig::default(),
+    )
+    .unwrap();
+
+    let accepting = endpoint.clone();
+    tokio::spawn(async move {
+        while let Some(incoming) = accepting.accept().await {
+            tokio::spawn(async move {
+                let Ok(connection) = incoming.await else {
+                    return;
+                };
+                while let Ok(mut transport) = QuicTransport::accept(&connection).await {
+                    let Ok((kind, message)) = transport.recv_message().await else {
+                        return;
+                    };
+                    let _ = transport.send_message(kind, &message).await;
+                    let _ = transport.finish().await;
+                }
+            });
+        }
+    });
+    endpoint
+}
+
+async fn echo(client: &Endpoint, address: SocketAddr) -> anyhow::Result<Vec<u8>> {
+    let connection = client.connect(address, "localhost")?.await?;
+    let mut transport = QuicTransport::open(&connection).await?;
+    transport
+        .send_message(MessageKind::Handshake, b"hello")
+        .await?;
+    let reply = transport.recv_kind(MessageKind::Handshake).await;
+    connection.close(0_u32.into(), b"done");
+    reply
+}
+
+#[test]
+fn test_parse_fingerprint() {
+    let fingerprint: Fingerprint = core::array::from_fn(|i| i as u8);
+    let text = hex::encode(fingerprint);
+    assert_eq!(parse_fingerprint(&text).unwrap(), fingerprint);
+    assert_eq!(
+        parse_fingerprint(&text.to_uppercase()).unwrap(),
+        fingerprint
+    );
+
+    let separated: Vec<String> = fingerprint
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(
+        parse_fingerprint(&separated.join(":")).unwrap(),
+        fingerprint
+    );
+
+    assert!(parse_fingerprint("not hex").is_err());
+    let error = parse_fingerprint("abcd").unwrap_err();
+    assert!(error.to_string().contains("expected 32 bytes, found 2"));
+}
+
+#[test]
+fn test_public_key_fingerprint() {
+    let identity = identity("localhost");
+    let fingerprint = identity.fingerprint().unwrap();
+    assert_eq!(
+        public_key_fingerprint(&identity.cert_chain[0]).unwrap(),
+        fingerprint
+    );
+    assert_ne!(
+        self::identity("localhost").fingerprint().unwrap(),
+        fingerprint
+    );
+    assert!(public_key_fingerprint(&vec![1, 2, 3].into()).is_err());
+}
+
+#[test]
+fn test_identity_generation() {
+    let dir = TempDir::new("tls-generate");
+    let (cert, key) = (dir.join("certs/cert.pem"), dir.join("certs/key.pem"));
+
+    // generated on first use, then loaded
+    let (first, generated) =
+        Identity::load_or_generate(&cert, &key, vec!["localhost".into()]).unwrap();
+    assert!(generated);
+    let (second, generated) =
+        Identity::load_or_generate(&cert, &key, vec!["localhost".into()]).unwrap();
+    assert!(!generated);
+    assert_eq!(first.fingerprint().unwrap(), second.fingerprint().unwrap());
+    assert_eq!(
+        Identity::load(&cert, &key).unwrap().fingerprint().unwrap(),
+        first.fingerprint().unwrap()
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&key).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // a lone certificate is not replaced
+    fs::remove_file(&key).unwrap();
+    let error = Identity::load_or_generate(&cert, &key, vec!["localhost".into()]).unwrap_err();
+    assert!(error.to_string().contains("has no private key"));
+}
+
+#[tokio::test]
+async fn test_mutual_tls() {
+    let server_identity = identity("localhost");
+    let allowed = identity("allowed");
+    let other = identity("other");
+
+    let server = echo_server(&server_identity, &[allowed.fingerprint().unwrap()]);
+    let address = server.local_addr().unwrap();
+    let roots = server_identity.cert_chain.clone();
+    let config = quic::QuicConfig::default();
+
+    let client = quic::client_endpoint_with_identity(&roots, allowed, &config).unwrap();
+    assert_eq!(echo(&client, address).await.unwrap(), b"hello");
+
+    // a certificate that is not on the allowlist
+    let client = quic::client_endpoint_with_identity(&roots, other, &config).unwrap();
+    assert!(echo(&client, address).await.is_err());
+
+    // no certificate at all
+    let client = quic::client_endpoint_with_config(&roots, &config).unwrap();
+    assert!(echo(&client, address).await.is_err());
+}
@@ -0,0 +1,285 @@
+mod common;
+
+use bytes::BytesMut;
+use common::split_arithmetic;
+use compute::prelude::*;
+use compute::transport::{codec, quic, Frame, FrameCodec, FrameError};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+// Runs both parties over the given transports and returns the evaluator's output
+async fn run_parties<G, E>(garbler_transport: G, evaluator_transport: E) -> u8
+where
+    G: Transport + 'static,
+    E: Transport,
+{
+    let (circuit, inputs, eval_inputs) = split_arithmetic(6_u8, 7_u8, 1_u8, 2_u8);
+
+    let garbler_circuit = circuit.clone();
+    let garbler =
+        tokio::spawn(
+            async move { run_garbler(garbler_transport, &garbler_circuit, &inputs).await },
+        );
+    let output = run_evaluator(evaluator_transport, &circuit, &eval_inputs)
+        .await
+        .unwrap();
+    garbler.await.unwrap().unwrap();
+
+    GarbledUint8::new(output).into()
+}
+
+fn encode(kind: MessageKind, session: u64, payload: &[u8]) -> BytesMut {
+    let mut buffer = BytesMut::new();
+    FrameCodec::default()
+        .encode(&Frame::new(kind, session, payload.to_vec()), &mut buffer)
+        .unwrap();
+    buffer
+}
+
+#[test]
+fn test_codec_coalesced_frames() {
+    let codec = FrameCodec::default();
+    let mut buffer = encode(MessageKind::Protocol, 7, &[1, 2, 3]);
+    buffer.extend_from_slice(&encode(MessageKind::Output, 7, &[]));
+    buffer.extend_from_slice(&encode(MessageKind::Protocol, 7, &[4; 100]));
+
+    let frames: Vec<Frame> = std::iter::from_fn(|| codec.decode(&mut buffer).unwrap()).collect();
+    assert_eq!(
+        frames,
+        vec![
+            Frame::new(MessageKind::Protocol, 7, vec![1, 2, 3]),
+            Frame::new(MessageKind::Output, 7, vec![]),
+            Frame::new(MessageKind::Protocol, 7, vec![4; 100]),
+        ]
+    );
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_codec_split_frame() {
+    let codec = FrameCodec::default();
+    let encoded = encode(MessageKind::Protocol, 1, &[9; 20]);
+
+    // feed the frame one byte at a time, it only decodes once the last byte arrived
+    let mut buffer = BytesMut::new();
+    for (i, byte) in encoded.iter().enumerate() {
+        buffer.extend_from_slice(&[*byte]);
+        let decoded = codec.decode(&mut buffer).unwrap();
+        assert_eq!(decoded.is_some(), i == encoded.len() - 1);
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_codec_rejects_invalid_headers() {
+    let codec = FrameCodec::new(16);
+
+    // the size is checked from the header alone, before the payload arrives
+    let mut buffer = encode(MessageKind::Protocol, 0, &[0; 17]);
+    buffer.truncate(codec::HEADER_SIZE);
+    assert!(matches!(
+        codec.decode(&mut buffer),
+        Err(FrameError::FrameTooLarge { size: 17, max: 16 })
+    ));
+    assert!(matches!(
+        codec.encode(
+            &Frame::new(MessageKind::Protocol, 0, vec![0; 17]),
+            &mut BytesMut::new()
+        ),
+        Err(FrameError::FrameTooLarge { .. })
+    ));
+
+    let mut buffer = encode(MessageKind::Protocol, 0, &[]);
+    buffer[4] = codec::PROTOCOL_VERSION + 1;
+    assert!(matches!(
+        codec.decode(&mut buffer),
+        Err(FrameError::UnsupportedVersion(_))
+    ));
+
+    let mut buffer = encode(MessageKind::Protocol, 0, &[]);
+    buffer[5] = 0xff;
+    assert!(matches!(
+        codec.decode(&mut buffer),
+        Err(FrameError::UnknownMessageKind(0xff))
+    ));
+}
+
+#[tokio::test]
+async fn test_stream_transport_errors() {
+    // a clean close between frames
+    let (writer, reader) = tokio::io::duplex(64);
+    drop(writer);
+    let error = StreamTransport::new(reader).recv().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<FrameError>(),
+        Some(FrameError::ConnectionClosed)
+    ));
+
+    // a close in the middle of a frame
+    let (mut writer, reader) = tokio::io::duplex(64);
+    let encoded = encode(MessageKind::Protocol, 0, &[1, 2, 3]);
+    writer
+        .write_all(&encoded[..encoded.len() - 1])
+        .await
+        .unwrap();
+    drop(writer);
+    let error = StreamTransport::new(reader).recv().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<FrameError>(),
+        Some(FrameError::Truncated { buffered: 16 })
+    ));
+
+    // a frame of another session
+    let (a, b) = tokio::io::duplex(64);
+    let mut sender = StreamTransport::new(a).with_session(1);
+    let mut receiver = StreamTransport::new(b).with_session(2);
+    sender.send(&[1]).await.unwrap();
+    let error = receiver.recv().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<FrameError>(),
+        Some(FrameError::SessionMismatch {
+            expected: 2,
+            received: 1
+        })
+    ));
+
+    // a frame of another kind
+    let (mut sender, mut receiver) = MemoryTransport::pair();
+    sender
+        .send_message(MessageKind::Output, &[1])
+        .await
+        .unwrap();
+    let error = receiver.recv().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<FrameError>(),
+        Some(FrameError::UnexpectedMessage { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_stream_transport_coalesced_writes() {
+    let (mut writer, reader) = tokio::io::duplex(1024);
+    let mut buffer = encode(MessageKind::Protocol, 3, &[1, 2]);
+    buffer.extend_from_slice(&encode(MessageKind::Output, 3, &[3]));
+    writer.write_all(&buffer).await.unwrap();
+
+    let mut transport = StreamTransport::new(reader).with_session(3);
+    assert_eq!(transport.recv().await.unwrap(), vec![1, 2]);
+    assert_eq!(
+        transport.recv_kind(MessageKind::Output).await.unwrap(),
+        vec![3]
+    );
+}
+
+#[tokio::test]
+async fn test_channel_transport() {
+    let (garbler, evaluator) = ChannelTransport::pair();
+    assert_eq!(run_parties(garbler, evaluator).await, 41);
+}
+
+#[tokio::test]
+async fn test_memory_transport() {
+    let (garbler, evaluator) = MemoryTransport::pair();
+    assert_eq!(run_parties(garbler, evaluator).await, 41);
+}
+
+#[tokio::test]
+async fn test_tcp_transport() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let garbler = TcpTransport::connect(address).await.unwrap();
+    let (evaluator, _) = TcpTransport::accept(&listener).await.unwrap();
+    assert_eq!(run_parties(garbler, evaluator).await, 41);
+}
+
+#[tokio::test]
+async fn test_quic_transport() {
+    let (certificate, key) = quic::self_signed_certificate(vec!["localhost".into()]).unwrap();
+    let server = quic::server_endpoint(
+        "127.0.0.1:0".parse().unwrap(),
+        vec![certificate.clone()],
+        key,
+    )
+    .unwrap();
+    let address = server.local_addr().unwrap();
+    let client = quic::client_endpoint(&[certificate]).unwrap();
+
+    let connect = async {
+        let connection = client.connect(address, "localhost").unwrap().await.unwrap();
+        let transport = QuicTransport::open(&connection).await.unwrap();
+        (connection, transport)
+    };
+    let accept = async { server.accept().await.unwrap().await.unwrap() };
+    let ((client_connection, garbler), server_connection) = tokio::join!(connect, accept);
+
+    // the stream only reaches the server with the garbler's first message
+    let (circuit, inputs, eval_inputs) = split_arithmetic(6_u8, 7_u8, 1_u8, 2_u8);
+    let garbler_circuit = circuit.clone();
+    let garbler = tokio::spawn(async move {
+        let mut garbler = garbler;
+        run_garbler(&mut garbler, &garbler_circuit, &inputs).await?;
+        garbler.recv_kind(MessageKind::Output).await
+    });
+
+    let mut evaluator = QuicTransport::accept(&server_connection).await.unwrap();
+    let output = run_evaluator(&mut evaluator, &circuit, &eval_inputs)
+        .await
+        .unwrap();
+    let output: Vec<u8> = output.into_iter().map(u8::from).collect();
+    evaluator
+        .send_message(MessageKind::Output, &output)
+        .await
+        .unwrap();
+    evaluator.finish().await.unwrap();
+
+    assert_eq!(garbler.await.unwrap().unwrap(), output);
+    let result: u8 = GarbledUint8::new(output.iter().map(|bit| *bit == 1).collect()).into();
+    assert_eq!(result, 41);
+
+    client_connection.close(0_u32.into(), b"done");
+    client.wait_idle().await;
+}
+
+#[tokio::test]
+async fn test_error_frames() {
+    let (mut a, mut b) = MemoryTransport::pair();
+
+    // a reported error fails the other party's next receive
+    let error = anyhow::Error::from(SessionError::Timeout(std::time::Duration::from_secs(5)));
+    assert_eq!(
+        report_error(&mut a, &error).await,
+        Some(SessionError::Timeout(std::time::Duration::from_secs(5)))
+    );
+    let received = b.recv().await.unwrap_err();
+    assert_eq!(
+        received.downcast_ref::<PeerError>(),
+        Some(&PeerError(SessionError::Timeout(
+            std::time::Duration::from_secs(5)
+        )))
+    );
+
+    // errors from the other party are not reported back
+    assert_eq!(report_error(&mut b, &received).await, None);
+
+    // invalid messages are reported as such
+    let error = anyhow::Error::from(FrameError::UnexpectedMessage {
+        expected: MessageKind::Handshake,
+        received: MessageKind::Protocol,
+    });
+    assert_eq!(
+        report_error(&mut a, &error).await,
+        Some(SessionError::InvalidMessage)
+    );
+    assert!(b.recv_message().await.unwrap().0 == MessageKind::Error);
+
+    // an error frame that does not hold a `SessionError`
+    a.send_message(MessageKind::Error, &[0xff; 3])
+        .await
+        .unwrap();
+    let error = b.recv().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<FrameError>(),
+        Some(FrameError::InvalidErrorFrame(_))
+    ));
+}
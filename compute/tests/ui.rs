@@ -0,0 +1,7 @@
+// Unsupported syntax in #[encrypted] functions is reported as a compile error pointing at the
+// offending code. Run with TRYBUILD=overwrite to update the expected output.
+#[test]
+fn test_macro_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
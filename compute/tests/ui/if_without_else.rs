@@ -0,0 +1,12 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn larger(a: u8, b: u8) -> u8 {
+    let mut result = a;
+    if b > a {
+        result = b;
+    }
+    result
+}
+
+fn main() {}
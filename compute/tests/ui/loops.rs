@@ -0,0 +1,19 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn halve_until_small(a: u8) -> u8 {
+    let mut value = a;
+    while value > 10 {
+        value = value / 2;
+    }
+    value
+}
+
+#[encrypted(execute)]
+fn spin(a: u8) -> u8 {
+    loop {
+        a
+    }
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn level(age: u8) -> u8 {
+    match age {
+        1..=17 => 1,
+        adult @ 18..=65 => adult,
+        _ => 3,
+    }
+}
+
+#[encrypted(execute)]
+fn senior(age: u8) -> u8 {
+    match age {
+        x if x > 65 => 1,
+        _ => 0,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn sum(a: u8, b: u8) {
+    a + b;
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn sum(a: u8, b: u8) -> u8 {
+    let total = a + b;
+    return total;
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use compute::prelude::*;
+
+#[encrypted(execute, limit = 16)]
+fn sum(a: u8, b: u8) -> u8 {
+    a + b
+}
+
+#[encrypted(run)]
+fn difference(a: u8, b: u8) -> u8 {
+    a - b
+}
+
+fn main() {}
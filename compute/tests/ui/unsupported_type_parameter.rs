@@ -0,0 +1,14 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn larger(a: T, b: T) -> T {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+fn main() {
+    larger(String::from("a"), String::from("b"));
+}
@@ -1,39 +1,49 @@
 // src/bin/client.rs
 use compute::prelude::*;
-use s2n_quic::{client::Connect, Client};
-use server::util::extract;
-use server::util::prepare;
-use std::{error::Error, net::SocketAddr, path::Path};
-use tracing::debug;
-use tracing::info;
-
-#[encrypted(compile)]
-fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> u8 {
-    let res = a * b;
-    let res = res + c;
-    res - d
-}
+use compute::transport::quic;
+use compute::transport::tls::Identity;
+use server::circuits::multi_arithmetic;
+use server::config::ClientConfig;
+use std::error::Error;
+use tracing::{debug, info};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // Initialize tracing subscriber for logging
-    tracing_subscriber::fmt::init();
-
-    let client = Client::builder()
-        .with_tls(Path::new("server/certs/cert.pem"))?
-        .with_io("0.0.0.0:0")?
-        .start()?;
+    let config = ClientConfig::load()?;
 
-    let addr: SocketAddr = "127.0.0.1:4433".parse()?;
-    let connect = Connect::new(addr).with_server_name("localhost");
-    let mut connection = client.connect(connect).await?;
+    // Initialize tracing subscriber for logging
+    tracing_subscriber::fmt()
+        .with_max_level(config.log_level)
+        .init();
+    debug!("Configuration: {:?}", config);
+
+    let roots = quic::load_certificates(&config.ca_cert)?;
+    let client = match &config.identity {
+        Some((cert, key)) => {
+            let identity = if config.generate_cert {
+                let names = vec!["gvm-client".to_string()];
+                let (identity, generated) = Identity::load_or_generate(cert, key, names)?;
+                if generated {
+                    info!("Generated a self-signed certificate in {}", cert.display());
+                }
+                identity
+            } else {
+                Identity::load(cert, key)?
+            };
+            // the server operator allows this client by the fingerprint of its key
+            info!(
+                "Client certificate fingerprint: {}",
+                hex::encode(identity.fingerprint()?)
+            );
+            quic::client_endpoint_with_identity(&roots, identity, &config.quic)?
+        }
+        None => quic::client_endpoint_with_config(&roots, &config.quic)?,
+    };
 
-    // Ensure the connection doesn't time out with inactivity
-    connection.keep_alive(true)?;
+    let connection = client.connect(config.server, &config.server_name)?.await?;
 
-    // Open a new stream and split the receiving and sending sides
-    let mut stream = connection.open_bidirectional_stream().await?;
-    //let (mut receive_stream, mut send_stream) = stream.split();
+    // Open a new bidirectional stream for the protocol messages
+    let mut transport = QuicTransport::open(&connection).await?;
 
     // Initialize garbler with sample data
     let a = 2_u8;
@@ -41,77 +51,39 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let c = 3_u8;
     let d = 4_u8;
     //let circuit = Circuit::default();
-    let (circuit, input_garbler) = multi_arithmetic(a, b, c, d);
+    let (circuit, input_garbler, _) = multi_arithmetic(a, b, c, d);
     info!("Circuit: {:?}", hex::encode(circuit.blake3_hash()));
 
-    let (mut garbler, mut msg_for_evaluator) = GatewayGarbler::start(&circuit, &input_garbler)?;
-
-    // Send initial message from garbler to evaluator
-    stream
-        .send(prepare(msg_for_evaluator.clone()).into())
-        .await?;
-    //stream.flush().await?;
-
-    info!(
-        "Message for evaluator, length: {:?}",
-        msg_for_evaluator.len()
-    );
-
-    while let Some(first_chunk) = stream.receive().await? {
-        let (size, mut data) = extract(&first_chunk)?;
-        info!("size from header: {:?}", size);
-
-        // Continue receiving until the full message is obtained
-        while data.len() < size as usize {
-            let chunk = stream.receive().await?.expect("no data received");
-            data.extend_from_slice(&chunk);
-        }
-
-        info!("Received message, length: {:?}", data.len());
-        debug!("Received data: {:?}", hex::encode(&data));
-
-        if garbler.is_complete() {
-            // Receive the final output from the evaluator
-            //let final_output = stream.receive().await?.expect("no data received");
-            info!("Final output received: {:?}", hex::encode(&data));
-            fn bits_to_u128(mut bits: Vec<u8>) -> u128 {
-                bits.reverse();
-                // Ensure we use at most 128 bits (truncate if necessary)
-                let bit_length = bits.len().min(128);
-                let mut result: u128 = 0;
-
-                // Iterate over the bits, shifting and accumulating them into the result
-                for &bit in &bits[..bit_length] {
-                    result = (result << 1) | u128::from(bit);
-                }
-
-                result
-            }
-
-            println!("The resulting number: {}", bits_to_u128(data));
-            break;
-        }
-
-        // Pass evaluator response to garbler "next" function
-        let (next_garbler, next_message) = garbler.next(&data)?;
-        info!("Steps remaining: {}", next_garbler.steps());
-        garbler = next_garbler;
+    for circuit in list_circuits(&mut transport).await? {
+        info!(
+            "Server hosts '{}' (garbler inputs {:?}, evaluator inputs {:?}, output {}, {} AND gates)",
+            circuit.id,
+            circuit.inputs.garbler,
+            circuit.inputs.evaluator,
+            circuit.output,
+            circuit.and_gates
+        );
+    }
 
-        info!("Sending message, length: {:?}", next_message.len());
-        // Send the next message back to evaluator
-        stream.send(prepare(next_message.clone()).into()).await?;
-        //stream.flush().await?;
+    // Make sure the server runs the same circuit before garbling it
+    propose_circuit(
+        &mut transport,
+        &CircuitProposal::named("multi_arithmetic", &circuit),
+    )
+    .await?;
 
-        debug!(
-            "Sent message for garbler: {:?}",
-            hex::encode(msg_for_evaluator.clone())
-        );
+    // Run the garbler's side of the protocol, then receive the output from the evaluator
+    run_garbler(&mut transport, &circuit, &input_garbler).await?;
 
-        msg_for_evaluator = next_message;
-    }
+    let output = recv_output(&mut transport).await?;
+    info!("Final output received: {}", output.schema());
+    let result: GarbledUint8 = output.decode()?;
 
-    debug!("last message: {:?}", msg_for_evaluator);
+    println!("The resulting number: {}", result);
     println!("Garbler has completed the interaction.");
 
+    connection.close(0_u32.into(), b"done");
+    client.wait_idle().await;
+
     Ok(())
 }
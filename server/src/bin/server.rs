@@ -1,52 +1,143 @@
 use compute::prelude::*;
-use s2n_quic::Server;
-use server::util::extract;
-use server::util::prepare;
-use std::{error::Error, path::Path};
-use tracing::{debug, error, info, instrument};
-
-#[encrypted(compile)]
-fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> u8 {
-    let res = a * b;
-    let res = res + c;
-    res - d
-}
+use compute::transport::quic;
+use compute::transport::tls::{FingerprintVerifier, Identity};
+use quinn::Connection;
+use server::circuits;
+use server::config::ServerConfig;
+use server::metrics::{SessionMetrics, SessionOutcome};
+use server::session::serve_session;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{debug, error, info, instrument, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let config = ServerConfig::load()?;
+
     // Initialize tracing subscriber for logging
-    tracing_subscriber::fmt::init();
+    tracing_subscriber::fmt()
+        .with_max_level(config.log_level)
+        .init();
+    debug!("Configuration: {:?}", config);
+
+    // Register the circuits garblers may run; the evaluator has no inputs of its own
+    let mut registry = CircuitRegistry::new();
+    registry.set_max_gates(config.max_circuit_gates);
+    circuits::register_builtin(&mut registry)?;
+    if config.circuit_dir.is_dir() {
+        let ids = registry.load_dir(&config.circuit_dir)?;
+        info!(
+            "Loaded {} circuits from {}",
+            ids.len(),
+            config.circuit_dir.display()
+        );
+    } else {
+        warn!(
+            "Circuit directory {} not found, hosting only the built-in circuits",
+            config.circuit_dir.display()
+        );
+    }
+    for circuit in registry.infos() {
+        info!(
+            id = %circuit.id,
+            hash = %hex::encode(circuit.hash),
+            garbler_inputs = ?circuit.inputs.garbler,
+            evaluator_inputs = ?circuit.inputs.evaluator,
+            output = %circuit.output,
+            and_gates = circuit.and_gates,
+            "Registered circuit"
+        );
+    }
+    let registry = Arc::new(registry);
 
     // Setup server
-    let mut server = Server::builder()
-        .with_tls((
-            Path::new("server/certs/cert.pem"),
-            Path::new("server/certs/key.pem"),
-        ))?
-        .with_io("127.0.0.1:4433")?
-        .start()?;
-
-    info!("Server started and listening on 127.0.0.1:4433");
-
-    while let Some(mut connection) = server.accept().await {
-        info!("Accepted new connection");
-
+    let identity = if config.generate_cert {
+        let names = vec!["localhost".to_string(), config.listen.ip().to_string()];
+        let (identity, generated) = Identity::load_or_generate(&config.cert, &config.key, names)?;
+        if generated {
+            info!(
+                "Generated a self-signed certificate in {}",
+                config.cert.display()
+            );
+        }
+        identity
+    } else {
+        Identity::load(&config.cert, &config.key)?
+    };
+    info!(
+        "Server certificate fingerprint: {}",
+        hex::encode(identity.fingerprint()?)
+    );
+
+    let server = if config.allowed_clients.is_empty() {
+        quic::server_endpoint_with_config(
+            config.listen,
+            identity.cert_chain,
+            identity.key,
+            &config.quic,
+        )?
+    } else {
+        info!(
+            "Requiring client certificates from {} allowed clients",
+            config.allowed_clients.len()
+        );
+        let verifier = FingerprintVerifier::new(config.allowed_clients.iter().copied());
+        quic::server_endpoint_with_client_auth(
+            config.listen,
+            identity.cert_chain,
+            identity.key,
+            Arc::new(verifier),
+            &config.quic,
+        )?
+    };
+
+    info!("Server started and listening on {}", config.listen);
+
+    // Each session holds a slot while it runs, so at most `max_sessions` run at the same time
+    let sessions = Arc::new(Semaphore::new(config.max_sessions));
+    let metrics = Arc::new(SessionMetrics::new());
+    let session_timeout = config.session_timeout;
+
+    while let Some(incoming) = server.accept().await {
         // Spawn a new task for the connection
+        let registry = registry.clone();
+        let sessions = sessions.clone();
+        let metrics = metrics.clone();
         tokio::spawn(async move {
-            while let Ok(Some(stream)) = connection.accept_bidirectional_stream().await {
-                info!("Accepted bidirectional stream");
-
-                // Initialize the evaluator instance with circuit and dummy input
-                let (circuit, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
-
-                info!("Circuit: {:?}", hex::encode(circuit.blake3_hash()));
+            let connection = match incoming.await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!("Error accepting connection: {:?}", e);
+                    return;
+                }
+            };
+            info!("Accepted new connection");
 
-                let evaluator =
-                    GatewayEvaluator::new(&circuit, &[]).expect("Evaluator initialization failed");
+            while let Ok(transport) = QuicTransport::accept(&connection).await {
+                info!("Accepted bidirectional stream");
 
-                if let Err(e) = handle_evaluator_connection(evaluator, stream).await {
-                    error!("Error handling evaluator connection: {:?}", e);
+                if sessions.available_permits() == 0 {
+                    warn!("All session slots are taken, waiting for a free one");
                 }
+                let Ok(slot) = sessions.clone().acquire_owned().await else {
+                    return;
+                };
+                let registry = registry.clone();
+                let metrics = metrics.clone();
+                let connection = connection.clone();
+                tokio::spawn(async move {
+                    handle_evaluator_connection(
+                        &registry,
+                        transport,
+                        connection,
+                        session_timeout,
+                        &metrics,
+                    )
+                    .await;
+                    drop(slot);
+                });
             }
         });
     }
@@ -54,51 +145,32 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-#[instrument(skip_all, fields(evaluator, stream))]
+#[instrument(skip_all)]
 async fn handle_evaluator_connection(
-    mut evaluator: GatewayEvaluator,
-    mut stream: s2n_quic::stream::BidirectionalStream,
-) -> Result<(), Box<dyn Error>> {
+    registry: &CircuitRegistry,
+    mut transport: QuicTransport,
+    connection: Connection,
+    session_timeout: Duration,
+    metrics: &SessionMetrics,
+) {
     info!("Starting evaluator connection handler");
 
-    while let Some(first_chunk) = stream.receive().await? {
-        let (size, mut data) = extract(&first_chunk)?;
-        info!("size from header: {:?}", size);
+    // The session is cancelled as soon as the garbler's connection goes away
+    let closed = async move {
+        connection.closed().await;
+    };
+    let result = serve_session(&mut transport, registry, session_timeout, closed, metrics).await;
+    // Deliver the output or the error frame before the stream closes
+    if let Err(e) = transport.finish().await {
+        debug!("Error finishing the stream: {:?}", e);
+    }
 
-        // Continue receiving until the full message is obtained
-        while data.len() < size as usize {
-            let chunk = stream.receive().await?.expect("no data received");
-            data.extend_from_slice(&chunk);
+    match (&result, SessionOutcome::of(&result)) {
+        (Ok(()), _) => info!("Final output sent to client, closing connection"),
+        (Err(e), SessionOutcome::Rejected) => info!("Circuit rejected: {}", e),
+        (Err(e), outcome) => {
+            error!("Session aborted ({:?}): {:#}", outcome, e);
+            warn!("Sessions: {}", metrics.snapshot());
         }
-
-        info!("Received data, length: {:?}", data.len());
-        debug!("Received data: {:?}", hex::encode(&data));
-
-        // Check if the evaluator has completed all steps
-        if evaluator.is_complete() {
-            info!("Evaluator processing complete, preparing output");
-            let output_message = evaluator.output(&data)?;
-            let mut output_data = Vec::new();
-            for bit in output_message {
-                output_data.push(if bit { 1 } else { 0 });
-            }
-            stream.send(prepare(output_data).into()).await?;
-            info!("Final output sent to client, closing connection");
-            break;
-        }
-
-        // Process the evaluator "next" step with received data
-        let (next_evaluator, response) = evaluator.next(&data)?;
-        info!("Steps remaining: {}", next_evaluator.steps());
-        evaluator = next_evaluator;
-
-        // Send back the response
-        info!("Sending data, length: {:?}", response.len());
-        debug!("Sending data: {:?}", hex::encode(&response));
-        stream.send(prepare(response).into()).await?;
-        info!("Sent response back to client");
     }
-
-    debug!("Evaluator connection handler complete");
-    Ok(())
 }
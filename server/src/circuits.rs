@@ -0,0 +1,31 @@
+//! Encrypted functions the evaluator server hosts out of the box.
+//!
+//! The client compiles the same functions to garble them, so both sides agree on the circuits.
+
+use anyhow::Result;
+use compute::prelude::*;
+
+#[encrypted(compile)]
+pub fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> u8 {
+    let res = a * b;
+    let res = res + c;
+    res - d
+}
+
+#[encrypted(compile)]
+pub fn weighted_sum(a: u16, b: u16, weight: u16) -> u16 {
+    a * weight + b
+}
+
+/// Registers every built-in circuit under its function name, with the widths of its parameters and
+/// the output schema of its return type.
+pub fn register_builtin(registry: &mut CircuitRegistry) -> Result<()> {
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    registry.register("multi_arithmetic", circuit);
+    registry.set_input_widths("multi_arithmetic", InputWidths::new([8; 4], []))?;
+    registry.set_output_schema("multi_arithmetic", OutputSchema::Uint(8))?;
+    let (circuit, _, _) = weighted_sum(0_u16, 0_u16, 0_u16);
+    registry.register("weighted_sum", circuit);
+    registry.set_input_widths("weighted_sum", InputWidths::new([16; 3], []))?;
+    registry.set_output_schema("weighted_sum", OutputSchema::Uint(16))
+}
@@ -0,0 +1,342 @@
+//! Settings of the server and client binaries.
+//!
+//! Every setting can be given as a command line flag, as an environment variable or in a TOML file
+//! passed with `--config`, in that order of precedence. The file has a `[server]` and a `[client]`
+//! table, so both binaries can share one. Relative paths in the file are resolved against the
+//! directory of the file, and the default paths point into this crate instead of the working
+//! directory.
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser};
+use compute::transport::quic::{QuicConfig, DEFAULT_IDLE_TIMEOUT};
+use compute::transport::tls::{parse_fingerprint, Fingerprint};
+use serde::Deserialize;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::level_filters::LevelFilter;
+
+/// Directory of this crate, which holds the development certificates and circuits.
+const CRATE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+pub const DEFAULT_PORT: u16 = 4433;
+pub const DEFAULT_MAX_SESSIONS: usize = 64;
+pub const DEFAULT_MAX_CIRCUIT_GATES: usize = 1 << 24;
+pub const DEFAULT_SERVER_NAME: &str = "localhost";
+pub const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(300);
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::INFO;
+
+fn default_address() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT))
+}
+
+fn crate_path(path: &str) -> PathBuf {
+    Path::new(CRATE_DIR).join(path)
+}
+
+/// The contents of a configuration file.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub server: ServerOptions,
+    pub client: ClientOptions,
+}
+
+impl ConfigFile {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut file: ConfigFile = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for path in [
+            &mut file.server.cert,
+            &mut file.server.key,
+            &mut file.server.circuit_dir,
+            &mut file.client.ca_cert,
+            &mut file.client.cert,
+            &mut file.client.key,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = base.join(&*path);
+        }
+        Ok(file)
+    }
+}
+
+/// Command line of the server binary.
+#[derive(Parser, Debug)]
+#[command(
+    name = "server",
+    about = "Evaluates encrypted functions for garbling clients"
+)]
+pub struct ServerCli {
+    /// TOML file whose [server] table holds the settings not given as flags.
+    #[arg(long, env = "GVM_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[command(flatten)]
+    pub options: ServerOptions,
+}
+
+/// Server settings as given by the user; missing ones fall back to the defaults.
+#[derive(Args, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerOptions {
+    /// Address to accept QUIC connections on [default: 127.0.0.1:4433].
+    #[arg(long, env = "GVM_LISTEN")]
+    pub listen: Option<SocketAddr>,
+    /// PEM file with the certificate chain of the server.
+    #[arg(long, env = "GVM_CERT")]
+    pub cert: Option<PathBuf>,
+    /// PEM file with the private key of the server.
+    #[arg(long, env = "GVM_KEY")]
+    pub key: Option<PathBuf>,
+    /// Generate a self-signed certificate and key if neither file exists [default: true].
+    #[arg(long, env = "GVM_GENERATE_CERT")]
+    pub generate_cert: Option<bool>,
+    /// Public key fingerprints of the clients allowed to connect, as hex SHA-256 hashes. When
+    /// given, clients must authenticate with a certificate (mutual TLS).
+    #[arg(
+        long = "allow-client",
+        env = "GVM_ALLOWED_CLIENTS",
+        value_delimiter = ','
+    )]
+    pub allowed_clients: Option<Vec<String>>,
+    /// Directory of serialized circuits hosted next to the built-in ones.
+    #[arg(long, env = "GVM_CIRCUIT_DIR")]
+    pub circuit_dir: Option<PathBuf>,
+    /// Sessions evaluated at the same time; further sessions wait for a free slot [default: 64].
+    #[arg(long, env = "GVM_MAX_SESSIONS")]
+    pub max_sessions: Option<usize>,
+    /// Gates of the largest circuit loaded from a file or uploaded by a client [default: 16777216].
+    #[arg(long, env = "GVM_MAX_CIRCUIT_GATES")]
+    pub max_circuit_gates: Option<usize>,
+    /// Seconds without any packets after which a connection is closed, at least 1 [default: 30].
+    #[arg(long, env = "GVM_IDLE_TIMEOUT_SECS")]
+    pub idle_timeout_secs: Option<u64>,
+    /// Seconds a session may take from the handshake to the output [default: 300].
+    #[arg(long, env = "GVM_SESSION_TIMEOUT_SECS")]
+    pub session_timeout_secs: Option<u64>,
+    /// Most verbose level logged: off, error, warn, info, debug or trace [default: info].
+    #[arg(long, env = "GVM_LOG_LEVEL")]
+    pub log_level: Option<String>,
+}
+
+impl ServerOptions {
+    /// Fills the settings missing from `self` with those of `fallback`.
+    pub fn or(self, fallback: ServerOptions) -> ServerOptions {
+        ServerOptions {
+            listen: self.listen.or(fallback.listen),
+            cert: self.cert.or(fallback.cert),
+            key: self.key.or(fallback.key),
+            generate_cert: self.generate_cert.or(fallback.generate_cert),
+            allowed_clients: self.allowed_clients.or(fallback.allowed_clients),
+            circuit_dir: self.circuit_dir.or(fallback.circuit_dir),
+            max_sessions: self.max_sessions.or(fallback.max_sessions),
+            max_circuit_gates: self.max_circuit_gates.or(fallback.max_circuit_gates),
+            idle_timeout_secs: self.idle_timeout_secs.or(fallback.idle_timeout_secs),
+            session_timeout_secs: self.session_timeout_secs.or(fallback.session_timeout_secs),
+            log_level: self.log_level.or(fallback.log_level),
+        }
+    }
+}
+
+/// The settings of the server binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub listen: SocketAddr,
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub generate_cert: bool,
+    /// Fingerprints of the clients allowed to connect; any client may connect if empty.
+    pub allowed_clients: Vec<Fingerprint>,
+    pub circuit_dir: PathBuf,
+    pub max_sessions: usize,
+    pub max_circuit_gates: usize,
+    pub quic: QuicConfig,
+    pub session_timeout: Duration,
+    pub log_level: LevelFilter,
+}
+
+impl ServerConfig {
+    /// Reads the settings from the command line, the environment and the config file.
+    pub fn load() -> Result<Self> {
+        Self::from_cli(ServerCli::parse())
+    }
+
+    pub fn from_cli(cli: ServerCli) -> Result<Self> {
+        let file = match &cli.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+        Self::from_options(cli.options.or(file.server))
+    }
+
+    pub fn from_options(options: ServerOptions) -> Result<Self> {
+        let max_sessions = options.max_sessions.unwrap_or(DEFAULT_MAX_SESSIONS);
+        if max_sessions == 0 {
+            bail!("The server needs at least one session slot");
+        }
+        Ok(ServerConfig {
+            listen: options.listen.unwrap_or_else(default_address),
+            cert: options.cert.unwrap_or_else(|| crate_path("certs/cert.pem")),
+            key: options.key.unwrap_or_else(|| crate_path("certs/key.pem")),
+            generate_cert: options.generate_cert.unwrap_or(true),
+            allowed_clients: options
+                .allowed_clients
+                .unwrap_or_default()
+                .iter()
+                .map(|fingerprint| parse_fingerprint(fingerprint))
+                .collect::<Result<_>>()?,
+            circuit_dir: options
+                .circuit_dir
+                .unwrap_or_else(|| crate_path("circuits")),
+            max_sessions,
+            max_circuit_gates: options
+                .max_circuit_gates
+                .unwrap_or(DEFAULT_MAX_CIRCUIT_GATES),
+            quic: quic_config(options.idle_timeout_secs)?,
+            session_timeout: options
+                .session_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_SESSION_TIMEOUT),
+            log_level: log_level(options.log_level.as_deref())?,
+        })
+    }
+}
+
+/// Command line of the client binary.
+#[derive(Parser, Debug)]
+#[command(
+    name = "client",
+    about = "Garbles an encrypted function with an evaluator server"
+)]
+pub struct ClientCli {
+    /// TOML file whose [client] table holds the settings not given as flags.
+    #[arg(long, env = "GVM_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[command(flatten)]
+    pub options: ClientOptions,
+}
+
+/// Client settings as given by the user; missing ones fall back to the defaults.
+#[derive(Args, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientOptions {
+    /// Address of the evaluator server [default: 127.0.0.1:4433].
+    #[arg(long, env = "GVM_SERVER")]
+    pub server: Option<SocketAddr>,
+    /// Name the server certificate must be issued for [default: localhost].
+    #[arg(long, env = "GVM_SERVER_NAME")]
+    pub server_name: Option<String>,
+    /// PEM file with the certificates trusted to sign the server certificate.
+    #[arg(long, env = "GVM_CA_CERT")]
+    pub ca_cert: Option<PathBuf>,
+    /// PEM file with the client certificate, for servers that require mutual TLS.
+    #[arg(long, env = "GVM_CLIENT_CERT")]
+    pub cert: Option<PathBuf>,
+    /// PEM file with the private key of the client certificate.
+    #[arg(long, env = "GVM_CLIENT_KEY")]
+    pub key: Option<PathBuf>,
+    /// Generate a self-signed client certificate and key if neither file exists [default: true].
+    #[arg(long, env = "GVM_GENERATE_CERT")]
+    pub generate_cert: Option<bool>,
+    /// Seconds without any packets after which the connection is closed, at least 1 [default: 30].
+    #[arg(long, env = "GVM_IDLE_TIMEOUT_SECS")]
+    pub idle_timeout_secs: Option<u64>,
+    /// Most verbose level logged: off, error, warn, info, debug or trace [default: info].
+    #[arg(long, env = "GVM_LOG_LEVEL")]
+    pub log_level: Option<String>,
+}
+
+impl ClientOptions {
+    /// Fills the settings missing from `self` with those of `fallback`.
+    pub fn or(self, fallback: ClientOptions) -> ClientOptions {
+        ClientOptions {
+            server: self.server.or(fallback.server),
+            server_name: self.server_name.or(fallback.server_name),
+            ca_cert: self.ca_cert.or(fallback.ca_cert),
+            cert: self.cert.or(fallback.cert),
+            key: self.key.or(fallback.key),
+            generate_cert: self.generate_cert.or(fallback.generate_cert),
+            idle_timeout_secs: self.idle_timeout_secs.or(fallback.idle_timeout_secs),
+            log_level: self.log_level.or(fallback.log_level),
+        }
+    }
+}
+
+/// The settings of the client binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    pub server: SocketAddr,
+    pub server_name: String,
+    pub ca_cert: PathBuf,
+    /// Certificate and key files the client authenticates with, if any.
+    pub identity: Option<(PathBuf, PathBuf)>,
+    pub generate_cert: bool,
+    pub quic: QuicConfig,
+    pub log_level: LevelFilter,
+}
+
+impl ClientConfig {
+    /// Reads the settings from the command line, the environment and the config file.
+    pub fn load() -> Result<Self> {
+        Self::from_cli(ClientCli::parse())
+    }
+
+    pub fn from_cli(cli: ClientCli) -> Result<Self> {
+        let file = match &cli.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+        Self::from_options(cli.options.or(file.client))
+    }
+
+    pub fn from_options(options: ClientOptions) -> Result<Self> {
+        let identity = match (options.cert, options.key) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => bail!("The client certificate and key must be given together"),
+        };
+        Ok(ClientConfig {
+            server: options.server.unwrap_or_else(default_address),
+            server_name: options
+                .server_name
+                .unwrap_or_else(|| DEFAULT_SERVER_NAME.to_string()),
+            ca_cert: options
+                .ca_cert
+                .unwrap_or_else(|| crate_path("certs/cert.pem")),
+            identity,
+            generate_cert: options.generate_cert.unwrap_or(true),
+            quic: quic_config(options.idle_timeout_secs)?,
+            log_level: log_level(options.log_level.as_deref())?,
+        })
+    }
+}
+
+fn quic_config(idle_timeout_secs: Option<u64>) -> Result<QuicConfig> {
+    // keep-alives are sent at half the idle timeout, which would be continuously at zero
+    if idle_timeout_secs == Some(0) {
+        bail!("The idle timeout must be at least one second");
+    }
+    Ok(QuicConfig {
+        idle_timeout: idle_timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT),
+        ..QuicConfig::default()
+    })
+}
+
+fn log_level(level: Option<&str>) -> Result<LevelFilter> {
+    match level {
+        Some(level) => level
+            .parse()
+            .map_err(|_| anyhow!("Invalid log level '{}'", level)),
+        None => Ok(DEFAULT_LOG_LEVEL),
+    }
+}
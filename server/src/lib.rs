@@ -1 +1,8 @@
-pub mod util;
+//! Evaluator server and garbler client for running encrypted functions between two parties.
+//!
+//! The protocol messages are exchanged with the transports in `compute::transport`.
+
+pub mod circuits;
+pub mod config;
+pub mod metrics;
+pub mod session;
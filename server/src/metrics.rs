@@ -0,0 +1,153 @@
+//! Counters of the sessions served by the evaluator, by how they ended.
+
+use compute::handshake::HandshakeError;
+use compute::session::{PeerError, SessionError};
+use compute::transport::FrameError;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How a session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionOutcome {
+    Completed,
+    /// The garbler proposed a circuit the evaluator does not serve.
+    Rejected,
+    TimedOut,
+    /// The evaluator stopped the session, because the connection closed.
+    Cancelled,
+    /// The garbler reported an error or went away mid-session.
+    PeerAborted,
+    /// The session failed on the evaluator's side, for example on an invalid message.
+    Failed,
+}
+
+impl SessionOutcome {
+    pub fn of<T>(result: &anyhow::Result<T>) -> Self {
+        let Err(error) = result else {
+            return SessionOutcome::Completed;
+        };
+        if error.is::<HandshakeError>() {
+            return SessionOutcome::Rejected;
+        }
+        if let Some(error) = error.downcast_ref::<SessionError>() {
+            return match error {
+                SessionError::Timeout(_) => SessionOutcome::TimedOut,
+                SessionError::Cancelled => SessionOutcome::Cancelled,
+                _ => SessionOutcome::Failed,
+            };
+        }
+        let disconnected = match error.downcast_ref::<FrameError>() {
+            Some(error) => matches!(
+                error,
+                FrameError::Io(_) | FrameError::ConnectionClosed | FrameError::Truncated { .. }
+            ),
+            None => error.is::<std::io::Error>(),
+        };
+        if disconnected || error.is::<PeerError>() {
+            return SessionOutcome::PeerAborted;
+        }
+        SessionOutcome::Failed
+    }
+
+    /// Whether the session was cut short, as opposed to completed or turned down in the handshake.
+    pub fn is_aborted(self) -> bool {
+        !matches!(self, SessionOutcome::Completed | SessionOutcome::Rejected)
+    }
+}
+
+/// Session counters shared by every connection of a server.
+#[derive(Debug, Default)]
+pub struct SessionMetrics {
+    started: AtomicU64,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+    timed_out: AtomicU64,
+    cancelled: AtomicU64,
+    peer_aborted: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl SessionMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&self) {
+        self.started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self, outcome: SessionOutcome) {
+        let counter = match outcome {
+            SessionOutcome::Completed => &self.completed,
+            SessionOutcome::Rejected => &self.rejected,
+            SessionOutcome::TimedOut => &self.timed_out,
+            SessionOutcome::Cancelled => &self.cancelled,
+            SessionOutcome::PeerAborted => &self.peer_aborted,
+            SessionOutcome::Failed => &self.failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        // Sessions are counted as started before they finish, so reading `started` last keeps it
+        // at least as large as the finished counts read before it
+        let completed = self.completed.load(Ordering::Relaxed);
+        let rejected = self.rejected.load(Ordering::Relaxed);
+        let timed_out = self.timed_out.load(Ordering::Relaxed);
+        let cancelled = self.cancelled.load(Ordering::Relaxed);
+        let peer_aborted = self.peer_aborted.load(Ordering::Relaxed);
+        let failed = self.failed.load(Ordering::Relaxed);
+        MetricsSnapshot {
+            started: self.started.load(Ordering::Relaxed),
+            completed,
+            rejected,
+            timed_out,
+            cancelled,
+            peer_aborted,
+            failed,
+        }
+    }
+}
+
+/// The session counters at one point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub started: u64,
+    pub completed: u64,
+    pub rejected: u64,
+    pub timed_out: u64,
+    pub cancelled: u64,
+    pub peer_aborted: u64,
+    pub failed: u64,
+}
+
+impl MetricsSnapshot {
+    /// Sessions that were cut short, whatever the reason.
+    pub fn aborted(&self) -> u64 {
+        self.timed_out + self.cancelled + self.peer_aborted + self.failed
+    }
+
+    /// Sessions that have started but not ended yet.
+    pub fn active(&self) -> u64 {
+        self.started
+            .saturating_sub(self.completed + self.rejected + self.aborted())
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} started, {} active, {} completed, {} rejected, {} aborted ({} timed out, {} cancelled, {} by the peer, {} failed)",
+            self.started,
+            self.active(),
+            self.completed,
+            self.rejected,
+            self.aborted(),
+            self.timed_out,
+            self.cancelled,
+            self.peer_aborted,
+            self.failed
+        )
+    }
+}
@@ -0,0 +1,65 @@
+//! Serving one garbler session on the evaluator.
+
+use anyhow::Result;
+use compute::prelude::*;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::metrics::{SessionMetrics, SessionOutcome};
+
+/// Runs the evaluator's side of a session: the circuit handshake, the protocol and sending the
+/// garbler its part of the output.
+///
+/// The session fails with a `SessionError` when it runs longer than `timeout` or when `cancelled`
+/// completes first, for example because the connection closed. Failures the garbler does not know
+/// about are reported to it in an error frame, and every session is counted in `metrics`.
+pub async fn serve_session<T: Transport>(
+    mut transport: T,
+    registry: &CircuitRegistry,
+    timeout: Duration,
+    cancelled: impl Future<Output = ()>,
+    metrics: &SessionMetrics,
+) -> Result<()> {
+    metrics.start();
+    let result = tokio::select! {
+        result = tokio::time::timeout(timeout, evaluate(&mut transport, registry)) => {
+            result.unwrap_or_else(|_| Err(SessionError::Timeout(timeout).into()))
+        }
+        () = cancelled => Err(SessionError::Cancelled.into()),
+    };
+
+    if let Err(error) = &result {
+        if let Some(report) = report_error(&mut transport, error).await {
+            debug!("Reported to the garbler: {}", report);
+        }
+    }
+    metrics.finish(SessionOutcome::of(&result));
+    result
+}
+
+async fn evaluate<T: Transport>(mut transport: T, registry: &CircuitRegistry) -> Result<()> {
+    // Agree on the circuit before any garbled material is exchanged
+    let circuit = accept_circuit(&mut transport, registry).await?;
+    info!("Circuit accepted: {:?}", hex::encode(circuit.blake3_hash()));
+
+    // The evaluator has no inputs of its own
+    let schema = registry.output_schema(&circuit);
+    let policy = registry.output_policy(&circuit);
+    debug!("Delivering output of type {} as {}", schema, policy);
+    let output = run_evaluator_with_policy(&mut transport, &circuit, &[], &schema, &policy).await?;
+
+    info!("Evaluator processing complete");
+    if let Some(output) = output {
+        info!(
+            "Evaluator learned output of type {}: {}",
+            output.schema(),
+            output
+                .bits()
+                .iter()
+                .map(|bit| if *bit { '1' } else { '0' })
+                .collect::<String>()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use clap::Parser;
+use server::config::*;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::level_filters::LevelFilter;
+
+// Writes `contents` to a config file in a fresh directory under the system temp dir
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("server-config-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("gvm.toml");
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_server_defaults() {
+    let config = ServerConfig::from_options(ServerOptions::default()).unwrap();
+    assert_eq!(config.listen, "127.0.0.1:4433".parse().unwrap());
+    assert!(config.cert.ends_with("server/certs/cert.pem"));
+    assert!(config.cert.is_file());
+    assert!(config.key.is_file());
+    assert_eq!(config.max_sessions, DEFAULT_MAX_SESSIONS);
+    assert_eq!(config.max_circuit_gates, DEFAULT_MAX_CIRCUIT_GATES);
+    assert_eq!(config.quic.idle_timeout, Duration::from_secs(30));
+    assert_eq!(config.log_level, LevelFilter::INFO);
+}
+
+#[test]
+fn test_server_flags() {
+    let cli = ServerCli::try_parse_from([
+        "server",
+        "--listen",
+        "0.0.0.0:5000",
+        "--max-sessions",
+        "4",
+        "--max-circuit-gates",
+        "1000",
+        "--idle-timeout-secs",
+        "5",
+        "--log-level",
+        "debug",
+    ])
+    .unwrap();
+    let config = ServerConfig::from_cli(cli).unwrap();
+    assert_eq!(config.listen, "0.0.0.0:5000".parse().unwrap());
+    assert_eq!(config.max_sessions, 4);
+    assert_eq!(config.max_circuit_gates, 1000);
+    assert_eq!(config.quic.idle_timeout, Duration::from_secs(5));
+    assert_eq!(config.log_level, LevelFilter::DEBUG);
+}
+
+#[test]
+fn test_config_file() {
+    let path = config_file(
+        "file",
+        r#"
+        [server]
+        listen = "127.0.0.1:6000"
+        cert = "tls/cert.pem"
+        max_sessions = 2
+
+        [client]
+        server = "10.0.0.1:6000"
+        server_name = "evaluator"
+        ca_cert = "/etc/gvm/ca.pem"
+        "#,
+    );
+    let dir = path.parent().unwrap().to_path_buf();
+
+    // flags override the file, which overrides the defaults
+    let cli = ServerCli::try_parse_from([
+        "server",
+        "--config",
+        path.to_str().unwrap(),
+        "--max-sessions",
+        "8",
+    ])
+    .unwrap();
+    let config = ServerConfig::from_cli(cli).unwrap();
+    assert_eq!(config.listen, "127.0.0.1:6000".parse().unwrap());
+    assert_eq!(config.cert, dir.join("tls/cert.pem"));
+    assert!(config.key.ends_with("server/certs/key.pem"));
+    assert_eq!(config.max_sessions, 8);
+
+    let cli = ClientCli::try_parse_from(["client", "--config", path.to_str().unwrap()]).unwrap();
+    let config = ClientConfig::from_cli(cli).unwrap();
+    assert_eq!(config.server, "10.0.0.1:6000".parse().unwrap());
+    assert_eq!(config.server_name, "evaluator");
+    assert_eq!(config.ca_cert, PathBuf::from("/etc/gvm/ca.pem"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_example_config() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config.example.toml");
+    let file = ConfigFile::load(&path).unwrap();
+
+    // the example lists the defaults
+    let server = ServerConfig::from_options(file.server).unwrap();
+    assert_eq!(
+        server,
+        ServerConfig::from_options(ServerOptions::default()).unwrap()
+    );
+    let client = ClientConfig::from_options(file.client).unwrap();
+    assert_eq!(
+        client,
+        ClientConfig::from_options(ClientOptions::default()).unwrap()
+    );
+}
+
+#[test]
+fn test_invalid_settings() {
+    let options = ServerOptions {
+        log_level: Some("loud".into()),
+        ..ServerOptions::default()
+    };
+    let error = ServerConfig::from_options(options).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid log level 'loud'");
+
+    let options = ServerOptions {
+        max_sessions: Some(0),
+        ..ServerOptions::default()
+    };
+    assert!(ServerConfig::from_options(options).is_err());
+
+    let options = ServerOptions {
+        idle_timeout_secs: Some(0),
+        ..ServerOptions::default()
+    };
+    assert!(ServerConfig::from_options(options).is_err());
+    let options = ClientOptions {
+        idle_timeout_secs: Some(0),
+        ..ClientOptions::default()
+    };
+    assert!(ClientConfig::from_options(options).is_err());
+
+    let path = config_file("unknown", "[server]\nport = 4433\n");
+    let error = ConfigFile::load(&path).unwrap_err();
+    assert!(format!("{:#}", error).contains("unknown field `port`"));
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    assert!(ServerCli::try_parse_from(["server", "--listen", "localhost"]).is_err());
+}
@@ -0,0 +1,233 @@
+use compute::handshake::HandshakeError;
+use compute::prelude::*;
+use server::circuits::{self, multi_arithmetic};
+use server::metrics::{MetricsSnapshot, SessionMetrics, SessionOutcome};
+use server::session::serve_session;
+use std::future::pending;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn registry() -> CircuitRegistry {
+    let mut registry = CircuitRegistry::new();
+    circuits::register_builtin(&mut registry).unwrap();
+    registry
+}
+
+// The error frame the garbler received instead of the next message
+fn peer_error(error: anyhow::Error) -> SessionError {
+    error
+        .downcast::<PeerError>()
+        .expect("expected an error from the evaluator")
+        .0
+}
+
+#[tokio::test]
+async fn test_completed_session() {
+    let (circuit, inputs, _) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+    let (mut garbler, evaluator) = MemoryTransport::pair();
+    let metrics = SessionMetrics::new();
+    let registry = registry();
+
+    let garbler = async {
+        propose_circuit(
+            &mut garbler,
+            &CircuitProposal::named("multi_arithmetic", &circuit),
+        )
+        .await?;
+        run_garbler(&mut garbler, &circuit, &inputs).await?;
+        recv_output(&mut garbler).await
+    };
+    let evaluator = serve_session(evaluator, &registry, TIMEOUT, pending(), &metrics);
+    let (output, result) = tokio::join!(garbler, evaluator);
+
+    result.unwrap();
+    let result: GarbledUint8 = output.unwrap().decode().unwrap();
+    assert_eq!(u8::from(result), 9);
+    assert_eq!(
+        metrics.snapshot(),
+        MetricsSnapshot {
+            started: 1,
+            completed: 1,
+            ..MetricsSnapshot::default()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_message() {
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let (mut garbler, evaluator) = MemoryTransport::pair();
+    let metrics = SessionMetrics::new();
+    let registry = registry();
+
+    // an output where the garbler's first protocol message belongs
+    let garbler = async {
+        propose_circuit(
+            &mut garbler,
+            &CircuitProposal::named("multi_arithmetic", &circuit),
+        )
+        .await?;
+        garbler
+            .send_message(MessageKind::Output, &[1, 2, 3])
+            .await?;
+        garbler.recv().await
+    };
+    let evaluator = serve_session(evaluator, &registry, TIMEOUT, pending(), &metrics);
+    let (garbler, result) = tokio::join!(garbler, evaluator);
+
+    assert_eq!(SessionOutcome::of(&result), SessionOutcome::Failed);
+    assert_eq!(
+        peer_error(garbler.unwrap_err()),
+        SessionError::InvalidMessage
+    );
+
+    // a protocol message before the handshake
+    let (mut garbler, evaluator) = MemoryTransport::pair();
+    let garbler = async {
+        garbler.send(&[1, 2, 3]).await?;
+        garbler.recv().await
+    };
+    let evaluator = serve_session(evaluator, &registry, TIMEOUT, pending(), &metrics);
+    let (garbler, _) = tokio::join!(garbler, evaluator);
+    assert_eq!(
+        peer_error(garbler.unwrap_err()),
+        SessionError::InvalidMessage
+    );
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.failed, 2);
+    assert_eq!(snapshot.aborted(), 2);
+    assert_eq!(snapshot.active(), 0);
+}
+
+#[tokio::test]
+async fn test_session_timeout() {
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let (mut garbler, evaluator) = MemoryTransport::pair();
+    let metrics = SessionMetrics::new();
+    let registry = registry();
+    let timeout = Duration::from_millis(100);
+
+    // the garbler agrees on a circuit but never starts the protocol
+    let garbler = async {
+        propose_circuit(
+            &mut garbler,
+            &CircuitProposal::named("multi_arithmetic", &circuit),
+        )
+        .await?;
+        garbler.recv().await
+    };
+    let evaluator = serve_session(evaluator, &registry, timeout, pending(), &metrics);
+    let (garbler, result) = tokio::join!(garbler, evaluator);
+
+    assert_eq!(
+        result.unwrap_err().downcast::<SessionError>().unwrap(),
+        SessionError::Timeout(timeout)
+    );
+    assert_eq!(
+        peer_error(garbler.unwrap_err()),
+        SessionError::Timeout(timeout)
+    );
+    assert_eq!(metrics.snapshot().timed_out, 1);
+}
+
+#[tokio::test]
+async fn test_cancelled_session() {
+    let (garbler, evaluator) = MemoryTransport::pair();
+    let metrics = SessionMetrics::new();
+    let registry = registry();
+    let (cancel, cancelled) = tokio::sync::oneshot::channel::<()>();
+
+    let evaluator = tokio::spawn(async move {
+        let cancelled = async {
+            let _ = cancelled.await;
+        };
+        let result = serve_session(evaluator, &registry, TIMEOUT, cancelled, &metrics).await;
+        (result, metrics.snapshot())
+    });
+    cancel.send(()).unwrap();
+    let (result, snapshot) = evaluator.await.unwrap();
+
+    assert_eq!(
+        result.unwrap_err().downcast::<SessionError>().unwrap(),
+        SessionError::Cancelled
+    );
+    assert_eq!(snapshot.cancelled, 1);
+    drop(garbler);
+}
+
+#[tokio::test]
+async fn test_rejected_and_disconnected_sessions() {
+    let (circuit, _, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+    let metrics = SessionMetrics::new();
+    let registry = registry();
+
+    // rejections are answered in the handshake and not counted as aborted
+    let (mut garbler, evaluator) = MemoryTransport::pair();
+    let proposal = CircuitProposal::named("unknown", &circuit);
+    let garbler = propose_circuit(&mut garbler, &proposal);
+    let evaluator = serve_session(evaluator, &registry, TIMEOUT, pending(), &metrics);
+    let (garbler, result) = tokio::join!(garbler, evaluator);
+    assert!(garbler.unwrap_err().is::<HandshakeError>());
+    assert_eq!(SessionOutcome::of(&result), SessionOutcome::Rejected);
+
+    // the garbler goes away mid-session
+    let (garbler, evaluator) = MemoryTransport::pair();
+    drop(garbler);
+    let result = serve_session(evaluator, &registry, TIMEOUT, pending(), &metrics).await;
+    assert!(result.is_err());
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.rejected, 1);
+    assert_eq!(snapshot.peer_aborted, 1);
+    assert_eq!(snapshot.aborted(), 1);
+    assert_eq!(snapshot.started, 2);
+}
+
+#[tokio::test]
+async fn test_session_with_policy() {
+    let (circuit, inputs, _) = multi_arithmetic(6_u8, 7_u8, 1_u8, 2_u8);
+    let schema = OutputSchema::Tuple(vec![OutputSchema::Uint(4), OutputSchema::Uint(4)]);
+    let policy = OutputPolicy::PerElement(vec![Recipient::Garbler, Recipient::Evaluator]);
+    let mut registry = registry();
+    registry
+        .set_output_schema("multi_arithmetic", schema.clone())
+        .unwrap();
+    registry
+        .set_output_policy("multi_arithmetic", policy.clone())
+        .unwrap();
+    let (mut garbler, evaluator) = MemoryTransport::pair();
+    let metrics = SessionMetrics::new();
+
+    // the garbler only learns the low nibble of 6 * 7 + 1 - 2 = 0x29
+    let garbler = async {
+        propose_circuit_with_policy(
+            &mut garbler,
+            &CircuitProposal::named("multi_arithmetic", &circuit),
+            &policy,
+        )
+        .await?;
+        run_garbler_with_policy(&mut garbler, &circuit, &inputs, &schema, &policy).await
+    };
+    let evaluator = serve_session(evaluator, &registry, TIMEOUT, pending(), &metrics);
+    let (output, result) = tokio::join!(garbler, evaluator);
+
+    result.unwrap();
+    let low: GarbledUint4 = output.unwrap().unwrap().decode().unwrap();
+    assert_eq!(u8::from(low), 9);
+    assert_eq!(metrics.snapshot().completed, 1);
+}
+
+#[test]
+fn test_snapshot_active_does_not_underflow() {
+    // a session finishing between the loads of a snapshot can count as finished but not started
+    let snapshot = MetricsSnapshot {
+        started: 1,
+        completed: 1,
+        failed: 1,
+        ..MetricsSnapshot::default()
+    };
+    assert_eq!(snapshot.active(), 0);
+    assert!(snapshot.to_string().contains("0 active"));
+}